@@ -0,0 +1,7 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_verification_token_idx;
+
+ALTER TABLE users
+DROP COLUMN verified,
+DROP COLUMN verification_token,
+DROP COLUMN token_expires_at;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE users
+ADD COLUMN verified BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN verification_token VARCHAR(255),
+ADD COLUMN token_expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Accounts created before verification existed keep working
+UPDATE users SET verified = TRUE;
+
+CREATE INDEX users_verification_token_idx ON users (verification_token);
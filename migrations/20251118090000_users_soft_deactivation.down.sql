@@ -0,0 +1,12 @@
+-- Add down migration script here
+ALTER TABLE truck_loads
+DROP CONSTRAINT truck_loads_userid_fkey,
+ADD CONSTRAINT truck_loads_userid_fkey FOREIGN KEY (userid) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE deliveries
+DROP CONSTRAINT deliveries_userid_fkey,
+ADD CONSTRAINT deliveries_userid_fkey FOREIGN KEY (userid) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE users
+DROP COLUMN is_active,
+DROP COLUMN deactivated_at;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+ALTER TABLE users
+ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN deactivated_at TIMESTAMP WITH TIME ZONE;
+
+-- Users are deactivated, never deleted: keep their history
+ALTER TABLE deliveries
+DROP CONSTRAINT deliveries_userid_fkey,
+ADD CONSTRAINT deliveries_userid_fkey FOREIGN KEY (userid) REFERENCES users(id) ON DELETE RESTRICT;
+
+ALTER TABLE truck_loads
+DROP CONSTRAINT truck_loads_userid_fkey,
+ADD CONSTRAINT truck_loads_userid_fkey FOREIGN KEY (userid) REFERENCES users(id) ON DELETE RESTRICT;
@@ -0,0 +1,17 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS product_prices;
+
+ALTER TABLE delivery_product
+DROP CONSTRAINT delivery_product_productid_fkey,
+ADD CONSTRAINT delivery_product_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE CASCADE;
+
+ALTER TABLE truck_load_products
+DROP CONSTRAINT truck_load_products_productid_fkey,
+ADD CONSTRAINT truck_load_products_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE CASCADE;
+
+ALTER TABLE sales_product
+DROP CONSTRAINT sales_product_productid_fkey,
+ADD CONSTRAINT sales_product_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE CASCADE;
+
+ALTER TABLE products
+DROP COLUMN archived_at;
@@ -0,0 +1,31 @@
+-- Add up migration script here
+-- Archived products stay in the database so sales, loads and deliveries keep their history
+ALTER TABLE products
+ADD COLUMN archived_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE sales_product
+DROP CONSTRAINT sales_product_productid_fkey,
+ADD CONSTRAINT sales_product_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE RESTRICT;
+
+ALTER TABLE truck_load_products
+DROP CONSTRAINT truck_load_products_productid_fkey,
+ADD CONSTRAINT truck_load_products_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE RESTRICT;
+
+ALTER TABLE delivery_product
+DROP CONSTRAINT delivery_product_productid_fkey,
+ADD CONSTRAINT delivery_product_productid_fkey FOREIGN KEY (productid) REFERENCES products(id) ON DELETE RESTRICT;
+
+-- A price applies from effective_from until the next row for the same product
+CREATE TABLE product_prices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+    price DOUBLE PRECISION NOT NULL CHECK (price >= 0),
+    effective_from DATE NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (product_id, effective_from)
+);
+
+-- Existing prices have applied since the beginning
+INSERT INTO product_prices (product_id, price, effective_from)
+SELECT id, price, DATE '1970-01-01' FROM products;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE sales_product
+DROP COLUMN unit_price,
+DROP COLUMN line_total,
+DROP COLUMN commission_rate;
@@ -0,0 +1,28 @@
+-- Add up migration script here
+-- Each sale line keeps the price and commission it was sold at
+ALTER TABLE sales_product
+ADD COLUMN unit_price DOUBLE PRECISION,
+ADD COLUMN line_total DOUBLE PRECISION,
+ADD COLUMN commission_rate DOUBLE PRECISION;
+
+-- Backfill from the price in force on each sale date and the current commission
+UPDATE sales_product sp
+SET unit_price = COALESCE(
+        (
+            SELECT pp.price FROM product_prices pp
+            WHERE pp.product_id = sp.productid AND pp.effective_from <= s.date
+            ORDER BY pp.effective_from DESC
+            LIMIT 1
+        ),
+        p.price
+    ),
+    commission_rate = COALESCE(p.commission, 0)
+FROM sales s, products p
+WHERE s.salesid = sp.salesid AND p.id = sp.productid;
+
+UPDATE sales_product SET line_total = quantity * unit_price;
+
+ALTER TABLE sales_product
+ALTER COLUMN unit_price SET NOT NULL,
+ALTER COLUMN line_total SET NOT NULL,
+ALTER COLUMN commission_rate SET NOT NULL;
@@ -0,0 +1,28 @@
+-- Add down migration script here
+ALTER TABLE products
+    ALTER COLUMN price TYPE DOUBLE PRECISION USING price::DOUBLE PRECISION,
+    ALTER COLUMN commission TYPE DOUBLE PRECISION USING commission::DOUBLE PRECISION;
+
+ALTER TABLE product_prices
+    ALTER COLUMN price TYPE DOUBLE PRECISION USING price::DOUBLE PRECISION;
+
+ALTER TABLE sales
+    ALTER COLUMN total_amount TYPE DOUBLE PRECISION USING total_amount::DOUBLE PRECISION,
+    ALTER COLUMN paid_amount TYPE DOUBLE PRECISION USING paid_amount::DOUBLE PRECISION;
+
+ALTER TABLE sales_product
+    ALTER COLUMN unit_price TYPE DOUBLE PRECISION USING unit_price::DOUBLE PRECISION,
+    ALTER COLUMN line_total TYPE DOUBLE PRECISION USING line_total::DOUBLE PRECISION,
+    ALTER COLUMN commission_rate TYPE DOUBLE PRECISION USING commission_rate::DOUBLE PRECISION;
+
+ALTER TABLE payment
+    ALTER COLUMN amount TYPE DOUBLE PRECISION USING amount::DOUBLE PRECISION;
+
+ALTER TABLE allowance
+    ALTER COLUMN amount TYPE DOUBLE PRECISION USING amount::DOUBLE PRECISION;
+
+ALTER TABLE truck_allowance
+    ALTER COLUMN amount TYPE DOUBLE PRECISION USING amount::DOUBLE PRECISION;
+
+ALTER TABLE trucks
+    ALTER COLUMN max_allowance TYPE DOUBLE PRECISION USING max_allowance::DOUBLE PRECISION;
@@ -0,0 +1,29 @@
+-- Add up migration script here
+-- Money is stored as exact decimals, rounded half away from zero to cents
+ALTER TABLE products
+    ALTER COLUMN price TYPE NUMERIC(12, 2) USING ROUND(price::NUMERIC, 2),
+    ALTER COLUMN commission TYPE NUMERIC(12, 2) USING ROUND(commission::NUMERIC, 2);
+
+ALTER TABLE product_prices
+    ALTER COLUMN price TYPE NUMERIC(12, 2) USING ROUND(price::NUMERIC, 2);
+
+ALTER TABLE sales
+    ALTER COLUMN total_amount TYPE NUMERIC(12, 2) USING ROUND(total_amount::NUMERIC, 2),
+    ALTER COLUMN paid_amount TYPE NUMERIC(12, 2) USING ROUND(paid_amount::NUMERIC, 2);
+
+ALTER TABLE sales_product
+    ALTER COLUMN unit_price TYPE NUMERIC(12, 2) USING ROUND(unit_price::NUMERIC, 2),
+    ALTER COLUMN line_total TYPE NUMERIC(12, 2) USING ROUND(line_total::NUMERIC, 2),
+    ALTER COLUMN commission_rate TYPE NUMERIC(12, 2) USING ROUND(commission_rate::NUMERIC, 2);
+
+ALTER TABLE payment
+    ALTER COLUMN amount TYPE NUMERIC(12, 2) USING ROUND(amount::NUMERIC, 2);
+
+ALTER TABLE allowance
+    ALTER COLUMN amount TYPE NUMERIC(12, 2) USING ROUND(amount::NUMERIC, 2);
+
+ALTER TABLE truck_allowance
+    ALTER COLUMN amount TYPE NUMERIC(12, 2) USING ROUND(amount::NUMERIC, 2);
+
+ALTER TABLE trucks
+    ALTER COLUMN max_allowance TYPE NUMERIC(12, 2) USING ROUND(max_allowance::NUMERIC, 2);
@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE products
+DROP COLUMN category_id,
+DROP COLUMN sku,
+DROP COLUMN barcode,
+DROP COLUMN pack_size,
+DROP COLUMN base_unit;
+
+DROP TABLE IF EXISTS product_categories;
@@ -0,0 +1,27 @@
+-- Add up migration script here
+CREATE TABLE product_categories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- pack_size is how much of base_unit one unit_type holds (e.g. a 500 ml bottle)
+ALTER TABLE products
+ADD COLUMN category_id UUID REFERENCES product_categories(id) ON DELETE RESTRICT,
+ADD COLUMN sku VARCHAR(64),
+ADD COLUMN barcode VARCHAR(14),
+ADD COLUMN pack_size NUMERIC(12, 3) NOT NULL DEFAULT 1 CHECK (pack_size > 0),
+ADD COLUMN base_unit VARCHAR(20);
+
+-- Existing products get a SKU derived from their id until someone renames it
+UPDATE products
+SET sku = 'P-' || UPPER(SUBSTRING(REPLACE(id::TEXT, '-', '') FROM 1 FOR 12)),
+    base_unit = unit_type;
+
+ALTER TABLE products
+ALTER COLUMN sku SET NOT NULL,
+ALTER COLUMN base_unit SET NOT NULL,
+ADD CONSTRAINT products_sku_key UNIQUE (sku),
+ADD CONSTRAINT products_barcode_key UNIQUE (barcode);
+
+CREATE INDEX products_category_id_idx ON products (category_id);
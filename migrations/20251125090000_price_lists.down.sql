@@ -0,0 +1,11 @@
+-- Add down migration script here
+ALTER TABLE sales_product
+DROP COLUMN price_list_id;
+
+DROP TABLE IF EXISTS price_list_items;
+DROP TABLE IF EXISTS price_lists;
+
+ALTER TABLE shops
+DROP COLUMN group_id;
+
+DROP TABLE IF EXISTS shop_groups;
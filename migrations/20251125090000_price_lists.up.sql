@@ -0,0 +1,41 @@
+-- Add up migration script here
+CREATE TABLE shop_groups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+ALTER TABLE shops
+ADD COLUMN group_id UUID REFERENCES shop_groups(id) ON DELETE SET NULL;
+
+-- A price list applies to one shop, one shop group, or (with neither) every shop.
+-- Missing validity dates leave that end open.
+CREATE TABLE price_lists (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    shop_id UUID REFERENCES shops(shopid) ON DELETE RESTRICT,
+    shop_group_id UUID REFERENCES shop_groups(id) ON DELETE RESTRICT,
+    valid_from DATE,
+    valid_to DATE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CHECK (shop_id IS NULL OR shop_group_id IS NULL),
+    CHECK (valid_from IS NULL OR valid_to IS NULL OR valid_to >= valid_from)
+);
+
+-- min_quantity > 1 makes the row a quantity break
+CREATE TABLE price_list_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    price_list_id UUID NOT NULL REFERENCES price_lists(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+    min_quantity INT NOT NULL DEFAULT 1 CHECK (min_quantity >= 1),
+    price NUMERIC(12, 2) NOT NULL CHECK (price >= 0),
+    UNIQUE (price_list_id, product_id, min_quantity)
+);
+
+CREATE INDEX price_list_items_product_id_idx ON price_list_items (product_id);
+
+-- Which price list (if any) priced each sale line
+ALTER TABLE sales_product
+ADD COLUMN price_list_id UUID REFERENCES price_lists(id) ON DELETE RESTRICT;
@@ -0,0 +1,12 @@
+-- Add down migration script here
+ALTER TABLE sales
+DROP COLUMN net_amount,
+DROP COLUMN tax_amount;
+
+ALTER TABLE sales_product
+DROP COLUMN tax_rate,
+DROP COLUMN net_amount,
+DROP COLUMN tax_amount;
+
+ALTER TABLE products
+DROP COLUMN tax_rate;
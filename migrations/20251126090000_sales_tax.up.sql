@@ -0,0 +1,24 @@
+-- Add up migration script here
+-- VAT rate in percent; 0 for zero-rated goods such as fresh milk
+ALTER TABLE products
+ADD COLUMN tax_rate NUMERIC(5, 2) NOT NULL DEFAULT 0 CHECK (tax_rate >= 0 AND tax_rate <= 100);
+
+-- line_total and total_amount stay the gross amounts the shop pays
+ALTER TABLE sales_product
+ADD COLUMN tax_rate NUMERIC(5, 2) NOT NULL DEFAULT 0,
+ADD COLUMN net_amount NUMERIC(12, 2),
+ADD COLUMN tax_amount NUMERIC(12, 2) NOT NULL DEFAULT 0;
+
+ALTER TABLE sales
+ADD COLUMN net_amount NUMERIC(12, 2),
+ADD COLUMN tax_amount NUMERIC(12, 2) NOT NULL DEFAULT 0;
+
+-- Earlier sales were untaxed
+UPDATE sales_product SET net_amount = line_total;
+UPDATE sales SET net_amount = COALESCE(total_amount, 0);
+
+ALTER TABLE sales_product
+ALTER COLUMN net_amount SET NOT NULL;
+
+ALTER TABLE sales
+ALTER COLUMN net_amount SET NOT NULL;
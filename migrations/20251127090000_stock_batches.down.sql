@@ -0,0 +1,14 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS truck_load_batches;
+
+-- Collapse batch lines back to one row per product
+DELETE FROM delivery_product a
+USING delivery_product b
+WHERE a.deliveryid = b.deliveryid AND a.productid = b.productid AND a.id > b.id;
+
+ALTER TABLE delivery_product
+DROP COLUMN batch_id,
+DROP COLUMN id,
+ADD PRIMARY KEY (deliveryid, productid);
+
+DROP TABLE IF EXISTS stock_batches;
@@ -0,0 +1,38 @@
+-- Add up migration script here
+-- Stock per delivered batch; warehouse_stock keeps the per-product total
+CREATE TABLE stock_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    productid UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+    batch_number VARCHAR(64) NOT NULL,
+    production_date DATE,
+    expiry_date DATE,
+    quantity INT NOT NULL DEFAULT 0 CHECK (quantity >= 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (productid, batch_number),
+    CHECK (production_date IS NULL OR expiry_date IS NULL OR expiry_date >= production_date)
+);
+
+CREATE INDEX stock_batches_expiry_date_idx ON stock_batches (expiry_date) WHERE quantity > 0;
+
+-- Stock received before batches were tracked has no known expiry
+INSERT INTO stock_batches (productid, batch_number, quantity)
+SELECT productid, 'LEGACY', SUM(quantity)
+FROM warehouse_stock
+GROUP BY productid
+HAVING SUM(quantity) > 0;
+
+-- A delivery may bring several batches of the same product
+ALTER TABLE delivery_product
+DROP CONSTRAINT delivery_product_pkey,
+ADD COLUMN id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+ADD COLUMN batch_id UUID REFERENCES stock_batches(id) ON DELETE RESTRICT;
+
+-- Which batches each truck load was filled from, and how much came back
+CREATE TABLE truck_load_batches (
+    truckloadid UUID NOT NULL REFERENCES truck_loads(truckloadid) ON DELETE CASCADE,
+    productid UUID NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+    batch_id UUID NOT NULL REFERENCES stock_batches(id) ON DELETE RESTRICT,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    returned_quantity INT NOT NULL DEFAULT 0 CHECK (returned_quantity >= 0 AND returned_quantity <= quantity),
+    PRIMARY KEY (truckloadid, batch_id)
+);
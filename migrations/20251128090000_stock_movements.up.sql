@@ -0,0 +1,56 @@
+-- Add up migration script here
+-- Append-only ledger of every warehouse stock change
+CREATE TABLE stock_movements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    productid UUID NOT NULL REFERENCES products(id),
+    batch_id UUID REFERENCES stock_batches(id),
+    quantity INT NOT NULL CHECK (quantity <> 0),
+    reason VARCHAR(32) NOT NULL
+        CHECK (reason IN ('opening_balance', 'delivery', 'truck_load', 'truck_return', 'adjustment')),
+    deliveryid UUID REFERENCES deliveries(deliveryid),
+    truckloadid UUID REFERENCES truck_loads(truckloadid),
+    adjustment_id UUID,
+    user_id UUID REFERENCES users(id),
+    occurred_on DATE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX stock_movements_productid_occurred_on_idx ON stock_movements (productid, occurred_on);
+CREATE INDEX stock_movements_created_at_idx ON stock_movements (created_at);
+
+CREATE FUNCTION stock_movements_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'stock_movements is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stock_movements_append_only
+BEFORE UPDATE OR DELETE ON stock_movements
+FOR EACH ROW EXECUTE FUNCTION stock_movements_append_only();
+
+-- Rebuild what history we have. Returns weren't dated, so they count on the load date.
+INSERT INTO stock_movements (productid, batch_id, quantity, reason, deliveryid, user_id, occurred_on, created_at)
+SELECT dp.productid, dp.batch_id, dp.quantity, 'delivery', d.deliveryid, d.userid, d.date, d.date::TIMESTAMP WITH TIME ZONE
+FROM delivery_product dp
+JOIN deliveries d ON d.deliveryid = dp.deliveryid;
+
+INSERT INTO stock_movements (productid, quantity, reason, truckloadid, occurred_on, created_at)
+SELECT tlp.productid, -tlp.quantity, 'truck_load', tl.truckloadid, tl.date, COALESCE(tl.created_at, tl.date::TIMESTAMP)
+FROM truck_load_products tlp
+JOIN truck_loads tl ON tl.truckloadid = tlp.truckloadid;
+
+INSERT INTO stock_movements (productid, quantity, reason, truckloadid, occurred_on, created_at)
+SELECT tlp.productid, tlp.remaining_quantity, 'truck_return', tl.truckloadid, tl.date, COALESCE(tl.updated_at, tl.date::TIMESTAMP)
+FROM truck_load_products tlp
+JOIN truck_loads tl ON tl.truckloadid = tlp.truckloadid
+WHERE tlp.remaining_quantity > 0;
+
+-- Whatever history can't explain becomes an opening balance, so the ledger sums to warehouse_stock
+INSERT INTO stock_movements (productid, quantity, reason, occurred_on, created_at)
+SELECT p.id, diff.quantity, 'opening_balance', DATE '1970-01-01', NOW()
+FROM products p
+CROSS JOIN LATERAL (
+    SELECT COALESCE((SELECT SUM(quantity) FROM warehouse_stock WHERE productid = p.id), 0)
+         - COALESCE((SELECT SUM(quantity) FROM stock_movements WHERE productid = p.id), 0) AS quantity
+) diff
+WHERE diff.quantity <> 0;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE stock_movements DROP CONSTRAINT IF EXISTS stock_movements_adjustment_id_fkey;
+DROP TABLE IF EXISTS stock_adjustments;
@@ -0,0 +1,24 @@
+-- Add up migration script here
+-- Manual stock corrections; ones worth more than the approval limit wait for an admin
+CREATE TABLE stock_adjustments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    productid UUID NOT NULL REFERENCES products(id),
+    batch_id UUID REFERENCES stock_batches(id),
+    quantity INT NOT NULL CHECK (quantity <> 0),
+    reason VARCHAR(32) NOT NULL
+        CHECK (reason IN ('spoiled', 'damaged', 'expired', 'sample', 'count_correction')),
+    notes TEXT,
+    value NUMERIC(12,2) NOT NULL CHECK (value >= 0),
+    status VARCHAR(16) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected')),
+    created_by UUID NOT NULL REFERENCES users(id),
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX stock_adjustments_status_idx ON stock_adjustments (status, created_at);
+
+ALTER TABLE stock_movements
+    ADD CONSTRAINT stock_movements_adjustment_id_fkey
+    FOREIGN KEY (adjustment_id) REFERENCES stock_adjustments(id);
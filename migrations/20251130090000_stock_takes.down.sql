@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE stock_adjustments DROP COLUMN IF EXISTS stock_take_id;
+DROP TABLE IF EXISTS stock_take_lines;
+DROP TABLE IF EXISTS stock_takes;
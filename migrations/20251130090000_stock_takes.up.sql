@@ -0,0 +1,34 @@
+-- Add up migration script here
+-- Physical stock counts; posting turns the variances into adjustments
+CREATE TABLE stock_takes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    status VARCHAR(16) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'posted', 'cancelled')),
+    notes TEXT,
+    opened_by UUID NOT NULL REFERENCES users(id),
+    opened_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    closed_by UUID REFERENCES users(id),
+    closed_at TIMESTAMP WITH TIME ZONE
+);
+
+-- One count at a time
+CREATE UNIQUE INDEX stock_takes_one_open_idx ON stock_takes ((true)) WHERE status = 'open';
+
+-- system_quantity is what the books said when the line was counted
+CREATE TABLE stock_take_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    stock_take_id UUID NOT NULL REFERENCES stock_takes(id) ON DELETE CASCADE,
+    productid UUID NOT NULL REFERENCES products(id),
+    batch_id UUID REFERENCES stock_batches(id),
+    system_quantity INT NOT NULL,
+    counted_quantity INT NOT NULL CHECK (counted_quantity >= 0),
+    counted_by UUID NOT NULL REFERENCES users(id),
+    counted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX stock_take_lines_batch_idx
+    ON stock_take_lines (stock_take_id, productid, batch_id) WHERE batch_id IS NOT NULL;
+CREATE UNIQUE INDEX stock_take_lines_product_idx
+    ON stock_take_lines (stock_take_id, productid) WHERE batch_id IS NULL;
+
+ALTER TABLE stock_adjustments ADD COLUMN stock_take_id UUID REFERENCES stock_takes(id);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE products
+    DROP CONSTRAINT IF EXISTS products_target_level_reorder_point_check,
+    DROP COLUMN IF EXISTS target_level,
+    DROP COLUMN IF EXISTS reorder_point;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Reorder when stock falls to reorder_point; order back up to target_level
+ALTER TABLE products
+    ADD COLUMN reorder_point INT CHECK (reorder_point >= 0),
+    ADD COLUMN target_level INT CHECK (target_level > 0),
+    ADD CONSTRAINT products_target_level_reorder_point_check CHECK (target_level >= reorder_point);
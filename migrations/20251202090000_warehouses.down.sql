@@ -0,0 +1,88 @@
+-- Add down migration script here
+-- Everything folds back into one store, so first merge what the single-store
+-- constraints can't hold apart. The movement ledger is corrected too.
+ALTER TABLE stock_movements DISABLE TRIGGER stock_movements_append_only;
+
+-- A transfer's out and in legs cancel once both warehouses are the same store
+DELETE FROM stock_movements WHERE reason IN ('transfer_out', 'transfer_in');
+
+DROP INDEX IF EXISTS stock_movements_warehouse_id_idx;
+ALTER TABLE stock_movements DROP CONSTRAINT IF EXISTS stock_movements_reason_check;
+ALTER TABLE stock_movements ADD CONSTRAINT stock_movements_reason_check
+    CHECK (reason IN ('opening_balance', 'delivery', 'truck_load', 'truck_return', 'adjustment'));
+ALTER TABLE stock_movements DROP COLUMN IF EXISTS transfer_id;
+DROP TABLE IF EXISTS stock_transfer_lines;
+DROP TABLE IF EXISTS stock_transfers;
+
+-- A batch number held in several warehouses becomes one batch, preferring
+-- the copy in the default warehouse, then the oldest
+CREATE TEMPORARY TABLE merged_batches AS
+SELECT id, FIRST_VALUE(id) OVER (
+           PARTITION BY productid, batch_number
+           ORDER BY warehouse_id = '00000000-0000-0000-0000-000000000001' DESC, created_at, id
+       ) AS keep
+FROM stock_batches;
+DELETE FROM merged_batches WHERE id = keep;
+
+UPDATE stock_batches sb SET quantity = sb.quantity + moved.quantity
+FROM (
+    SELECT m.keep, SUM(b.quantity) AS quantity
+    FROM merged_batches m
+    JOIN stock_batches b ON b.id = m.id
+    GROUP BY m.keep
+) moved
+WHERE sb.id = moved.keep;
+
+UPDATE delivery_product t SET batch_id = m.keep FROM merged_batches m WHERE t.batch_id = m.id;
+UPDATE truck_load_batches t SET batch_id = m.keep FROM merged_batches m WHERE t.batch_id = m.id;
+UPDATE stock_adjustments t SET batch_id = m.keep FROM merged_batches m WHERE t.batch_id = m.id;
+UPDATE stock_take_lines t SET batch_id = m.keep FROM merged_batches m WHERE t.batch_id = m.id;
+UPDATE stock_movements t SET batch_id = m.keep FROM merged_batches m WHERE t.batch_id = m.id;
+
+DELETE FROM stock_batches WHERE id IN (SELECT id FROM merged_batches);
+DROP TABLE merged_batches;
+
+ALTER TABLE stock_movements ENABLE TRIGGER stock_movements_append_only;
+
+-- One stock row per product again
+WITH totals AS (
+    SELECT productid, SUM(quantity) AS quantity,
+           (ARRAY_AGG(stockid ORDER BY warehouse_id = '00000000-0000-0000-0000-000000000001' DESC, stockid))[1] AS keep
+    FROM warehouse_stock
+    GROUP BY productid
+    HAVING COUNT(*) > 1
+)
+UPDATE warehouse_stock ws SET quantity = totals.quantity
+FROM totals WHERE ws.stockid = totals.keep;
+
+DELETE FROM warehouse_stock ws
+USING (
+    SELECT productid,
+           (ARRAY_AGG(stockid ORDER BY warehouse_id = '00000000-0000-0000-0000-000000000001' DESC, stockid))[1] AS keep
+    FROM warehouse_stock
+    GROUP BY productid
+    HAVING COUNT(*) > 1
+) dupes
+WHERE ws.productid = dupes.productid AND ws.stockid <> dupes.keep;
+
+-- Only one stock take can stay open; later ones are cancelled
+UPDATE stock_takes st SET status = 'cancelled', closed_by = st.opened_by, closed_at = NOW()
+WHERE st.status = 'open'
+  AND st.id <> (SELECT id FROM stock_takes WHERE status = 'open' ORDER BY opened_at, id LIMIT 1);
+
+DROP INDEX IF EXISTS stock_takes_one_open_idx;
+CREATE UNIQUE INDEX stock_takes_one_open_idx ON stock_takes ((true)) WHERE status = 'open';
+
+ALTER TABLE stock_batches DROP CONSTRAINT IF EXISTS stock_batches_warehouse_product_batch_key;
+ALTER TABLE stock_batches ADD CONSTRAINT stock_batches_productid_batch_number_key UNIQUE (productid, batch_number);
+ALTER TABLE warehouse_stock DROP CONSTRAINT IF EXISTS warehouse_stock_warehouse_product_key;
+
+ALTER TABLE stock_takes DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE stock_adjustments DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE stock_movements DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE truck_loads DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE deliveries DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE stock_batches DROP COLUMN IF EXISTS warehouse_id;
+ALTER TABLE warehouse_stock DROP COLUMN IF EXISTS warehouse_id;
+
+DROP TABLE IF EXISTS warehouses;
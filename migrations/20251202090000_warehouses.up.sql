@@ -0,0 +1,97 @@
+-- Add up migration script here
+-- Stock locations: the plant store plus depots. Existing stock is in the plant store.
+CREATE TABLE warehouses (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    address VARCHAR(255),
+    -- Used whenever a request doesn't name a warehouse
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX warehouses_one_default_idx ON warehouses ((true)) WHERE is_default;
+
+INSERT INTO warehouses (id, name, is_default)
+VALUES ('00000000-0000-0000-0000-000000000001', 'Main plant store', TRUE);
+
+-- A constant default fills existing rows without rewriting them (and
+-- without tripping the stock_movements append-only trigger)
+ALTER TABLE warehouse_stock
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE stock_batches
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE deliveries
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE truck_loads
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE stock_movements
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE stock_adjustments
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+ALTER TABLE stock_takes
+    ADD COLUMN warehouse_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES warehouses(id);
+
+ALTER TABLE warehouse_stock ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE stock_batches ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE deliveries ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE truck_loads ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE stock_movements ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE stock_adjustments ALTER COLUMN warehouse_id DROP DEFAULT;
+ALTER TABLE stock_takes ALTER COLUMN warehouse_id DROP DEFAULT;
+
+-- One stock row per product and warehouse; fold any duplicates first
+WITH totals AS (
+    SELECT productid, SUM(quantity) AS quantity, (ARRAY_AGG(stockid))[1] AS keep
+    FROM warehouse_stock
+    GROUP BY productid
+    HAVING COUNT(*) > 1
+)
+UPDATE warehouse_stock ws SET quantity = totals.quantity
+FROM totals WHERE ws.stockid = totals.keep;
+
+DELETE FROM warehouse_stock ws
+USING (
+    SELECT productid, (ARRAY_AGG(stockid))[1] AS keep
+    FROM warehouse_stock
+    GROUP BY productid
+    HAVING COUNT(*) > 1
+) dupes
+WHERE ws.productid = dupes.productid AND ws.stockid <> dupes.keep;
+
+ALTER TABLE warehouse_stock ADD CONSTRAINT warehouse_stock_warehouse_product_key UNIQUE (warehouse_id, productid);
+
+-- A transferred batch keeps its number in every warehouse it's in
+ALTER TABLE stock_batches DROP CONSTRAINT stock_batches_productid_batch_number_key;
+ALTER TABLE stock_batches ADD CONSTRAINT stock_batches_warehouse_product_batch_key UNIQUE (warehouse_id, productid, batch_number);
+
+-- One open stock take per warehouse
+DROP INDEX stock_takes_one_open_idx;
+CREATE UNIQUE INDEX stock_takes_one_open_idx ON stock_takes (warehouse_id) WHERE status = 'open';
+
+-- Moves between warehouses; each line is one source batch
+CREATE TABLE stock_transfers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    from_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    to_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    date DATE NOT NULL,
+    notes TEXT,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CHECK (from_warehouse_id <> to_warehouse_id)
+);
+
+CREATE TABLE stock_transfer_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transfer_id UUID NOT NULL REFERENCES stock_transfers(id) ON DELETE CASCADE,
+    productid UUID NOT NULL REFERENCES products(id),
+    from_batch_id UUID NOT NULL REFERENCES stock_batches(id),
+    to_batch_id UUID NOT NULL REFERENCES stock_batches(id),
+    quantity INT NOT NULL CHECK (quantity > 0)
+);
+
+ALTER TABLE stock_movements ADD COLUMN transfer_id UUID REFERENCES stock_transfers(id);
+ALTER TABLE stock_movements DROP CONSTRAINT stock_movements_reason_check;
+ALTER TABLE stock_movements ADD CONSTRAINT stock_movements_reason_check
+    CHECK (reason IN ('opening_balance', 'delivery', 'truck_load', 'truck_return', 'adjustment', 'transfer_out', 'transfer_in'));
+
+CREATE INDEX stock_movements_warehouse_id_idx ON stock_movements (warehouse_id, occurred_on);
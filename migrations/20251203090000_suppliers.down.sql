@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS deliveries_supplier_id_idx;
+ALTER TABLE deliveries DROP COLUMN IF EXISTS supplier_id;
+DROP TABLE IF EXISTS suppliers;
@@ -0,0 +1,17 @@
+-- Add up migration script here
+-- Who supplies what we receive: farmers, co-ops and packaging vendors
+CREATE TABLE suppliers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(150) NOT NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('farmer', 'cooperative', 'vendor')),
+    contact_number VARCHAR(30),
+    address VARCHAR(255),
+    -- Inactive suppliers stay on their old deliveries but can't get new ones
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Deliveries recorded before suppliers were tracked have no supplier
+ALTER TABLE deliveries ADD COLUMN supplier_id UUID REFERENCES suppliers(id);
+
+CREATE INDEX deliveries_supplier_id_idx ON deliveries (supplier_id, date);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS milk_collections;
+DROP TABLE IF EXISTS milk_rate_chart_entries;
+DROP TABLE IF EXISTS milk_rate_charts;
@@ -0,0 +1,45 @@
+-- Add up migration script here
+-- Farmers are paid per litre from a rate chart of fat/SNF slabs. The chart in
+-- force on a day is the one that started most recently.
+CREATE TABLE milk_rate_charts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    valid_from DATE NOT NULL UNIQUE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Milk testing at or above both minimums earns the rate
+CREATE TABLE milk_rate_chart_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chart_id UUID NOT NULL REFERENCES milk_rate_charts(id) ON DELETE CASCADE,
+    min_fat NUMERIC(5, 2) NOT NULL CHECK (min_fat >= 0 AND min_fat <= 100),
+    min_snf NUMERIC(5, 2) NOT NULL CHECK (min_snf >= 0 AND min_snf <= 100),
+    rate NUMERIC(10, 2) NOT NULL CHECK (rate >= 0),
+    UNIQUE (chart_id, min_fat, min_snf)
+);
+
+CREATE TABLE milk_collections (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    supplier_id UUID NOT NULL REFERENCES suppliers(id),
+    collected_on DATE NOT NULL,
+    litres NUMERIC(10, 2) NOT NULL CHECK (litres > 0),
+    fat NUMERIC(5, 2) NOT NULL CHECK (fat >= 0 AND fat <= 100),
+    snf NUMERIC(5, 2) NOT NULL CHECK (snf >= 0 AND snf <= 100),
+    -- Degrees Celsius at collection
+    temperature NUMERIC(4, 1) NOT NULL,
+    adulteration_result VARCHAR(20) NOT NULL CHECK (adulteration_result IN ('negative', 'positive', 'not_tested')),
+    rejected BOOLEAN NOT NULL DEFAULT FALSE,
+    rejection_reason TEXT,
+    -- Rejected milk isn't paid for, so it has no rate
+    rate_chart_id UUID REFERENCES milk_rate_charts(id),
+    rate_per_litre NUMERIC(10, 2),
+    amount NUMERIC(12, 2),
+    recorded_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CHECK (rejected = (rate_per_litre IS NULL)),
+    CHECK ((rate_per_litre IS NULL) = (amount IS NULL))
+);
+
+CREATE INDEX milk_collections_supplier_id_idx ON milk_collections (supplier_id, collected_on);
+CREATE INDEX milk_collections_collected_on_idx ON milk_collections (collected_on);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE stock_adjustments DROP COLUMN IF EXISTS counted_on;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- Day a stock-take correction was counted; its ledger movement is dated then,
+-- so point-in-time stock matches the count. Manual adjustments leave it empty.
+ALTER TABLE stock_adjustments ADD COLUMN counted_on DATE;
+
+-- A correction comes from its batch line, or the product line split by FEFO
+UPDATE stock_adjustments a SET counted_on = (
+    SELECT MAX(l.counted_at)::DATE
+    FROM stock_take_lines l
+    WHERE l.stock_take_id = a.stock_take_id
+      AND l.productid = a.productid
+      AND (l.batch_id = a.batch_id OR l.batch_id IS NULL)
+)
+WHERE a.stock_take_id IS NOT NULL;
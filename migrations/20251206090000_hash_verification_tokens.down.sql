@@ -0,0 +1,5 @@
+-- Add down migration script here
+-- Digests can't be turned back into tokens, so outstanding verification links
+-- stop working either way; clear them rather than leave unmatchable values.
+UPDATE users SET verification_token = NULL, token_expires_at = NULL
+WHERE verification_token IS NOT NULL;
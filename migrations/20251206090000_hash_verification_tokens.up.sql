@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Verification tokens are stored as their SHA-256 digest like reset and
+-- refresh tokens; outstanding links keep working once hashed.
+UPDATE users
+SET verification_token = encode(sha256(convert_to(verification_token, 'UTF8')), 'hex')
+WHERE verification_token IS NOT NULL;
@@ -1,8 +1,19 @@
+use rust_decimal::Decimal;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
     pub jwt_maxage: i64,
+    pub refresh_token_maxage: i64,
+    pub invite_maxage: i64,
+    pub open_registration: bool,
+    pub login_max_attempts: i32,
+    pub login_ip_max_attempts: i32,
+    pub login_lockout_seconds: i64,
+    pub require_admin_2fa: bool,
+    pub prices_include_tax: bool,
+    pub stock_adjustment_approval_limit: Decimal,
     pub port: u16,
 }
 
@@ -11,12 +22,39 @@ impl Config {
     pub fn init() -> Config {
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let jwt_secret = std::env::var("JWT_SECRET_KEY").expect("JWT_SECRET_KEY must be set");
+        // Seconds an access token stays valid
         let jwt_maxage = std::env::var("JWT_MAXAGE").expect("JWT_MAXAGE must be set");
+        // Seconds a refresh token (login session) stays valid (default: 30 days)
+        let refresh_token_maxage = std::env::var("REFRESH_TOKEN_MAXAGE").unwrap_or_else(|_| "2592000".to_string());
+        // Seconds an invitation link stays valid (default: 3 days)
+        let invite_maxage = std::env::var("INVITE_MAXAGE").unwrap_or_else(|_| "259200".to_string());
+        // When enabled, anyone may self-register, but only as a driver
+        let open_registration = std::env::var("OPEN_REGISTRATION").unwrap_or_default() == "true";
+        // Failed logins allowed per account / per client IP before locking
+        let login_max_attempts = std::env::var("LOGIN_MAX_ATTEMPTS").unwrap_or_else(|_| "5".to_string());
+        let login_ip_max_attempts = std::env::var("LOGIN_IP_MAX_ATTEMPTS").unwrap_or_else(|_| "20".to_string());
+        // Seconds of the first lockout; doubles with every further failure
+        let login_lockout_seconds = std::env::var("LOGIN_LOCKOUT_SECONDS").unwrap_or_else(|_| "60".to_string());
+        // When enabled, admins must enrol in TOTP before they can log in
+        let require_admin_2fa = std::env::var("REQUIRE_ADMIN_2FA").unwrap_or_default() == "true";
+        // Whether product and price list prices already include VAT (default: true)
+        let prices_include_tax = std::env::var("PRICES_INCLUDE_TAX").unwrap_or_default() != "false";
+        // Stock adjustments worth more than this need an admin's approval (default: 100.00)
+        let stock_adjustment_approval_limit = std::env::var("STOCK_ADJUSTMENT_APPROVAL_LIMIT").unwrap_or_else(|_| "100".to_string());
 
         Config {
             database_url,
             jwt_secret,
             jwt_maxage: jwt_maxage.parse::<i64>().unwrap(),
+            refresh_token_maxage: refresh_token_maxage.parse::<i64>().unwrap(),
+            invite_maxage: invite_maxage.parse::<i64>().unwrap(),
+            open_registration,
+            login_max_attempts: login_max_attempts.parse::<i32>().unwrap(),
+            login_ip_max_attempts: login_ip_max_attempts.parse::<i32>().unwrap(),
+            login_lockout_seconds: login_lockout_seconds.parse::<i64>().unwrap(),
+            require_admin_2fa,
+            prices_include_tax,
+            stock_adjustment_approval_limit: stock_adjustment_approval_limit.parse::<Decimal>().unwrap(),
             port: 8000,
         }
     }
@@ -1,9 +1,10 @@
 use async_trait::async_trait;
-use chrono::{ NaiveDate};
+use chrono::{DateTime, NaiveDate, Utc};
 use sqlx::{Pool, Postgres, Transaction};
+use rust_decimal::Decimal;
 use uuid::Uuid;
 
-use crate::models::{User, UserRole, Product, TruckLoad, Sale, Payment, Allowance, TruckAllowance, Truck, Shop};
+use crate::models::{User, UserRole, UserTwoFactor, Invite, Session, Product, ProductCategory, ProductPrice, TruckLoad, Sale, Payment, Allowance, TruckAllowance, Truck, Shop, ShopGroup, PriceList, PriceListItem, PriceListOffer, StockBatch, StockMovementReason, StockAdjustment, AdjustmentReason, AdjustmentStatus, StockTake, StockTakeStatus, Warehouse, StockTransfer, Supplier, SupplierKind, MilkRateChart, MilkRateChartEntry, MilkCollection, AdulterationResult};
 
 use crate::models::{Delivery};
 use crate::dtos::DailyProductSaleResponse;
@@ -11,6 +12,14 @@ use crate::dtos::AllowanceDistributionResponse;
 use crate::dtos::TruckAllowanceInfo;
 use crate::dtos::PendingPaymentResponse;
 use crate::dtos::SaleDto;
+use crate::dtos::TaxRateSummary;
+use crate::dtos::TruckLoadProductDto;
+use crate::dtos::{AcceptInviteDto, RegisterUserDto, UpdateUserDto};
+use crate::dtos::{CreateProductDto, UpdateProductDto, CreatePriceListDto, PriceListItemDto, SaleLineDto};
+use crate::dtos::{CreateSupplierDto, UpdateSupplierDto, SupplierProductTotalDto, SupplierTotalDto};
+use crate::dtos::{CreateMilkCollectionDto, CreateMilkRateChartDto, MilkCollectionQuery};
+use crate::dtos::{CreateStockAdjustmentDto, CreateStockTransferDto, DeliveryProductDto, ExpiringBatchDto, StockCountDto, StockLevelDto, StockMovementDto, StockMovementQuery, StockTakeLineDto, StockTransferLineResponse, ReorderSuggestionDto, WarehouseStockDto};
+use crate::utils::{approval, fefo, milk_rate, money, pricing, reorder, tax};
 
 use sqlx::Error as SqlxError;
 
@@ -46,21 +55,62 @@ pub trait UserExt {
         limit: usize,
     ) -> Result<Vec<User>, sqlx::Error>;
 
+    // `password` and `verification_token_hash` are hashes; the rest of the
+    // profile comes from the registration
     async fn save_user(
         &self,
-        first_name: &str,
-        last_name: &str,
-        email: &str,
+        registration: &RegisterUserDto,
         password: &str,
         role: UserRole,
-        address: Option<&str>,
-        city: Option<&str>,
-        district: Option<&str>,
-        contact_number: Option<&str>,
+        verification_token_hash: &str,
+        token_expires_at: DateTime<Utc>,
     ) -> Result<User, sqlx::Error>;
 
     async fn get_user_count(&self) -> Result<i64, sqlx::Error>;
 
+    async fn verify_email_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn update_user(
+        &self,
+        user_id: Uuid,
+        changes: &UpdateUserDto,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn update_user_role(
+        &self,
+        user_id: Uuid,
+        role: UserRole,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn update_user_password(
+        &self,
+        user_id: Uuid,
+        password: &str,
+        keep_session_id: Uuid,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn set_user_active(
+        &self,
+        user_id: Uuid,
+        is_active: bool,
+    ) -> Result<Option<User>, sqlx::Error>;
+
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        password: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error>;
+
     
 }
 
@@ -91,7 +141,8 @@ impl UserExt for DBClient {
                 User,
                 r#"
                 SELECT id, first_name, last_name, email, password, role as "role: UserRole",
-                       address, city, district, contact_number, created_at, updated_at
+                       address, city, district, contact_number, verified, verification_token,
+                       token_expires_at, is_active, deactivated_at, created_at, updated_at
                 FROM users
                 WHERE id = $1
                 "#,
@@ -106,7 +157,8 @@ impl UserExt for DBClient {
                 User,
                 r#"
                 SELECT id, first_name, last_name, email, password, role as "role: UserRole",
-                       address, city, district, contact_number, created_at, updated_at
+                       address, city, district, contact_number, verified, verification_token,
+                       token_expires_at, is_active, deactivated_at, created_at, updated_at
                 FROM users
                 WHERE first_name = $1
                 "#,
@@ -121,7 +173,8 @@ impl UserExt for DBClient {
                 User,
                 r#"
                 SELECT id, first_name, last_name, email, password, role as "role: UserRole",
-                       address, city, district, contact_number, created_at, updated_at
+                       address, city, district, contact_number, verified, verification_token,
+                       token_expires_at, is_active, deactivated_at, created_at, updated_at
                 FROM users
                 WHERE email = $1
                 "#,
@@ -145,7 +198,8 @@ impl UserExt for DBClient {
             User,
             r#"
             SELECT id, first_name, last_name, email, password, role as "role: UserRole",
-                   address, city, district, contact_number, created_at, updated_at
+                   address, city, district, contact_number, verified, verification_token,
+                   token_expires_at, is_active, deactivated_at, created_at, updated_at
             FROM users
             ORDER BY created_at DESC
             LIMIT $1 OFFSET $2
@@ -161,36 +215,36 @@ impl UserExt for DBClient {
 
     async fn save_user(
         &self,
-        first_name: &str,
-        last_name: &str,
-        email: &str,
+        registration: &RegisterUserDto,
         password: &str,
         role: UserRole,
-        address: Option<&str>,
-        city: Option<&str>,
-        district: Option<&str>,
-        contact_number: Option<&str>,
+        verification_token_hash: &str,
+        token_expires_at: DateTime<Utc>,
     ) -> Result<User, sqlx::Error> {
         let user = sqlx::query_as!(
             User,
             r#"
             INSERT INTO users (
-                first_name, last_name, email, password, role, address, city, district, contact_number
+                first_name, last_name, email, password, role, address, city, district, contact_number,
+                verification_token, token_expires_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING
                 id, first_name, last_name, email, password, role as "role: UserRole",
-                address, city, district, contact_number, created_at, updated_at
+                address, city, district, contact_number, verified, verification_token,
+                token_expires_at, is_active, deactivated_at, created_at, updated_at
             "#,
-            first_name,
-            last_name,
-            email,
+            registration.first_name,
+            registration.last_name,
+            registration.email,
             password,
             role as UserRole,
-            address,
-            city,
-            district,
-            contact_number
+            registration.address,
+            registration.city,
+            registration.district,
+            registration.contact_number,
+            verification_token_hash,
+            token_expires_at
         )
         .fetch_one(&self.pool)
         .await?;
@@ -205,876 +259,3798 @@ impl UserExt for DBClient {
         Ok(count.unwrap_or(0))
     }
 
-   
-}
-
-#[async_trait]
-pub trait ProductExt {
-    async fn create_product(
-        &self,
-        name: &str,
-        price: f64,
-        unit_type: &str,
-        commission: Option<f64>,
-    ) -> Result<Product, sqlx::Error>;
-
-    async fn get_product_by_id(&self, product_id: Uuid) -> Result<Option<Product>, sqlx::Error>;
-    async fn get_all_products(&self) -> Result<Vec<Product>, sqlx::Error>;
-}
-
-#[async_trait]
-impl ProductExt for DBClient {
-    async fn create_product(
+    async fn verify_email_token(
         &self,
-        name: &str,
-        price: f64,
-        unit_type: &str,
-        commission: Option<f64>,
-    ) -> Result<Product, sqlx::Error> {
-        let product = sqlx::query_as::<_, Product>(
-            "INSERT INTO products (name, price, unit_type, commission, created_at, updated_at)
-             VALUES ($1,$2,$3,$4,NOW(),NOW()) RETURNING *"
+        token_hash: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET verified = TRUE, verification_token = NULL, token_expires_at = NULL, updated_at = NOW()
+            WHERE verification_token = $1 AND token_expires_at > NOW()
+            RETURNING
+                id, first_name, last_name, email, password, role as "role: UserRole",
+                address, city, district, contact_number, verified, verification_token,
+                token_expires_at, is_active, deactivated_at, created_at, updated_at
+            "#,
+            token_hash
         )
-        .bind(name)
-        .bind(price)
-        .bind(unit_type)
-        .bind(commission.unwrap_or(0.0))
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
-        Ok(product)
-    }
 
-    async fn get_product_by_id(&self, product_id: Uuid) -> Result<Option<Product>, sqlx::Error> {
-        let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
-            .bind(product_id)
-            .fetch_optional(&self.pool)
-            .await?;
-        Ok(product)
+        Ok(user)
     }
 
-    async fn get_all_products(&self) -> Result<Vec<Product>, sqlx::Error> {
-    let products = sqlx::query_as::<_, Product>("SELECT * FROM products")
-        .fetch_all(&self.pool)
+    async fn create_password_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        // Only the most recently issued link stays usable
+        sqlx::query!(
+            "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+            user_id
+        )
+        .execute(&mut *tx)
         .await?;
-    Ok(products)
-}
 
-}
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            user_id,
+            token_hash,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
 
+        tx.commit().await?;
+        Ok(())
+    }
 
-#[async_trait]
-pub trait DeliveryExt {
-    async fn create_delivery(
+    async fn reset_password(
         &self,
-        user_id: Uuid,
-        date: NaiveDate,
-        products: Vec<(Uuid, i32)>, 
-    ) -> Result<Delivery, sqlx::Error>;
-
-    async fn get_deliveries_by_user(&self, user_id: Uuid) -> Result<Vec<Delivery>, sqlx::Error>;
-    async fn get_all_deliveries(&self) -> Result<Vec<Delivery>, sqlx::Error>;
+        token_hash: &str,
+        password: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
 
-}
-#[async_trait]
-impl DeliveryExt for DBClient {
-    async fn create_delivery(
-    &self,
-    user_id: Uuid,
-    date: NaiveDate,
-    products: Vec<(Uuid, i32)>,
-) -> Result<Delivery, sqlx::Error> {
-    let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+        // Consume the token; a second attempt with the same token finds nothing
+        let user_id = sqlx::query_scalar!(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = NOW()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+            token_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
-    // Insert into deliveries
-    let delivery = sqlx::query_as::<_, Delivery>(
-        "INSERT INTO deliveries (date, userid)
-         VALUES ($1, $2)
-         RETURNING *"
-    )
-    .bind(date)
-    .bind(user_id)
-    .fetch_one(&mut *tx)
-    .await?;
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
 
-    // Insert into delivery_product and update warehouse_stock
-    for (product_id, quantity) in &products {
-        // Insert into delivery_product
-        sqlx::query(
-            "INSERT INTO delivery_product (deliveryid, productid, quantity)
-             VALUES ($1, $2, $3)"
+        sqlx::query!(
+            "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+            password,
+            user_id
         )
-        .bind(delivery.deliveryid)
-        .bind(product_id)
-        .bind(quantity)
         .execute(&mut *tx)
         .await?;
 
-        // Check if product exists in warehouse_stock
-        let exists = sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS(SELECT 1 FROM warehouse_stock WHERE productid = $1)"
+        // Whoever knew the old password must not keep a live session
+        sqlx::query!(
+            "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            user_id
         )
-        .bind(product_id)
-        .fetch_one(&mut *tx)
+        .execute(&mut *tx)
         .await?;
 
-        if exists {
-            // Update existing stock
-            sqlx::query(
-                "UPDATE warehouse_stock 
-                 SET quantity = quantity + $2 
-                 WHERE productid = $1"
-            )
-            .bind(product_id)
-            .bind(quantity)
-            .execute(&mut *tx)
-            .await?;
-        } else {
-            // Insert new stock entry
-            sqlx::query(
-                "INSERT INTO warehouse_stock (productid, quantity)
-                 VALUES ($1, $2)"
-            )
-            .bind(product_id)
-            .bind(quantity)
-            .execute(&mut *tx)
-            .await?;
-        }
+        tx.commit().await?;
+        Ok(Some(user_id))
     }
+    async fn update_user(
+        &self,
+        user_id: Uuid,
+        changes: &UpdateUserDto,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET first_name = COALESCE($2, first_name),
+                last_name = COALESCE($3, last_name),
+                address = COALESCE($4, address),
+                city = COALESCE($5, city),
+                district = COALESCE($6, district),
+                contact_number = COALESCE($7, contact_number),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, first_name, last_name, email, password, role as "role: UserRole",
+                address, city, district, contact_number, verified, verification_token,
+                token_expires_at, is_active, deactivated_at, created_at, updated_at
+            "#,
+            user_id,
+            changes.first_name,
+            changes.last_name,
+            changes.address,
+            changes.city,
+            changes.district,
+            changes.contact_number
+        )
+        .fetch_optional(&self.pool)
+        .await?;
 
-    tx.commit().await?;
-
-    Ok(delivery)
-}
-
-
-    async fn get_deliveries_by_user(&self, user_id: Uuid) -> Result<Vec<Delivery>, sqlx::Error> {
-    let deliveries = sqlx::query_as::<_, Delivery>(
-        "SELECT * FROM deliveries WHERE userid = $1 ORDER BY date DESC"
-    )
-    .bind(user_id)
-    .fetch_all(&self.pool)
-    .await?;
-    Ok(deliveries)
-}
+        Ok(user)
+    }
 
-     async fn get_all_deliveries(&self) -> Result<Vec<Delivery>, sqlx::Error> {
-        let deliveries = sqlx::query_as::<_, Delivery>(
+    async fn update_user_role(
+        &self,
+        user_id: Uuid,
+        role: UserRole,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let user = sqlx::query_as!(
+            User,
             r#"
-            SELECT * FROM deliveries
-            ORDER BY date DESC
-            "#
+            UPDATE users
+            SET role = $2, updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, first_name, last_name, email, password, role as "role: UserRole",
+                address, city, district, contact_number, verified, verification_token,
+                token_expires_at, is_active, deactivated_at, created_at, updated_at
+            "#,
+            user_id,
+            role as UserRole
         )
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(deliveries)
+        Ok(user)
     }
 
-}
-
-#[async_trait]
-pub trait TruckLoadExt {
-    async fn create_truck_load(
+    async fn update_user_password(
         &self,
         user_id: Uuid,
-        truck_id: Uuid,
-        date: NaiveDate,
-        products: Vec<(Uuid, i32)>, 
-    ) -> Result<TruckLoad, sqlx::Error>;
+        password: &str,
+        keep_session_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
 
-    async fn get_all_truck_loads(&self) -> Result<Vec<TruckLoad>, sqlx::Error>;
+        sqlx::query!(
+            "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+            password,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
 
-    async fn update_remaining_quantities(
-    &self,
-    truckloadid: Uuid,
-    items: Vec<(Uuid, i32)>,  // (productid, remaining_quantity)
-) -> Result<Vec<(Uuid, i32)>, sqlx::Error>;
+        // Sign out every other device; the one making the change stays logged in
+        sqlx::query!(
+            "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND id <> $2 AND revoked_at IS NULL",
+            user_id,
+            keep_session_id
+        )
+        .execute(&mut *tx)
+        .await?;
 
-}
+        tx.commit().await?;
+        Ok(())
+    }
 
-#[async_trait]
-impl TruckLoadExt for DBClient {
-    async fn create_truck_load(
+    async fn set_user_active(
         &self,
         user_id: Uuid,
-        truck_id: Uuid,
-        date: NaiveDate,
-        products: Vec<(Uuid, i32)>,
-    ) -> Result<TruckLoad, sqlx::Error> {
+        is_active: bool,
+    ) -> Result<Option<User>, sqlx::Error> {
         let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
 
-        // 1. Insert into truck_load table
-        let truck_load = sqlx::query_as::<_, TruckLoad>(
-            "INSERT INTO truck_loads (date, userid, truckid)
-             VALUES ($1, $2, $3)
-             RETURNING *"
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET is_active = $2,
+                deactivated_at = CASE WHEN $2 THEN NULL ELSE NOW() END,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, first_name, last_name, email, password, role as "role: UserRole",
+                address, city, district, contact_number, verified, verification_token,
+                token_expires_at, is_active, deactivated_at, created_at, updated_at
+            "#,
+            user_id,
+            is_active
         )
-        .bind(date)
-        .bind(user_id)
-        .bind(truck_id)
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await?;
 
-        // 2. Loop through products to insert into truckload_products and decrease warehouse_stock
-        for (product_id, quantity) in &products {
-            // Check warehouse stock
-            let current_stock: i32 = sqlx::query_scalar(
-                "SELECT quantity FROM warehouse_stock WHERE productid = $1"
-            )
-            .bind(product_id)
-            .fetch_one(&mut *tx)
-            .await?;
-
-            if *quantity > current_stock {
-                return Err(sqlx::Error::RowNotFound); // or a custom error
-            }
-
-            // Insert into truckload_products
-            sqlx::query(
-                "INSERT INTO truck_load_products (truckloadid, productid, quantity)
-                 VALUES ($1, $2, $3)"
+        if !is_active {
+            sqlx::query!(
+                "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+                user_id
             )
-            .bind(truck_load.truckloadid)
-            .bind(product_id)
-            .bind(quantity)
             .execute(&mut *tx)
             .await?;
+        }
 
-            // Decrease warehouse stock
-            sqlx::query(
-                "UPDATE warehouse_stock
-                 SET quantity = quantity - $2
-                 WHERE productid = $1"
+        tx.commit().await?;
+        Ok(user)
+    }
+}
+
+#[async_trait]
+pub trait InviteExt {
+    async fn create_invite(
+        &self,
+        email: &str,
+        role: UserRole,
+        invited_by: Uuid,
+        expires_in_seconds: i64,
+    ) -> Result<Invite, sqlx::Error>;
+
+    // `password` is the hash; the profile comes from the accepted invite form
+    async fn accept_invite(
+        &self,
+        invite_id: Uuid,
+        acceptance: &AcceptInviteDto,
+        password: &str,
+    ) -> Result<Option<User>, sqlx::Error>;
+}
+
+#[async_trait]
+impl InviteExt for DBClient {
+    async fn create_invite(
+        &self,
+        email: &str,
+        role: UserRole,
+        invited_by: Uuid,
+        expires_in_seconds: i64,
+    ) -> Result<Invite, sqlx::Error> {
+        let invite = sqlx::query_as::<_, Invite>(
+            r#"
+            INSERT INTO invites (email, role, invited_by, expires_at)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+            RETURNING *
+            "#
+        )
+        .bind(email)
+        .bind(role)
+        .bind(invited_by)
+        .bind(expires_in_seconds as f64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(invite)
+    }
+
+    async fn accept_invite(
+        &self,
+        invite_id: Uuid,
+        acceptance: &AcceptInviteDto,
+        password: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        // Lock the invite so it can only be redeemed once
+        let invite = sqlx::query_as::<_, Invite>(
+            r#"
+            SELECT * FROM invites
+            WHERE id = $1 AND accepted_at IS NULL AND expires_at > NOW()
+            FOR UPDATE
+            "#
+        )
+        .bind(invite_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(invite) = invite else {
+            return Ok(None);
+        };
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (
+                first_name, last_name, email, password, role, address, city, district, contact_number,
+                verified
             )
-            .bind(product_id)
-            .bind(quantity)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, TRUE)
+            RETURNING *
+            "#
+        )
+        .bind(&acceptance.first_name)
+        .bind(&acceptance.last_name)
+        .bind(&invite.email)
+        .bind(password)
+        .bind(invite.role)
+        .bind(&acceptance.address)
+        .bind(&acceptance.city)
+        .bind(&acceptance.district)
+        .bind(&acceptance.contact_number)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE invites SET accepted_at = NOW() WHERE id = $1")
+            .bind(invite_id)
             .execute(&mut *tx)
             .await?;
-        }
 
-        // 3. Commit transaction
         tx.commit().await?;
 
-        Ok(truck_load)
+        Ok(Some(user))
     }
+}
 
-    async fn get_all_truck_loads(&self) -> Result<Vec<TruckLoad>, sqlx::Error> {
-        let truck_loads = sqlx::query_as::<_, TruckLoad>(
-            "SELECT * FROM truck_loads ORDER BY date DESC"
+#[async_trait]
+pub trait SessionExt {
+    async fn create_session(
+        &self,
+        user_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, sqlx::Error>;
+
+    async fn get_active_session(
+        &self,
+        session_id: Uuid,
+    ) -> Result<Option<Session>, sqlx::Error>;
+
+    async fn rotate_session(
+        &self,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Session>, sqlx::Error>;
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<(), sqlx::Error>;
+
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl SessionExt for DBClient {
+    async fn create_session(
+        &self,
+        user_id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, sqlx::Error> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"
+            INSERT INTO sessions (user_id, refresh_token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#
         )
-        .fetch_all(&self.pool)
+        .bind(user_id)
+        .bind(refresh_token_hash)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
         .await?;
-        Ok(truck_loads)
+
+        Ok(session)
     }
-    async fn update_remaining_quantities(
+
+    async fn get_active_session(
         &self,
-        truckloadid: Uuid,
-        items: Vec<(Uuid, i32)>,
-    ) -> Result<Vec<(Uuid, i32)>, sqlx::Error> {
+        session_id: Uuid,
+    ) -> Result<Option<Session>, sqlx::Error> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT * FROM sessions
+            WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            "#
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        let mut tx = self.pool.begin().await?;
-        let mut updated_items = Vec::new();
+        Ok(session)
+    }
 
-        for (productid, add_quantity) in items {
-            // 0. Fetch the current loaded quantity
-            let loaded: (i32,) = sqlx::query_as(
-                r#"
-                SELECT quantity
-                FROM truck_load_products
-                WHERE truckloadid = $1 AND productid = $2
-                "#
-            )
-            .bind(truckloadid)
-            .bind(productid)
-            .fetch_one(&mut *tx)
+    async fn rotate_session(
+        &self,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Session>, sqlx::Error> {
+        // The presented refresh token is replaced, so it can only be used once
+        let session = sqlx::query_as::<_, Session>(
+            r#"
+            UPDATE sessions
+            SET refresh_token_hash = $2, expires_at = $3, last_used_at = NOW()
+            WHERE refresh_token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            RETURNING *
+            "#
+        )
+        .bind(refresh_token_hash)
+        .bind(new_refresh_token_hash)
+        .bind(expires_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn revoke_session(&self, session_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+            .bind(session_id)
+            .execute(&self.pool)
             .await?;
 
-            // Check if adding would exceed loaded quantity
-            if add_quantity > loaded.0 {
-                return Err(sqlx::Error::Protocol(format!(
-                    "Remaining quantity {} exceeds loaded quantity {} for product {}",
-                    add_quantity, loaded.0, productid
-                ).into()));
-            }
+        Ok(())
+    }
 
-            // 1. Update truck_load_products
-            let updated = sqlx::query!(
-                r#"
-                UPDATE truck_load_products
-                SET remaining_quantity = remaining_quantity + $1
-                WHERE truckloadid = $2 AND productid = $3
-                RETURNING remaining_quantity
-                "#,
-                add_quantity,
-                truckloadid,
-                productid
-            )
-            .fetch_one(&mut *tx)
+    async fn revoke_user_sessions(&self, user_id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
+            .bind(user_id)
+            .execute(&self.pool)
             .await?;
 
-            // 2. Update warehouse stock
-            sqlx::query!(
-                r#"
-                UPDATE warehouse_stock
-                SET quantity = quantity + $1
-                WHERE productid = $2
-                "#,
-                add_quantity,
-                productid
-            )
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait LoginAttemptExt {
+    async fn get_locked_until(
+        &self,
+        scope: &str,
+        key: &str,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+
+    async fn record_failed_login(&self, scope: &str, key: &str) -> Result<i32, sqlx::Error>;
+
+    async fn lock_login(
+        &self,
+        scope: &str,
+        key: &str,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn clear_failed_logins(&self, scope: &str, key: &str) -> Result<u64, sqlx::Error>;
+}
+
+#[async_trait]
+impl LoginAttemptExt for DBClient {
+    async fn get_locked_until(
+        &self,
+        scope: &str,
+        key: &str,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let locked_until: Option<Option<DateTime<Utc>>> = sqlx::query_scalar(
+            "SELECT locked_until FROM login_failures WHERE scope = $1 AND key = $2 AND locked_until > NOW()"
+        )
+        .bind(scope)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(locked_until.flatten())
+    }
+
+    async fn record_failed_login(&self, scope: &str, key: &str) -> Result<i32, sqlx::Error> {
+        // A day without failures starts the count over
+        let failed_attempts: i32 = sqlx::query_scalar(
+            r#"
+            INSERT INTO login_failures (scope, key, failed_attempts, last_failed_at)
+            VALUES ($1, $2, 1, NOW())
+            ON CONFLICT (scope, key) DO UPDATE
+            SET failed_attempts = CASE
+                    WHEN login_failures.last_failed_at < NOW() - INTERVAL '1 day' THEN 1
+                    ELSE login_failures.failed_attempts + 1
+                END,
+                last_failed_at = NOW()
+            RETURNING failed_attempts
+            "#
+        )
+        .bind(scope)
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(failed_attempts)
+    }
+
+    async fn lock_login(
+        &self,
+        scope: &str,
+        key: &str,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE login_failures SET locked_until = $3 WHERE scope = $1 AND key = $2")
+            .bind(scope)
+            .bind(key)
+            .bind(locked_until)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn clear_failed_logins(&self, scope: &str, key: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM login_failures WHERE scope = $1 AND key = $2")
+            .bind(scope)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+pub trait TwoFactorExt {
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<Option<UserTwoFactor>, sqlx::Error>;
+
+    async fn save_pending_two_factor(
+        &self,
+        user_id: Uuid,
+        secret: &str,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn enable_two_factor(
+        &self,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error>;
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error>;
+
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error>;
+
+    async fn disable_two_factor(&self, user_id: Uuid) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+impl TwoFactorExt for DBClient {
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<Option<UserTwoFactor>, sqlx::Error> {
+        let two_factor = sqlx::query_as::<_, UserTwoFactor>(
+            "SELECT secret, enabled_at FROM user_two_factor WHERE user_id = $1"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    async fn save_pending_two_factor(
+        &self,
+        user_id: Uuid,
+        secret: &str,
+    ) -> Result<(), sqlx::Error> {
+        // Restarting enrolment replaces a secret that was never confirmed
+        sqlx::query(
+            r#"
+            INSERT INTO user_two_factor (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, created_at = NOW()
+            WHERE user_two_factor.enabled_at IS NULL
+            "#
+        )
+        .bind(user_id)
+        .bind(secret)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn enable_two_factor(
+        &self,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        sqlx::query("UPDATE user_two_factor SET enabled_at = NOW() WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+        insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE two_factor_recovery_codes
+            SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn disable_two_factor(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM user_two_factor WHERE user_id = $1")
+            .bind(user_id)
             .execute(&mut *tx)
             .await?;
 
-            updated_items.push((productid, updated.remaining_quantity));
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+// Issuing a new set of recovery codes always invalidates the previous set.
+async fn insert_recovery_codes(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    recovery_code_hashes: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    for code_hash in recovery_code_hashes {
+        sqlx::query("INSERT INTO two_factor_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+pub trait ProductExt {
+    async fn create_product(&self, product: &CreateProductDto) -> Result<Product, sqlx::Error>;
+
+    async fn get_product_by_id(&self, product_id: Uuid) -> Result<Option<Product>, sqlx::Error>;
+
+    // Every filter is optional; `active` None returns archived and active products
+    async fn get_all_products(
+        &self,
+        category_id: Option<Uuid>,
+        name: Option<&str>,
+        barcode: Option<&str>,
+        active: Option<bool>,
+    ) -> Result<Vec<Product>, sqlx::Error>;
+
+    async fn update_product(
+        &self,
+        product_id: Uuid,
+        changes: &UpdateProductDto,
+        new_price: Option<(Decimal, NaiveDate)>,
+        updated_by: Uuid,
+    ) -> Result<Option<Product>, sqlx::Error>;
+
+    async fn set_product_archived(
+        &self,
+        product_id: Uuid,
+        archived: bool,
+    ) -> Result<Option<Product>, sqlx::Error>;
+
+    // None clears a level
+    async fn set_reorder_levels(
+        &self,
+        product_id: Uuid,
+        reorder_point: Option<i32>,
+        target_level: Option<i32>,
+    ) -> Result<Option<Product>, sqlx::Error>;
+
+    async fn get_product_prices(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, sqlx::Error>;
+
+    async fn create_product_category(&self, name: &str) -> Result<ProductCategory, sqlx::Error>;
+    async fn get_product_categories(&self) -> Result<Vec<ProductCategory>, sqlx::Error>;
+}
+
+#[async_trait]
+impl ProductExt for DBClient {
+    async fn create_product(&self, new_product: &CreateProductDto) -> Result<Product, sqlx::Error> {
+        let price = money::round(new_product.price);
+        let commission = money::round(new_product.commission.unwrap_or(Decimal::ZERO));
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        let product = sqlx::query_as::<_, Product>(
+            "INSERT INTO products
+                (name, price, unit_type, commission, category_id, sku, barcode, pack_size, base_unit, tax_rate, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, COALESCE($8, 1), COALESCE($9, $3), COALESCE($10, 0), NOW(), NOW())
+             RETURNING *"
+        )
+        .bind(&new_product.name)
+        .bind(price)
+        .bind(&new_product.unit_type)
+        .bind(commission)
+        .bind(new_product.category_id)
+        .bind(&new_product.sku)
+        .bind(&new_product.barcode)
+        .bind(new_product.pack_size)
+        .bind(&new_product.base_unit)
+        .bind(new_product.tax_rate)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // The opening price applies to any sale date, like the backfilled prices
+        sqlx::query(
+            "INSERT INTO product_prices (product_id, price, effective_from)
+             VALUES ($1, $2, DATE '1970-01-01')"
+        )
+        .bind(product.id)
+        .bind(price)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(product)
+    }
+
+    async fn get_product_by_id(&self, product_id: Uuid) -> Result<Option<Product>, sqlx::Error> {
+        let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+            .bind(product_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(product)
+    }
+
+    async fn get_all_products(
+        &self,
+        category_id: Option<Uuid>,
+        name: Option<&str>,
+        barcode: Option<&str>,
+        active: Option<bool>,
+    ) -> Result<Vec<Product>, sqlx::Error> {
+        // Match the name literally, not as a LIKE pattern
+        let name_pattern = name.map(|name| {
+            format!("%{}%", name.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+        });
+
+        let products = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products
+             WHERE ($1::UUID IS NULL OR category_id = $1)
+               AND ($2::TEXT IS NULL OR name ILIKE $2)
+               AND ($3::TEXT IS NULL OR barcode = $3)
+               AND ($4::BOOLEAN IS NULL OR (archived_at IS NULL) = $4)
+             ORDER BY name"
+        )
+        .bind(category_id)
+        .bind(name_pattern)
+        .bind(barcode)
+        .bind(active)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(products)
+    }
+
+    async fn update_product(
+        &self,
+        product_id: Uuid,
+        changes: &UpdateProductDto,
+        new_price: Option<(Decimal, NaiveDate)>,
+        updated_by: Uuid,
+    ) -> Result<Option<Product>, sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        let product = sqlx::query_as::<_, Product>(
+            "UPDATE products
+             SET name = COALESCE($2, name),
+                 unit_type = COALESCE($3, unit_type),
+                 commission = COALESCE($4, commission),
+                 category_id = COALESCE($5, category_id),
+                 sku = COALESCE($6, sku),
+                 barcode = COALESCE($7, barcode),
+                 pack_size = COALESCE($8, pack_size),
+                 base_unit = COALESCE($9, base_unit),
+                 tax_rate = COALESCE($10, tax_rate),
+                 updated_at = NOW()
+             WHERE id = $1
+             RETURNING *"
+        )
+        .bind(product_id)
+        .bind(&changes.name)
+        .bind(&changes.unit_type)
+        .bind(changes.commission.map(money::round))
+        .bind(changes.category_id)
+        .bind(&changes.sku)
+        .bind(&changes.barcode)
+        .bind(changes.pack_size)
+        .bind(&changes.base_unit)
+        .bind(changes.tax_rate)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(mut product) = product else {
+            return Ok(None);
+        };
+
+        if let Some((price, effective_from)) = new_price {
+            sqlx::query(
+                "INSERT INTO product_prices (product_id, price, effective_from, created_by)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (product_id, effective_from) DO UPDATE
+                 SET price = EXCLUDED.price, created_by = EXCLUDED.created_by, created_at = NOW()"
+            )
+            .bind(product_id)
+            .bind(money::round(price))
+            .bind(effective_from)
+            .bind(updated_by)
+            .execute(&mut *tx)
+            .await?;
+
+            // products.price is only a display copy of the price in force when
+            // the product was last changed; a future price is left to
+            // product_prices, which is what sales and valuations read
+            let today = chrono::Local::now().date_naive();
+            if effective_from <= today {
+                product = sqlx::query_as::<_, Product>(
+                    "UPDATE products
+                     SET price = (
+                         SELECT price FROM product_prices
+                         WHERE product_id = $1 AND effective_from <= $2
+                         ORDER BY effective_from DESC
+                         LIMIT 1
+                     )
+                     WHERE id = $1
+                     RETURNING *"
+                )
+                .bind(product_id)
+                .bind(today)
+                .fetch_one(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(Some(product))
+    }
+
+    async fn set_product_archived(
+        &self,
+        product_id: Uuid,
+        archived: bool,
+    ) -> Result<Option<Product>, sqlx::Error> {
+        let product = sqlx::query_as::<_, Product>(
+            "UPDATE products
+             SET archived_at = CASE WHEN $2 THEN COALESCE(archived_at, NOW()) ELSE NULL END,
+                 updated_at = NOW()
+             WHERE id = $1
+             RETURNING *"
+        )
+        .bind(product_id)
+        .bind(archived)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(product)
+    }
+
+    async fn set_reorder_levels(
+        &self,
+        product_id: Uuid,
+        reorder_point: Option<i32>,
+        target_level: Option<i32>,
+    ) -> Result<Option<Product>, sqlx::Error> {
+        let product = sqlx::query_as::<_, Product>(
+            "UPDATE products
+             SET reorder_point = $2, target_level = $3, updated_at = NOW()
+             WHERE id = $1
+             RETURNING *"
+        )
+        .bind(product_id)
+        .bind(reorder_point)
+        .bind(target_level)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(product)
+    }
+
+    async fn get_product_prices(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, sqlx::Error> {
+        let prices = sqlx::query_as::<_, ProductPrice>(
+            "SELECT * FROM product_prices WHERE product_id = $1 ORDER BY effective_from DESC"
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(prices)
+    }
+
+    async fn create_product_category(&self, name: &str) -> Result<ProductCategory, sqlx::Error> {
+        let category = sqlx::query_as::<_, ProductCategory>(
+            "INSERT INTO product_categories (name) VALUES ($1) RETURNING *"
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(category)
+    }
+
+    async fn get_product_categories(&self) -> Result<Vec<ProductCategory>, sqlx::Error> {
+        let categories = sqlx::query_as::<_, ProductCategory>(
+            "SELECT * FROM product_categories ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(categories)
+    }
+}
+
+
+#[async_trait]
+pub trait DeliveryExt {
+    async fn create_delivery(
+        &self,
+        user_id: Uuid,
+        supplier_id: Uuid,
+        warehouse_id: Uuid,
+        date: NaiveDate,
+        products: &[DeliveryProductDto],
+    ) -> Result<Delivery, sqlx::Error>;
+
+    async fn get_deliveries_by_user(&self, user_id: Uuid) -> Result<Vec<Delivery>, sqlx::Error>;
+    async fn get_all_deliveries(&self) -> Result<Vec<Delivery>, sqlx::Error>;
+
+}
+#[async_trait]
+impl DeliveryExt for DBClient {
+    async fn create_delivery(
+    &self,
+    user_id: Uuid,
+    supplier_id: Uuid,
+    warehouse_id: Uuid,
+    date: NaiveDate,
+    products: &[DeliveryProductDto],
+) -> Result<Delivery, sqlx::Error> {
+    let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+    // Insert into deliveries
+    let delivery = sqlx::query_as::<_, Delivery>(
+        "INSERT INTO deliveries (date, userid, warehouse_id, supplier_id)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *"
+    )
+    .bind(date)
+    .bind(user_id)
+    .bind(warehouse_id)
+    .bind(supplier_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Insert into delivery_product and update stock_batches / warehouse_stock of the receiving warehouse
+    for line in products {
+        let (product_id, quantity) = (&line.product_id, &line.quantity);
+
+        // Add to the batch; a known batch must keep its expiry date
+        let batch_id: Option<Uuid> = sqlx::query_scalar(
+            "INSERT INTO stock_batches (warehouse_id, productid, batch_number, production_date, expiry_date, quantity)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (warehouse_id, productid, batch_number) DO UPDATE
+             SET quantity = stock_batches.quantity + EXCLUDED.quantity
+             WHERE stock_batches.expiry_date IS NOT DISTINCT FROM EXCLUDED.expiry_date
+             RETURNING id"
+        )
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(&line.batch_number)
+        .bind(line.production_date)
+        .bind(line.expiry_date)
+        .bind(quantity)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(batch_id) = batch_id else {
+            return Err(sqlx::Error::RowNotFound);
+        };
+
+        // Insert into delivery_product
+        sqlx::query(
+            "INSERT INTO delivery_product (deliveryid, productid, quantity, batch_id)
+             VALUES ($1, $2, $3, $4)"
+        )
+        .bind(delivery.deliveryid)
+        .bind(product_id)
+        .bind(quantity)
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await?;
+
+        record_movement(&mut tx, NewStockMovement {
+            productid: *product_id,
+            warehouse_id,
+            batch_id: Some(batch_id),
+            quantity: *quantity,
+            reason: StockMovementReason::Delivery,
+            reference: MovementRef::Delivery(delivery.deliveryid),
+            user_id,
+            occurred_on: date,
+        })
+        .await?;
+
+        add_warehouse_stock(&mut tx, warehouse_id, *product_id, *quantity).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(delivery)
+}
+
+
+    async fn get_deliveries_by_user(&self, user_id: Uuid) -> Result<Vec<Delivery>, sqlx::Error> {
+    let deliveries = sqlx::query_as::<_, Delivery>(
+        "SELECT * FROM deliveries WHERE userid = $1 ORDER BY date DESC"
+    )
+    .bind(user_id)
+    .fetch_all(&self.pool)
+    .await?;
+    Ok(deliveries)
+}
+
+     async fn get_all_deliveries(&self) -> Result<Vec<Delivery>, sqlx::Error> {
+        let deliveries = sqlx::query_as::<_, Delivery>(
+            r#"
+            SELECT * FROM deliveries
+            ORDER BY date DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+}
+
+#[async_trait]
+pub trait TruckLoadExt {
+    async fn create_truck_load(
+        &self,
+        user_id: Uuid,
+        truck_id: Uuid,
+        date: NaiveDate,
+        products: Vec<(Uuid, i32)>,
+        warehouse_id: Uuid,
+        created_by: Uuid,
+    ) -> Result<TruckLoad, sqlx::Error>;
+
+    async fn get_all_truck_loads(&self) -> Result<Vec<TruckLoad>, sqlx::Error>;
+
+    async fn get_driver_truck_load(
+        &self,
+        user_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<Option<TruckLoad>, sqlx::Error>;
+
+    async fn get_truck_load_products(
+        &self,
+        truckloadid: Uuid,
+    ) -> Result<Vec<TruckLoadProductDto>, sqlx::Error>;
+
+    async fn update_remaining_quantities(
+    &self,
+    truckloadid: Uuid,
+    items: Vec<(Uuid, i32)>,  // (productid, remaining_quantity)
+    user_id: Uuid,
+) -> Result<Vec<(Uuid, i32)>, sqlx::Error>;
+
+}
+
+#[async_trait]
+impl TruckLoadExt for DBClient {
+    async fn create_truck_load(
+        &self,
+        user_id: Uuid,
+        truck_id: Uuid,
+        date: NaiveDate,
+        products: Vec<(Uuid, i32)>,
+        warehouse_id: Uuid,
+        created_by: Uuid,
+    ) -> Result<TruckLoad, sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        // 1. Insert into truck_load table
+        let truck_load = sqlx::query_as::<_, TruckLoad>(
+            "INSERT INTO truck_loads (date, userid, truckid, warehouse_id)
+             VALUES ($1, $2, $3, $4)
+             RETURNING *"
+        )
+        .bind(date)
+        .bind(user_id)
+        .bind(truck_id)
+        .bind(warehouse_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // 2. Loop through products to insert into truckload_products and decrease warehouse_stock
+        for (product_id, quantity) in &products {
+            // Archived products can no longer be loaded
+            let loadable: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM products WHERE id = $1 AND archived_at IS NULL)"
+            )
+            .bind(product_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !loadable {
+                return Err(sqlx::Error::RowNotFound);
+            }
+
+            // Unexpired batches in the dispatching warehouse, soonest expiry first (locked until commit)
+            let batches: Vec<(Uuid, i32)> = sqlx::query_as(
+                "SELECT id, quantity FROM stock_batches
+                 WHERE productid = $1 AND warehouse_id = $3 AND quantity > 0
+                   AND (expiry_date IS NULL OR expiry_date >= $2)
+                 ORDER BY expiry_date NULLS LAST, created_at
+                 FOR UPDATE"
+            )
+            .bind(product_id)
+            .bind(date)
+            .bind(warehouse_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let Some(picks) = fefo::allocate(&batches, *quantity) else {
+                return Err(sqlx::Error::RowNotFound);
+            };
+
+            for (batch_id, taken) in picks {
+                sqlx::query("UPDATE stock_batches SET quantity = quantity - $2 WHERE id = $1")
+                    .bind(batch_id)
+                    .bind(taken)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query(
+                    "INSERT INTO truck_load_batches (truckloadid, productid, batch_id, quantity)
+                     VALUES ($1, $2, $3, $4)"
+                )
+                .bind(truck_load.truckloadid)
+                .bind(product_id)
+                .bind(batch_id)
+                .bind(taken)
+                .execute(&mut *tx)
+                .await?;
+
+                record_movement(&mut tx, NewStockMovement {
+                    productid: *product_id,
+                    warehouse_id,
+                    batch_id: Some(batch_id),
+                    quantity: -taken,
+                    reason: StockMovementReason::TruckLoad,
+                    reference: MovementRef::TruckLoad(truck_load.truckloadid),
+                    user_id: created_by,
+                    occurred_on: date,
+                })
+                .await?;
+            }
+
+            // Insert into truckload_products
+            sqlx::query(
+                "INSERT INTO truck_load_products (truckloadid, productid, quantity)
+                 VALUES ($1, $2, $3)"
+            )
+            .bind(truck_load.truckloadid)
+            .bind(product_id)
+            .bind(quantity)
+            .execute(&mut *tx)
+            .await?;
+
+            // Decrease warehouse stock
+            add_warehouse_stock(&mut tx, warehouse_id, *product_id, -quantity).await?;
+        }
+
+        // 3. Commit transaction
+        tx.commit().await?;
+
+        Ok(truck_load)
+    }
+
+    async fn get_all_truck_loads(&self) -> Result<Vec<TruckLoad>, sqlx::Error> {
+        let truck_loads = sqlx::query_as::<_, TruckLoad>(
+            "SELECT * FROM truck_loads ORDER BY date DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(truck_loads)
+    }
+
+    async fn get_driver_truck_load(
+        &self,
+        user_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<Option<TruckLoad>, sqlx::Error> {
+        // Latest load wins if a driver was reloaded during the day
+        let truck_load = sqlx::query_as::<_, TruckLoad>(
+            "SELECT * FROM truck_loads
+             WHERE userid = $1 AND date = $2
+             ORDER BY created_at DESC
+             LIMIT 1"
+        )
+        .bind(user_id)
+        .bind(date)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(truck_load)
+    }
+
+    async fn get_truck_load_products(
+        &self,
+        truckloadid: Uuid,
+    ) -> Result<Vec<TruckLoadProductDto>, sqlx::Error> {
+        let products = sqlx::query_as::<_, TruckLoadProductDto>(
+            "SELECT tlp.productid, p.name AS product_name, p.unit_type,
+                    tlp.quantity AS loaded_quantity, tlp.remaining_quantity
+             FROM truck_load_products tlp
+             JOIN products p ON p.id = tlp.productid
+             WHERE tlp.truckloadid = $1
+             ORDER BY p.name"
+        )
+        .bind(truckloadid)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(products)
+    }
+    async fn update_remaining_quantities(
+        &self,
+        truckloadid: Uuid,
+        items: Vec<(Uuid, i32)>,
+        user_id: Uuid,
+    ) -> Result<Vec<(Uuid, i32)>, sqlx::Error> {
+
+        let mut tx = self.pool.begin().await?;
+        let today = chrono::Local::now().date_naive();
+        let mut updated_items = Vec::new();
+
+        // Returns go back to the warehouse the load was dispatched from
+        let warehouse_id: Uuid = sqlx::query_scalar("SELECT warehouse_id FROM truck_loads WHERE truckloadid = $1")
+            .bind(truckloadid)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        for (productid, add_quantity) in items {
+            // 0. Fetch the loaded and already returned quantities (locked until commit)
+            let (loaded, already_returned): (i32, i32) = sqlx::query_as(
+                r#"
+                SELECT quantity, remaining_quantity
+                FROM truck_load_products
+                WHERE truckloadid = $1 AND productid = $2
+                FOR UPDATE
+                "#
+            )
+            .bind(truckloadid)
+            .bind(productid)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if add_quantity < 0 {
+                return Err(sqlx::Error::Protocol(format!(
+                    "Remaining quantity for product {} cannot be negative",
+                    productid
+                )));
+            }
+
+            // Everything returned so far can't exceed what was loaded
+            if i64::from(already_returned) + i64::from(add_quantity) > i64::from(loaded) {
+                return Err(sqlx::Error::Protocol(format!(
+                    "Returning {} more of product {} would exceed the loaded quantity {} ({} already returned)",
+                    add_quantity, productid, loaded, already_returned
+                )));
+            }
+
+            // 1. Update truck_load_products
+            let updated = sqlx::query!(
+                r#"
+                UPDATE truck_load_products
+                SET remaining_quantity = remaining_quantity + $1
+                WHERE truckloadid = $2 AND productid = $3
+                RETURNING remaining_quantity
+                "#,
+                add_quantity,
+                truckloadid,
+                productid
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            // 2. Update warehouse stock
+            add_warehouse_stock(&mut tx, warehouse_id, productid, add_quantity).await?;
+
+            let returned = return_to_batches(&mut tx, truckloadid, warehouse_id, productid, add_quantity).await?;
+            for (batch_id, quantity) in returned {
+                record_movement(&mut tx, NewStockMovement {
+                    productid,
+                    warehouse_id,
+                    batch_id: Some(batch_id),
+                    quantity,
+                    reason: StockMovementReason::TruckReturn,
+                    reference: MovementRef::TruckLoad(truckloadid),
+                    user_id,
+                    occurred_on: today,
+                })
+                .await?;
+            }
+
+            updated_items.push((productid, updated.remaining_quantity));
+        }
+
+        tx.commit().await?;
+        Ok(updated_items)
+}
+
+}
+
+// Puts returned stock back into the batches the load came from, freshest
+// first. Only loads from before batch tracking (no truck_load_batches rows)
+// return into the LEGACY batch. Returns how much went back into each batch.
+async fn return_to_batches(
+    tx: &mut Transaction<'_, Postgres>,
+    truckloadid: Uuid,
+    warehouse_id: Uuid,
+    productid: Uuid,
+    quantity: i32,
+) -> Result<Vec<(Uuid, i32)>, sqlx::Error> {
+    let allocations: Vec<(Uuid, i32)> = sqlx::query_as(
+        "SELECT tlb.batch_id, tlb.quantity - tlb.returned_quantity
+         FROM truck_load_batches tlb
+         JOIN stock_batches sb ON sb.id = tlb.batch_id
+         WHERE tlb.truckloadid = $1 AND tlb.productid = $2
+         ORDER BY sb.expiry_date DESC NULLS FIRST
+         FOR UPDATE OF tlb"
+    )
+    .bind(truckloadid)
+    .bind(productid)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let batched = !allocations.is_empty();
+    let mut returned = Vec::new();
+    let mut left = quantity;
+    for (batch_id, returnable) in allocations {
+        let back = returnable.min(left);
+        if back <= 0 {
+            continue;
+        }
+
+        sqlx::query(
+            "UPDATE truck_load_batches SET returned_quantity = returned_quantity + $3
+             WHERE truckloadid = $1 AND batch_id = $2"
+        )
+        .bind(truckloadid)
+        .bind(batch_id)
+        .bind(back)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("UPDATE stock_batches SET quantity = quantity + $2 WHERE id = $1")
+            .bind(batch_id)
+            .bind(back)
+            .execute(&mut **tx)
+            .await?;
+
+        returned.push((batch_id, back));
+        left -= back;
+    }
+
+    // A batched load can only take back what its batches gave out
+    if left > 0 && batched {
+        return Err(sqlx::Error::Protocol(format!(
+            "Product {} has {} more returned than its batches loaded",
+            productid, left
+        )));
+    }
+
+    if left > 0 {
+        let legacy_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO stock_batches (warehouse_id, productid, batch_number, quantity)
+             VALUES ($1, $2, 'LEGACY', $3)
+             ON CONFLICT (warehouse_id, productid, batch_number) DO UPDATE
+             SET quantity = stock_batches.quantity + EXCLUDED.quantity
+             RETURNING id"
+        )
+        .bind(warehouse_id)
+        .bind(productid)
+        .bind(left)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        returned.push((legacy_id, left));
+    }
+
+    Ok(returned)
+}
+
+// What a stock movement points back to
+#[derive(Debug, Clone, Copy)]
+enum MovementRef {
+    Delivery(Uuid),
+    TruckLoad(Uuid),
+    Adjustment(Uuid),
+    Transfer(Uuid),
+}
+
+struct NewStockMovement {
+    productid: Uuid,
+    warehouse_id: Uuid,
+    batch_id: Option<Uuid>,
+    quantity: i32,
+    reason: StockMovementReason,
+    reference: MovementRef,
+    user_id: Uuid,
+    occurred_on: NaiveDate,
+}
+
+// Appends to the stock ledger; always called inside the transaction that
+// changes the stock, so the two can't drift apart.
+async fn record_movement(
+    tx: &mut Transaction<'_, Postgres>,
+    movement: NewStockMovement,
+) -> Result<(), sqlx::Error> {
+    let (mut deliveryid, mut truckloadid, mut adjustment_id, mut transfer_id) = (None, None, None, None);
+    match movement.reference {
+        MovementRef::Delivery(id) => deliveryid = Some(id),
+        MovementRef::TruckLoad(id) => truckloadid = Some(id),
+        MovementRef::Adjustment(id) => adjustment_id = Some(id),
+        MovementRef::Transfer(id) => transfer_id = Some(id),
+    }
+
+    sqlx::query(
+        "INSERT INTO stock_movements
+            (productid, warehouse_id, batch_id, quantity, reason, deliveryid, truckloadid, adjustment_id, transfer_id, user_id, occurred_on)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+    )
+    .bind(movement.productid)
+    .bind(movement.warehouse_id)
+    .bind(movement.batch_id)
+    .bind(movement.quantity)
+    .bind(movement.reason.to_str())
+    .bind(deliveryid)
+    .bind(truckloadid)
+    .bind(adjustment_id)
+    .bind(transfer_id)
+    .bind(movement.user_id)
+    .bind(movement.occurred_on)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+// Changes a product's total in one warehouse. Going below zero trips the
+// quantity CHECK; taking from a warehouse that never had the product is
+// RowNotFound.
+async fn add_warehouse_stock(
+    tx: &mut Transaction<'_, Postgres>,
+    warehouse_id: Uuid,
+    productid: Uuid,
+    quantity: i32,
+) -> Result<(), sqlx::Error> {
+    // The CHECK applies to the row an upsert proposes, so removals can't upsert
+    if quantity < 0 {
+        let updated = sqlx::query(
+            "UPDATE warehouse_stock SET quantity = quantity + $3
+             WHERE warehouse_id = $1 AND productid = $2"
+        )
+        .bind(warehouse_id)
+        .bind(productid)
+        .bind(quantity)
+        .execute(&mut **tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT INTO warehouse_stock (warehouse_id, productid, quantity)
+         VALUES ($1, $2, $3)
+         ON CONFLICT (warehouse_id, productid) DO UPDATE
+         SET quantity = warehouse_stock.quantity + EXCLUDED.quantity"
+    )
+    .bind(warehouse_id)
+    .bind(productid)
+    .bind(quantity)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+// Price in force for a product on `date`, from its price history
+async fn price_on(
+    tx: &mut Transaction<'_, Postgres>,
+    product_id: Uuid,
+    date: NaiveDate,
+) -> Result<Option<Decimal>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT price FROM product_prices
+         WHERE product_id = $1 AND effective_from <= $2
+         ORDER BY effective_from DESC
+         LIMIT 1"
+    )
+    .bind(product_id)
+    .bind(date)
+    .fetch_optional(&mut **tx)
+    .await
+}
+
+// Records an adjustment valued at `unit_price`. The caller decides the status
+// against the approval limit; approved ones are applied at once. Stock-take
+// corrections carry the take and the day the line was counted.
+async fn raise_adjustment(
+    tx: &mut Transaction<'_, Postgres>,
+    adjustment: &CreateStockAdjustmentDto,
+    warehouse_id: Uuid,
+    stock_take: Option<(Uuid, NaiveDate)>,
+    created_by: Uuid,
+    unit_price: Decimal,
+    status: AdjustmentStatus,
+) -> Result<StockAdjustment, sqlx::Error> {
+    let value = money::line_total(unit_price, adjustment.quantity.abs());
+
+    let created = sqlx::query_as::<_, StockAdjustment>(
+        "INSERT INTO stock_adjustments (productid, batch_id, quantity, reason, notes, value, status, created_by, stock_take_id, warehouse_id, counted_on)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+         RETURNING *"
+    )
+    .bind(adjustment.product_id)
+    .bind(adjustment.batch_id)
+    .bind(adjustment.quantity)
+    .bind(adjustment.reason.to_str())
+    .bind(&adjustment.notes)
+    .bind(value)
+    .bind(status.to_str())
+    .bind(created_by)
+    .bind(stock_take.map(|(stock_take_id, _)| stock_take_id))
+    .bind(warehouse_id)
+    .bind(stock_take.map(|(_, counted_on)| counted_on))
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if status == AdjustmentStatus::Approved {
+        apply_adjustment(tx, &created, created_by).await?;
+    }
+
+    Ok(created)
+}
+
+// Moves an approved adjustment into batch and warehouse stock. Taking more
+// than is there trips the quantity CHECKs and rolls the transaction back.
+async fn apply_adjustment(
+    tx: &mut Transaction<'_, Postgres>,
+    adjustment: &StockAdjustment,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let batch_id: Uuid = match adjustment.batch_id {
+        Some(batch_id) => {
+            sqlx::query("UPDATE stock_batches SET quantity = quantity + $2 WHERE id = $1")
+                .bind(batch_id)
+                .bind(adjustment.quantity)
+                .execute(&mut **tx)
+                .await?;
+            batch_id
+        }
+        None => {
+            sqlx::query_scalar(
+                "INSERT INTO stock_batches (warehouse_id, productid, batch_number, quantity)
+                 VALUES ($1, $2, 'LEGACY', $3)
+                 ON CONFLICT (warehouse_id, productid, batch_number) DO UPDATE
+                 SET quantity = stock_batches.quantity + EXCLUDED.quantity
+                 RETURNING id"
+            )
+            .bind(adjustment.warehouse_id)
+            .bind(adjustment.productid)
+            .bind(adjustment.quantity)
+            .fetch_one(&mut **tx)
+            .await?
+        }
+    };
+
+    add_warehouse_stock(tx, adjustment.warehouse_id, adjustment.productid, adjustment.quantity).await?;
+
+    record_movement(tx, NewStockMovement {
+        productid: adjustment.productid,
+        warehouse_id: adjustment.warehouse_id,
+        batch_id: Some(batch_id),
+        quantity: adjustment.quantity,
+        reason: StockMovementReason::Adjustment,
+        reference: MovementRef::Adjustment(adjustment.id),
+        user_id,
+        occurred_on: adjustment.counted_on.unwrap_or_else(|| chrono::Local::now().date_naive()),
+    })
+    .await
+}
+
+#[async_trait]
+pub trait SalesExt {
+    async fn create_sale(
+        &self,
+        truckload_id: Uuid,
+        shop_id: Uuid,
+        date: NaiveDate,
+        products: Vec<(Uuid, i32)>, 
+        prices_include_tax: bool,
+    ) -> Result<(Sale, Vec<SaleLineDto>), sqlx::Error>;
+
+    async fn get_daily_product_sales(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<DailyProductSaleResponse>, sqlx::Error>;
+
+    async fn get_daily_total_sales_revenue(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Decimal, sqlx::Error>;
+
+    async fn get_daily_commission(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Decimal, sqlx::Error>;
+
+    async fn get_tax_summary(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TaxRateSummary>, sqlx::Error>;
+
+    async fn get_pending_payments(&self) -> Result<Vec<PendingPaymentResponse>, sqlx::Error>;
+
+    async fn get_all_sales(&self) -> Result<Vec<SaleDto>, sqlx::Error>;
+
+    async fn get_driver_sales(
+        &self,
+        user_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<Vec<SaleDto>, sqlx::Error>;
+
+}
+#[async_trait]
+impl SalesExt for DBClient {
+    async fn create_sale(
+        &self,
+        truckload_id: Uuid,
+        shop_id: Uuid,
+        date: NaiveDate,
+        products: Vec<(Uuid, i32)>, // (product_id, quantity)
+        prices_include_tax: bool,
+    ) -> Result<(Sale, Vec<SaleLineDto>), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        // ✅ Step 1: Price each line and calculate total amount
+        let mut net_amount = Decimal::ZERO;
+        let mut tax_amount = Decimal::ZERO;
+        let mut total_amount = Decimal::ZERO;
+        let mut lines: Vec<(SaleLineDto, Decimal)> = Vec::with_capacity(products.len()); // (line, commission_rate)
+
+        for (product_id, quantity) in &products {
+            // Price in force on the sale date; archived products can't be sold
+            let (base_price, commission_rate, tax_rate): (Decimal, Decimal, Decimal) = sqlx::query_as(
+                "SELECT pp.price, COALESCE(p.commission, 0), p.tax_rate FROM product_prices pp
+                 JOIN products p ON p.id = pp.product_id
+                 WHERE pp.product_id = $1 AND pp.effective_from <= $2 AND p.archived_at IS NULL
+                 ORDER BY pp.effective_from DESC
+                 LIMIT 1"
+            )
+            .bind(product_id)
+            .bind(date)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            // A shop, shop-group or default price list overrides the base price
+            let offers = sqlx::query_as::<_, PriceListOffer>(
+                "SELECT pl.id AS price_list_id, pl.name AS price_list_name,
+                        CASE WHEN pl.shop_id IS NOT NULL THEN $4
+                             WHEN pl.shop_group_id IS NOT NULL THEN $5
+                             ELSE $6 END AS scope_rank,
+                        pl.valid_from, pli.min_quantity, pli.price
+                 FROM price_lists pl
+                 JOIN price_list_items pli ON pli.price_list_id = pl.id
+                 WHERE pli.product_id = $1
+                   AND (pl.valid_from IS NULL OR pl.valid_from <= $2)
+                   AND (pl.valid_to IS NULL OR pl.valid_to >= $2)
+                   AND (
+                       pl.shop_id = $3
+                       OR pl.shop_group_id = (SELECT group_id FROM shops WHERE shopid = $3)
+                       OR (pl.shop_id IS NULL AND pl.shop_group_id IS NULL)
+                   )"
+            )
+            .bind(product_id)
+            .bind(date)
+            .bind(shop_id)
+            .bind(pricing::SHOP_SCOPE)
+            .bind(pricing::GROUP_SCOPE)
+            .bind(pricing::DEFAULT_SCOPE)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let offer = pricing::best_offer(&offers, *quantity);
+            let unit_price = offer.map_or(base_price, |offer| offer.price);
+            let amounts = tax::split(money::line_total(unit_price, *quantity), tax_rate, prices_include_tax);
+            net_amount += amounts.net;
+            tax_amount += amounts.tax;
+            total_amount += amounts.gross;
+            lines.push((
+                SaleLineDto {
+                    product_id: *product_id,
+                    quantity: *quantity,
+                    unit_price,
+                    tax_rate,
+                    net_amount: amounts.net,
+                    tax_amount: amounts.tax,
+                    line_total: amounts.gross,
+                    price_list_id: offer.map(|offer| offer.price_list_id),
+                    price_list_name: offer.map(|offer| offer.price_list_name.clone()),
+                },
+                commission_rate,
+            ));
+        }
+
+        // ✅ Step 2: Insert into sales table with total_amount and paid_amount = 0
+        let sale = sqlx::query_as::<_, Sale>(
+            "INSERT INTO sales (truckloadid, shopid, date, status, total_amount, paid_amount, net_amount, tax_amount)
+             VALUES ($1, $2, $3, 'pending', $4, 0, $5, $6)
+             RETURNING *"
+        )
+        .bind(truckload_id)
+        .bind(shop_id)
+        .bind(date)
+        .bind(total_amount)
+        .bind(net_amount)
+        .bind(tax_amount)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // ✅ Step 3: Insert into sales_product table, snapshotting price and commission
+        for (line, commission_rate) in &lines {
+            sqlx::query(
+                "INSERT INTO sales_product
+                    (salesid, productid, quantity, unit_price, line_total, commission_rate, price_list_id, tax_rate, net_amount, tax_amount)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+            )
+            .bind(sale.salesid)
+            .bind(line.product_id)
+            .bind(line.quantity)
+            .bind(line.unit_price)
+            .bind(line.line_total)
+            .bind(commission_rate)
+            .bind(line.price_list_id)
+            .bind(line.tax_rate)
+            .bind(line.net_amount)
+            .bind(line.tax_amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // ✅ Step 4: Commit transaction
+        tx.commit().await?;
+
+        Ok((sale, lines.into_iter().map(|(line, _)| line).collect()))
+    }
+
+   async fn get_daily_product_sales(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Vec<DailyProductSaleResponse>, sqlx::Error> {
+        let sales = sqlx::query_as::<_, DailyProductSaleResponse>(
+            r#"
+            SELECT 
+                p.name AS product_name,
+                SUM(sp.quantity) AS total_quantity,
+                SUM(sp.line_total) AS total_amount
+            FROM sales s
+            JOIN sales_product sp ON s.salesid = sp.salesid
+            JOIN products p ON sp.productid = p.id
+            WHERE s.date = $1
+            GROUP BY p.name
+            ORDER BY total_quantity DESC
+            "#
+        )
+        .bind(date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sales)
+    }
+
+    async fn get_daily_total_sales_revenue(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Decimal, sqlx::Error> {
+        let total_revenue: Decimal = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(paid_amount), 0)
+            FROM sales
+            WHERE date = $1
+            "#
+        )
+        .bind(date)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total_revenue)
+    }
+
+
+    async fn get_daily_commission(
+        &self,
+        date: NaiveDate,
+    ) -> Result<Decimal, sqlx::Error> {
+        let total_commission: Option<Decimal> = sqlx::query_scalar(
+            r#"
+            SELECT 
+                SUM(sp.quantity * sp.commission_rate) AS total_commission
+            FROM sales s
+            JOIN sales_product sp ON s.salesid = sp.salesid
+            WHERE s.date = $1
+            "#,
+        )
+        .bind(date)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(money::round(total_commission.unwrap_or(Decimal::ZERO)))
+    }
+
+    async fn get_tax_summary(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<TaxRateSummary>, sqlx::Error> {
+        let rates = sqlx::query_as::<_, TaxRateSummary>(
+            r#"
+            SELECT
+                sp.tax_rate,
+                COUNT(*) AS lines,
+                SUM(sp.net_amount) AS net_amount,
+                SUM(sp.tax_amount) AS tax_amount,
+                SUM(sp.line_total) AS gross_amount
+            FROM sales s
+            JOIN sales_product sp ON s.salesid = sp.salesid
+            WHERE s.date BETWEEN $1 AND $2
+            GROUP BY sp.tax_rate
+            ORDER BY sp.tax_rate
+            "#
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rates)
+    }
+
+    async fn get_pending_payments(&self) -> Result<Vec<PendingPaymentResponse>, sqlx::Error> {
+        let payments = sqlx::query_as::<_, PendingPaymentResponse>(
+            r#"
+            SELECT 
+                s.salesid,
+                s.total_amount,
+                s.paid_amount,
+                (s.total_amount - s.paid_amount) AS remaining_amount,
+                sh.name AS shop_name,
+                sh.address AS shop_address
+            FROM sales s
+            JOIN shops sh ON s.shopid = sh.shopid
+            WHERE s.status = 'pending'
+            ORDER BY s.date DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(payments)
+    }
+
+    async fn get_all_sales(&self) -> Result<Vec<SaleDto>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT salesid, truckloadid, shopid, date, net_amount, tax_amount, total_amount, paid_amount, status
+            FROM sales
+            ORDER BY date DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Map SQL rows to DTO
+        let sales = rows.into_iter().map(|r| SaleDto {
+            salesid: r.salesid,
+            truckload_id: r.truckloadid,
+            shop_id: r.shopid,
+            date: r.date,
+            net_amount: r.net_amount,
+            tax_amount: r.tax_amount,
+            total_amount: r.total_amount.unwrap_or(Decimal::ZERO), 
+            paid_amount: r.paid_amount.unwrap_or(Decimal::ZERO), 
+            status: r.status,
+        }).collect();
+
+        Ok(sales)
+    }
+
+    async fn get_driver_sales(
+        &self,
+        user_id: Uuid,
+        date: NaiveDate,
+    ) -> Result<Vec<SaleDto>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT s.salesid, s.truckloadid, s.shopid, s.date, s.net_amount, s.tax_amount, s.total_amount, s.paid_amount, s.status
+            FROM sales s
+            JOIN truck_loads tl ON tl.truckloadid = s.truckloadid
+            WHERE tl.userid = $1 AND s.date = $2
+            ORDER BY s.created_at DESC
+            "#,
+            user_id,
+            date
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let sales = rows.into_iter().map(|r| SaleDto {
+            salesid: r.salesid,
+            truckload_id: r.truckloadid,
+            shop_id: r.shopid,
+            date: r.date,
+            net_amount: r.net_amount,
+            tax_amount: r.tax_amount,
+            total_amount: r.total_amount.unwrap_or(Decimal::ZERO),
+            paid_amount: r.paid_amount.unwrap_or(Decimal::ZERO),
+            status: r.status,
+        }).collect();
+
+        Ok(sales)
+    }
+
+}
+
+
+
+
+#[async_trait]
+pub trait PaymentExt {
+    async fn create_payment(
+        &self,
+        salesid: Uuid,
+        amount: Decimal,
+        method: String,
+        date: NaiveDate,
+    ) -> Result<Payment, sqlx::Error>;
+
+}
+
+#[async_trait]
+impl PaymentExt for DBClient {
+   async fn create_payment(
+    &self,
+    salesid: Uuid,
+    amount: Decimal,
+    method: String,
+    date: NaiveDate,
+) -> Result<Payment, sqlx::Error> {
+    let amount = money::round(amount);
+    let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+    // 1️⃣ Insert payment
+    let payment = sqlx::query_as::<_, Payment>(
+        "INSERT INTO payment (salesid, amount, method, date, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, NOW(), NOW())
+         RETURNING *"
+    )
+    .bind(salesid)
+    .bind(amount)
+    .bind(method)
+    .bind(date)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // 2️⃣ Update paid_amount in sales table
+    sqlx::query(
+        "UPDATE sales
+         SET paid_amount = COALESCE(paid_amount, 0) + $1,
+             updated_at = NOW()
+         WHERE salesid = $2"
+    )
+    .bind(amount)
+    .bind(salesid)
+    .execute(&mut *tx)
+    .await?;
+
+     sqlx::query(
+        "UPDATE sales
+         SET status = 'paid', updated_at = NOW()
+         WHERE salesid = $1 AND COALESCE(paid_amount, 0) >= COALESCE(total_amount, 0)"
+    )
+    .bind(salesid)
+    .execute(&mut *tx)
+    .await?;
+
+    // 3️⃣ Commit transaction
+    tx.commit().await?;
+
+    Ok(payment)
+}
+
+
+}
+
+
+#[async_trait]
+pub trait AllowanceExt {
+    async fn create_allowance(
+        &self,
+        date: NaiveDate,
+        amount: Decimal,
+        notes: Option<String>,
+    ) -> Result<Allowance, sqlx::Error>;
+    async fn create_truck_allowance(
+    &self,
+    truckid: Uuid,
+    date: NaiveDate,
+    amount: Decimal,
+) -> Result<TruckAllowance, sqlx::Error>;
+
+
+    async fn get_allowance_distribution_by_date(
+        &self,
+        date: chrono::NaiveDate,
+    ) -> Result<AllowanceDistributionResponse, sqlx::Error>;
+}
+  
+
+#[async_trait]
+impl AllowanceExt for DBClient {
+    async fn create_allowance(
+        &self,
+        date: NaiveDate,
+        amount: Decimal,
+        notes: Option<String>,
+    ) -> Result<Allowance, sqlx::Error> {
+        let amount = money::round(amount);
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        let allowance = sqlx::query_as::<_, Allowance>(
+            "INSERT INTO allowance (date, amount, notes, created_at, updated_at)
+             VALUES ($1, $2, $3, NOW(), NOW())
+             RETURNING *"
+        )
+        .bind(date)
+        .bind(amount)
+        .bind(notes)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(allowance)
+    }
+
+
+    async fn create_truck_allowance(
+    &self,
+    truckid: Uuid,
+    date: NaiveDate,
+    amount: Decimal,
+) -> Result<TruckAllowance, SqlxError> {
+    let amount = money::round(amount);
+    let mut tx = self.pool.begin().await?;
+
+    // 1. Get truck max_allowance
+    let max_allowance: Decimal = sqlx::query_scalar(
+        "SELECT max_allowance FROM trucks WHERE truckid = $1"
+    )
+    .bind(truckid)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if amount > max_allowance {
+        return Err(SqlxError::Protocol(
+            "Allowance exceeds truck max limit".into()
+        ));
+    }
+
+    // 2. Get allowanceid from allowance table using date
+    let allowanceid: Uuid = sqlx::query_scalar(
+        "SELECT allowanceid FROM allowance WHERE date = $1"
+    )
+    .bind(date)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // 3. Insert into truck_allowance
+    let truck_allowance = sqlx::query_as::<_, TruckAllowance>(
+        "INSERT INTO truck_allowance (allowanceid, truckid, amount, created_at, updated_at)
+         VALUES ($1, $2, $3, NOW(), NOW())
+         RETURNING *"
+    )
+    .bind(allowanceid)
+    .bind(truckid)
+    .bind(amount)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(truck_allowance)
+}
+
+
+
+    async fn get_allowance_distribution_by_date(
+        &self,
+        date: chrono::NaiveDate,
+    ) -> Result<AllowanceDistributionResponse, sqlx::Error> {
+        // Get the allowance record for the given date
+        let row = sqlx::query!(
+            "SELECT allowanceid, amount FROM allowance WHERE date = $1",
+            date
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let allowanceid = row.allowanceid;
+        let total_amount = row.amount;
+
+        // Get truck allocations
+        let distributed: Vec<TruckAllowanceInfo> = sqlx::query_as!(
+            TruckAllowanceInfo,
+            r#"
+            SELECT t.trucknumber, ta.amount
+            FROM truck_allowance ta
+            JOIN trucks t ON ta.truckid = t.truckid
+            WHERE ta.allowanceid = $1
+            ORDER BY t.trucknumber
+            "#,
+            allowanceid
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(AllowanceDistributionResponse {
+            date,
+            total_amount,
+            distributed,
+        })
+    }
+
+
+}
+
+#[async_trait]
+pub trait TruckExt {
+    async fn create_truck(
+        &self,
+        trucknumber: &str,
+        model: &str,
+        max_allowance: Option<Decimal>,
+    ) -> Result<Truck, sqlx::Error>;
+
+    async fn get_all_trucks(&self) -> Result<Vec<Truck>, sqlx::Error>;
+
+    async fn update_max_allowance(
+        &self,
+        trucknumber: &str,
+        max_allowance: Decimal,
+    ) -> Result<Truck, sqlx::Error>;
+}
+
+#[async_trait]
+impl TruckExt for DBClient {
+    async fn create_truck(
+        &self,
+        trucknumber: &str,
+        model: &str,
+        max_allowance: Option<Decimal>,
+    ) -> Result<Truck, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let truck = sqlx::query_as::<_, Truck>(
+            r#"
+            INSERT INTO trucks (trucknumber, model, max_allowance, created_at, updated_at)
+            VALUES ($1, $2, $3, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(trucknumber)
+        .bind(model)
+        .bind(max_allowance.map(money::round))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(truck)
+    }
+
+    async fn get_all_trucks(&self) -> Result<Vec<Truck>, sqlx::Error> {
+        let trucks = sqlx::query_as::<_, Truck>(
+            "SELECT * FROM trucks ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(trucks)
+    }
+
+    async fn update_max_allowance(
+        &self,
+        trucknumber: &str,
+        max_allowance: Decimal,
+    ) -> Result<Truck, sqlx::Error> {
+        let updated_truck = sqlx::query_as::<_, Truck>(
+            r#"
+            UPDATE trucks
+            SET max_allowance = $1, updated_at = NOW()
+            WHERE trucknumber = $2
+            RETURNING *
+            "#
+        )
+        .bind(money::round(max_allowance))
+        .bind(trucknumber)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(updated_truck)
+    }
+}
+
+
+#[async_trait]
+pub trait ShopExt {
+    async fn create_shop(
+        &self,
+        name: &str,
+        address: &str,
+        city: Option<&str>,
+        district: Option<&str>,
+        contact_number: Option<&str>,
+        group_id: Option<Uuid>,
+    ) -> Result<Shop, sqlx::Error>;
+
+    async fn get_all_shops(&self) -> Result<Vec<Shop>, sqlx::Error>;
+
+    async fn create_shop_group(&self, name: &str) -> Result<ShopGroup, sqlx::Error>;
+    async fn get_shop_groups(&self) -> Result<Vec<ShopGroup>, sqlx::Error>;
+
+    async fn set_shop_group(
+        &self,
+        shop_id: Uuid,
+        group_id: Option<Uuid>,
+    ) -> Result<Option<Shop>, sqlx::Error>;
+}
+
+#[async_trait]
+impl ShopExt for DBClient {
+    async fn create_shop(
+        &self,
+        name: &str,
+        address: &str,
+        city: Option<&str>,
+        district: Option<&str>,
+        contact_number: Option<&str>,
+        group_id: Option<Uuid>,
+    ) -> Result<Shop, sqlx::Error> {
+        let shop = sqlx::query_as::<_, Shop>(
+            r#"
+            INSERT INTO shops (name, address, city, district, contact_number, group_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(name)
+        .bind(address)
+        .bind(city)
+        .bind(district)
+        .bind(contact_number)
+        .bind(group_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(shop)
+    }
+
+    async fn get_all_shops(&self) -> Result<Vec<Shop>, sqlx::Error> {
+        let shops = sqlx::query_as::<_, Shop>(
+            "SELECT * FROM shops ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(shops)
+    }
+
+    async fn create_shop_group(&self, name: &str) -> Result<ShopGroup, sqlx::Error> {
+        let group = sqlx::query_as::<_, ShopGroup>(
+            "INSERT INTO shop_groups (name) VALUES ($1) RETURNING *"
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    async fn get_shop_groups(&self) -> Result<Vec<ShopGroup>, sqlx::Error> {
+        let groups = sqlx::query_as::<_, ShopGroup>(
+            "SELECT * FROM shop_groups ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    async fn set_shop_group(
+        &self,
+        shop_id: Uuid,
+        group_id: Option<Uuid>,
+    ) -> Result<Option<Shop>, sqlx::Error> {
+        let shop = sqlx::query_as::<_, Shop>(
+            "UPDATE shops SET group_id = $2, updated_at = NOW() WHERE shopid = $1 RETURNING *"
+        )
+        .bind(shop_id)
+        .bind(group_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(shop)
+    }
+}
+
+#[async_trait]
+pub trait PriceListExt {
+    async fn create_price_list(
+        &self,
+        price_list: &CreatePriceListDto,
+        created_by: Uuid,
+    ) -> Result<(PriceList, Vec<PriceListItem>), sqlx::Error>;
+
+    async fn get_price_lists(&self) -> Result<Vec<PriceList>, sqlx::Error>;
+
+    async fn get_price_list(
+        &self,
+        price_list_id: Uuid,
+    ) -> Result<Option<(PriceList, Vec<PriceListItem>)>, sqlx::Error>;
+
+    // Replaces every item of the list; None if the list doesn't exist
+    async fn replace_price_list_items(
+        &self,
+        price_list_id: Uuid,
+        items: &[PriceListItemDto],
+    ) -> Result<Option<(PriceList, Vec<PriceListItem>)>, sqlx::Error>;
+}
+
+async fn insert_price_list_items(
+    tx: &mut Transaction<'_, Postgres>,
+    price_list_id: Uuid,
+    items: &[PriceListItemDto],
+) -> Result<Vec<PriceListItem>, sqlx::Error> {
+    let mut inserted = Vec::with_capacity(items.len());
+    for item in items {
+        let row = sqlx::query_as::<_, PriceListItem>(
+            "INSERT INTO price_list_items (price_list_id, product_id, min_quantity, price)
+             VALUES ($1, $2, $3, $4)
+             RETURNING *"
+        )
+        .bind(price_list_id)
+        .bind(item.product_id)
+        .bind(item.min_quantity.unwrap_or(1))
+        .bind(money::round(item.price))
+        .fetch_one(&mut **tx)
+        .await?;
+        inserted.push(row);
+    }
+    Ok(inserted)
+}
+
+#[async_trait]
+impl PriceListExt for DBClient {
+    async fn create_price_list(
+        &self,
+        price_list: &CreatePriceListDto,
+        created_by: Uuid,
+    ) -> Result<(PriceList, Vec<PriceListItem>), sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        let list = sqlx::query_as::<_, PriceList>(
+            "INSERT INTO price_lists (name, shop_id, shop_group_id, valid_from, valid_to, created_by)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *"
+        )
+        .bind(&price_list.name)
+        .bind(price_list.shop_id)
+        .bind(price_list.shop_group_id)
+        .bind(price_list.valid_from)
+        .bind(price_list.valid_to)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let items = insert_price_list_items(&mut tx, list.id, &price_list.items).await?;
+
+        tx.commit().await?;
+        Ok((list, items))
+    }
+
+    async fn get_price_lists(&self) -> Result<Vec<PriceList>, sqlx::Error> {
+        let lists = sqlx::query_as::<_, PriceList>(
+            "SELECT * FROM price_lists ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lists)
+    }
+
+    async fn get_price_list(
+        &self,
+        price_list_id: Uuid,
+    ) -> Result<Option<(PriceList, Vec<PriceListItem>)>, sqlx::Error> {
+        let list = sqlx::query_as::<_, PriceList>("SELECT * FROM price_lists WHERE id = $1")
+            .bind(price_list_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(list) = list else {
+            return Ok(None);
+        };
+
+        let items = sqlx::query_as::<_, PriceListItem>(
+            "SELECT * FROM price_list_items WHERE price_list_id = $1 ORDER BY product_id, min_quantity"
+        )
+        .bind(price_list_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Some((list, items)))
+    }
+
+    async fn replace_price_list_items(
+        &self,
+        price_list_id: Uuid,
+        items: &[PriceListItemDto],
+    ) -> Result<Option<(PriceList, Vec<PriceListItem>)>, sqlx::Error> {
+        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+
+        let list = sqlx::query_as::<_, PriceList>(
+            "UPDATE price_lists SET updated_at = NOW() WHERE id = $1 RETURNING *"
+        )
+        .bind(price_list_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(list) = list else {
+            return Ok(None);
+        };
+
+        sqlx::query("DELETE FROM price_list_items WHERE price_list_id = $1")
+            .bind(price_list_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let items = insert_price_list_items(&mut tx, price_list_id, items).await?;
+
+        tx.commit().await?;
+        Ok(Some((list, items)))
+    }
+}
+
+#[async_trait]
+pub trait StockExt {
+    // Current levels, or the level at the end of `as_of` rebuilt from history.
+    // `product_id` narrows the result to one product, `warehouse_id` to one
+    // warehouse; without it quantities are totals across warehouses.
+    async fn get_stock_levels(
+        &self,
+        product_id: Option<Uuid>,
+        as_of: Option<NaiveDate>,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockLevelDto>, sqlx::Error>;
+
+    async fn get_product_batches(
+        &self,
+        product_id: Uuid,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockBatch>, sqlx::Error>;
+
+    // A product's current quantity in each warehouse
+    async fn get_product_warehouse_stock(&self, product_id: Uuid) -> Result<Vec<WarehouseStockDto>, sqlx::Error>;
+
+    // Batches still in stock that expire on or before `until` (expired ones included)
+    async fn get_expiring_batches(
+        &self,
+        today: NaiveDate,
+        until: NaiveDate,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<ExpiringBatchDto>, sqlx::Error>;
+
+    // Raises an adjustment; it's applied straight away unless its value is
+    // above `approval_limit`, in which case it waits as pending. A batch is
+    // adjusted in its own warehouse.
+    async fn create_stock_adjustment(
+        &self,
+        adjustment: &CreateStockAdjustmentDto,
+        created_by: Uuid,
+        approval_limit: Decimal,
+    ) -> Result<StockAdjustment, sqlx::Error>;
+
+    async fn get_stock_adjustments(
+        &self,
+        status: Option<AdjustmentStatus>,
+    ) -> Result<Vec<StockAdjustment>, sqlx::Error>;
+
+    // Approves (and applies) or rejects a pending adjustment
+    async fn decide_stock_adjustment(
+        &self,
+        adjustment_id: Uuid,
+        decision: AdjustmentStatus,
+        decided_by: Uuid,
+    ) -> Result<StockAdjustment, sqlx::Error>;
+
+    // Active products at or below their reorder point, with their outflow over the
+    // `days` up to and including `today`; shortest cover first
+    async fn get_reorder_suggestions(
+        &self,
+        today: NaiveDate,
+        days: i32,
+    ) -> Result<Vec<ReorderSuggestionDto>, sqlx::Error>;
+
+    // Active managers, who get the low stock alerts
+    async fn get_reorder_alert_recipients(&self) -> Result<Vec<User>, sqlx::Error>;
+
+    // Ledger rows matching every filter given, newest first
+    async fn get_stock_movements(
+        &self,
+        filter: &StockMovementQuery,
+        page: u32,
+        limit: usize,
+    ) -> Result<Vec<StockMovementDto>, sqlx::Error>;
+}
+
+#[async_trait]
+impl StockExt for DBClient {
+    async fn get_stock_levels(
+        &self,
+        product_id: Option<Uuid>,
+        as_of: Option<NaiveDate>,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockLevelDto>, sqlx::Error> {
+        // Past levels are replayed from the stock ledger
+        let levels = sqlx::query_as::<_, StockLevelDto>(
+            "SELECT p.id AS product_id, p.name, p.sku, p.category_id, p.unit_type, p.pack_size, p.base_unit,
+                    (p.archived_at IS NOT NULL) AS archived,
+                    (CASE WHEN $2::DATE IS NULL THEN
+                        COALESCE((
+                            SELECT SUM(ws.quantity) FROM warehouse_stock ws
+                            WHERE ws.productid = p.id AND ($3::UUID IS NULL OR ws.warehouse_id = $3)
+                        ), 0)
+                    ELSE
+                        COALESCE((
+                            SELECT SUM(sm.quantity) FROM stock_movements sm
+                            WHERE sm.productid = p.id AND sm.occurred_on <= $2
+                              AND ($3::UUID IS NULL OR sm.warehouse_id = $3)
+                        ), 0)
+                    END)::BIGINT AS quantity
+             FROM products p
+             WHERE ($1::UUID IS NULL OR p.id = $1)
+             ORDER BY p.name"
+        )
+        .bind(product_id)
+        .bind(as_of)
+        .bind(warehouse_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(levels)
+    }
+
+    async fn get_product_batches(
+        &self,
+        product_id: Uuid,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<StockBatch>, sqlx::Error> {
+        let batches = sqlx::query_as::<_, StockBatch>(
+            "SELECT * FROM stock_batches
+             WHERE productid = $1 AND quantity > 0
+               AND ($2::UUID IS NULL OR warehouse_id = $2)
+             ORDER BY expiry_date NULLS LAST, created_at"
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(batches)
+    }
+
+    async fn get_product_warehouse_stock(&self, product_id: Uuid) -> Result<Vec<WarehouseStockDto>, sqlx::Error> {
+        let stock = sqlx::query_as::<_, WarehouseStockDto>(
+            "SELECT w.id AS warehouse_id, w.name AS warehouse_name,
+                    COALESCE(ws.quantity, 0)::BIGINT AS quantity
+             FROM warehouses w
+             LEFT JOIN warehouse_stock ws ON ws.warehouse_id = w.id AND ws.productid = $1
+             ORDER BY w.is_default DESC, w.name"
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stock)
+    }
+
+    async fn get_expiring_batches(
+        &self,
+        today: NaiveDate,
+        until: NaiveDate,
+        warehouse_id: Option<Uuid>,
+    ) -> Result<Vec<ExpiringBatchDto>, sqlx::Error> {
+        let batches = sqlx::query_as::<_, ExpiringBatchDto>(
+            "SELECT sb.id AS batch_id, sb.productid AS product_id, p.name AS product_name,
+                    sb.warehouse_id, w.name AS warehouse_name,
+                    sb.batch_number, sb.production_date, sb.expiry_date, sb.quantity,
+                    (sb.expiry_date - $1) AS days_left
+             FROM stock_batches sb
+             JOIN products p ON p.id = sb.productid
+             JOIN warehouses w ON w.id = sb.warehouse_id
+             WHERE sb.quantity > 0 AND sb.expiry_date <= $2
+               AND ($3::UUID IS NULL OR sb.warehouse_id = $3)
+             ORDER BY sb.expiry_date, p.name, w.name"
+        )
+        .bind(today)
+        .bind(until)
+        .bind(warehouse_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(batches)
+    }
+
+    async fn get_stock_movements(
+        &self,
+        filter: &StockMovementQuery,
+        page: u32,
+        limit: usize,
+    ) -> Result<Vec<StockMovementDto>, sqlx::Error> {
+        let offset = (page - 1) * limit as u32;
+
+        let movements = sqlx::query_as::<_, StockMovementDto>(
+            "SELECT sm.id, sm.productid AS product_id, p.name AS product_name, sm.warehouse_id,
+                    sm.batch_id, sb.batch_number, sm.quantity, sm.reason,
+                    sm.deliveryid AS delivery_id, sm.truckloadid AS truck_load_id, sm.adjustment_id,
+                    sm.transfer_id, sm.user_id, sm.occurred_on, sm.created_at
+             FROM stock_movements sm
+             JOIN products p ON p.id = sm.productid
+             LEFT JOIN stock_batches sb ON sb.id = sm.batch_id
+             WHERE ($1::UUID IS NULL OR sm.productid = $1)
+               AND ($2::UUID IS NULL OR sm.batch_id = $2)
+               AND ($3::VARCHAR IS NULL OR sm.reason = $3)
+               AND ($4::UUID IS NULL OR sm.user_id = $4)
+               AND ($5::UUID IS NULL OR sm.deliveryid = $5)
+               AND ($6::UUID IS NULL OR sm.truckloadid = $6)
+               AND ($7::DATE IS NULL OR sm.occurred_on >= $7)
+               AND ($8::DATE IS NULL OR sm.occurred_on <= $8)
+               AND ($9::UUID IS NULL OR sm.warehouse_id = $9)
+               AND ($10::UUID IS NULL OR sm.transfer_id = $10)
+             ORDER BY sm.occurred_on DESC, sm.created_at DESC
+             LIMIT $11 OFFSET $12"
+        )
+        .bind(filter.product_id)
+        .bind(filter.batch_id)
+        .bind(filter.reason.map(StockMovementReason::to_str))
+        .bind(filter.user_id)
+        .bind(filter.delivery_id)
+        .bind(filter.truck_load_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.warehouse_id)
+        .bind(filter.transfer_id)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(movements)
+    }
+
+    async fn create_stock_adjustment(
+        &self,
+        adjustment: &CreateStockAdjustmentDto,
+        created_by: Uuid,
+        approval_limit: Decimal,
+    ) -> Result<StockAdjustment, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let warehouse_id: Option<Uuid> = match adjustment.batch_id {
+            Some(batch_id) => {
+                sqlx::query_scalar(
+                    "SELECT warehouse_id FROM stock_batches
+                     WHERE id = $1 AND productid = $2 AND ($3::UUID IS NULL OR warehouse_id = $3)"
+                )
+                .bind(batch_id)
+                .bind(adjustment.product_id)
+                .bind(adjustment.warehouse_id)
+                .fetch_optional(&mut *tx)
+                .await?
+            }
+            None => {
+                sqlx::query_scalar(
+                    "SELECT id FROM warehouses
+                     WHERE id = COALESCE($1, (SELECT id FROM warehouses WHERE is_default))"
+                )
+                .bind(adjustment.warehouse_id)
+                .fetch_optional(&mut *tx)
+                .await?
+            }
+        };
+
+        let Some(warehouse_id) = warehouse_id else {
+            return Err(sqlx::Error::RowNotFound);
+        };
+
+        // Valued at the product's current price
+        let unit_price = price_on(&mut tx, adjustment.product_id, chrono::Local::now().date_naive())
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let status = approval::adjustment_status(unit_price, &[adjustment.quantity], approval_limit);
+
+        let created = raise_adjustment(&mut tx, adjustment, warehouse_id, None, created_by, unit_price, status).await?;
+
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    async fn get_stock_adjustments(
+        &self,
+        status: Option<AdjustmentStatus>,
+    ) -> Result<Vec<StockAdjustment>, sqlx::Error> {
+        let adjustments = sqlx::query_as::<_, StockAdjustment>(
+            "SELECT * FROM stock_adjustments
+             WHERE ($1::VARCHAR IS NULL OR status = $1)
+             ORDER BY created_at DESC"
+        )
+        .bind(status.map(AdjustmentStatus::to_str))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(adjustments)
+    }
+
+    async fn decide_stock_adjustment(
+        &self,
+        adjustment_id: Uuid,
+        decision: AdjustmentStatus,
+        decided_by: Uuid,
+    ) -> Result<StockAdjustment, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Only pending adjustments can be decided, and only once
+        let decided = sqlx::query_as::<_, StockAdjustment>(
+            "UPDATE stock_adjustments
+             SET status = $2, decided_by = $3, decided_at = NOW()
+             WHERE id = $1 AND status = 'pending'
+             RETURNING *"
+        )
+        .bind(adjustment_id)
+        .bind(decision.to_str())
+        .bind(decided_by)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+        if decision == AdjustmentStatus::Approved {
+            apply_adjustment(&mut tx, &decided, decided_by).await?;
         }
 
         tx.commit().await?;
-        Ok(updated_items)
-}
+        Ok(decided)
+    }
+
+    async fn get_reorder_suggestions(
+        &self,
+        today: NaiveDate,
+        days: i32,
+    ) -> Result<Vec<ReorderSuggestionDto>, sqlx::Error> {
+        let since = today - chrono::Days::new(days as u64 - 1);
+
+        let rows = sqlx::query_as::<_, ReorderSuggestionDto>(
+            "SELECT p.id AS product_id, p.name, p.sku, p.unit_type, p.reorder_point, p.target_level,
+                    COALESCE((SELECT SUM(ws.quantity) FROM warehouse_stock ws WHERE ws.productid = p.id), 0)::BIGINT AS on_hand,
+                    COALESCE((
+                        SELECT SUM(tlp.quantity - tlp.remaining_quantity) FROM truck_load_products tlp
+                        JOIN truck_loads tl ON tl.truckloadid = tlp.truckloadid
+                        WHERE tlp.productid = p.id AND tl.date BETWEEN $1 AND $2
+                    ), 0)::BIGINT AS outflow
+             FROM products p
+             WHERE p.reorder_point IS NOT NULL AND p.archived_at IS NULL"
+        )
+        .bind(since)
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut suggestions: Vec<ReorderSuggestionDto> = rows
+            .into_iter()
+            .filter(|row| reorder::needs_reorder(row.on_hand, row.reorder_point))
+            .map(|mut row| {
+                row.daily_outflow = reorder::daily_outflow(row.outflow, days);
+                row.days_of_cover = reorder::days_of_cover(row.on_hand, row.daily_outflow);
+                row.suggested_quantity = reorder::suggested_quantity(row.on_hand, row.reorder_point, row.target_level);
+                row
+            })
+            .collect();
+
+        // Unlimited cover (None) sorts last
+        suggestions.sort_by(|a, b| {
+            (a.days_of_cover.is_none(), a.days_of_cover)
+                .cmp(&(b.days_of_cover.is_none(), b.days_of_cover))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(suggestions)
+    }
+
+    async fn get_reorder_alert_recipients(&self) -> Result<Vec<User>, sqlx::Error> {
+        let users = sqlx::query_as::<_, User>(
+            "SELECT * FROM users
+             WHERE role = 'manager' AND is_active AND verified
+             ORDER BY email"
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
+        Ok(users)
+    }
 }
 
 #[async_trait]
-pub trait SalesExt {
-    async fn create_sale(
+pub trait StockTakeExt {
+    async fn open_stock_take(
         &self,
-        truckload_id: Uuid,
-        shop_id: Uuid,
-        date: NaiveDate,
-        products: Vec<(Uuid, i32)>, 
-    ) -> Result<Sale, sqlx::Error>;
+        warehouse_id: Uuid,
+        notes: Option<&str>,
+        opened_by: Uuid,
+    ) -> Result<StockTake, sqlx::Error>;
 
-    async fn get_daily_product_sales(
-        &self,
-        date: NaiveDate,
-    ) -> Result<Vec<DailyProductSaleResponse>, sqlx::Error>;
+    async fn get_stock_takes(&self) -> Result<Vec<StockTake>, sqlx::Error>;
 
-    async fn get_daily_total_sales_revenue(
-        &self,
-        date: NaiveDate,
-    ) -> Result<f64, sqlx::Error>;
+    async fn get_stock_take(&self, stock_take_id: Uuid) -> Result<Option<StockTake>, sqlx::Error>;
 
-    async fn get_daily_commission(
+    // Records counts against an open take, snapshotting the book quantity
+    // at the time of counting. Counting a line again replaces it.
+    async fn submit_stock_counts(
         &self,
-        date: NaiveDate,
-    ) -> Result<f64, sqlx::Error>;
+        stock_take_id: Uuid,
+        counts: &[StockCountDto],
+        counted_by: Uuid,
+    ) -> Result<(), sqlx::Error>;
 
-    async fn get_pending_payments(&self) -> Result<Vec<PendingPaymentResponse>, sqlx::Error>;
+    async fn get_stock_take_lines(&self, stock_take_id: Uuid) -> Result<Vec<StockTakeLineDto>, sqlx::Error>;
 
-    async fn get_all_sales(&self) -> Result<Vec<SaleDto>, sqlx::Error>;
+    // Closes an open take and raises a count correction for every variance
+    async fn post_stock_take(
+        &self,
+        stock_take_id: Uuid,
+        posted_by: Uuid,
+        approval_limit: Decimal,
+    ) -> Result<(StockTake, Vec<StockAdjustment>), sqlx::Error>;
 
+    async fn cancel_stock_take(
+        &self,
+        stock_take_id: Uuid,
+        cancelled_by: Uuid,
+    ) -> Result<StockTake, sqlx::Error>;
 }
+
 #[async_trait]
-impl SalesExt for DBClient {
-    async fn create_sale(
+impl StockTakeExt for DBClient {
+    async fn open_stock_take(
         &self,
-        truckload_id: Uuid,
-        shop_id: Uuid,
-        date: NaiveDate,
-        products: Vec<(Uuid, i32)>, // (product_id, quantity)
-    ) -> Result<Sale, sqlx::Error> {
-        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+        warehouse_id: Uuid,
+        notes: Option<&str>,
+        opened_by: Uuid,
+    ) -> Result<StockTake, sqlx::Error> {
+        let stock_take = sqlx::query_as::<_, StockTake>(
+            "INSERT INTO stock_takes (warehouse_id, notes, opened_by) VALUES ($1, $2, $3) RETURNING *"
+        )
+        .bind(warehouse_id)
+        .bind(notes)
+        .bind(opened_by)
+        .fetch_one(&self.pool)
+        .await?;
 
-        // ✅ Step 1: Calculate total amount
-        let mut total_amount: f64 = 0.0;
+        Ok(stock_take)
+    }
 
-        for (product_id, quantity) in &products {
-            let price: f64 = sqlx::query_scalar(
-                "SELECT price FROM products WHERE id = $1"
-            )
-            .bind(product_id)
-            .fetch_one(&mut *tx)
+    async fn get_stock_takes(&self) -> Result<Vec<StockTake>, sqlx::Error> {
+        let stock_takes = sqlx::query_as::<_, StockTake>(
+            "SELECT * FROM stock_takes ORDER BY opened_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stock_takes)
+    }
+
+    async fn get_stock_take(&self, stock_take_id: Uuid) -> Result<Option<StockTake>, sqlx::Error> {
+        let stock_take = sqlx::query_as::<_, StockTake>("SELECT * FROM stock_takes WHERE id = $1")
+            .bind(stock_take_id)
+            .fetch_optional(&self.pool)
             .await?;
 
-            total_amount += price * (*quantity as f64);
-        }
+        Ok(stock_take)
+    }
 
-        // ✅ Step 2: Insert into sales table with total_amount and paid_amount = 0
-        let sale = sqlx::query_as::<_, Sale>(
-            "INSERT INTO sales (truckloadid, shopid, date, status, total_amount, paid_amount)
-             VALUES ($1, $2, $3, 'pending', $4, 0)
-             RETURNING *"
+    async fn submit_stock_counts(
+        &self,
+        stock_take_id: Uuid,
+        counts: &[StockCountDto],
+        counted_by: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Holds off posting until these counts are in
+        let take: Option<(String, Uuid)> = sqlx::query_as(
+            "SELECT status, warehouse_id FROM stock_takes WHERE id = $1 FOR UPDATE"
         )
-        .bind(truckload_id)
-        .bind(shop_id)
-        .bind(date)
-        .bind(total_amount)
-        .fetch_one(&mut *tx)
+        .bind(stock_take_id)
+        .fetch_optional(&mut *tx)
         .await?;
 
-        // ✅ Step 3: Insert into sales_product table
-        for (product_id, quantity) in &products {
+        let Some((status, warehouse_id)) = take else {
+            return Err(sqlx::Error::RowNotFound);
+        };
+        if status != StockTakeStatus::Open.to_str() {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        for count in counts {
+            // A product is counted either by batch or as a whole, never both
+            let mixed: bool = sqlx::query_scalar(
+                "SELECT EXISTS(
+                    SELECT 1 FROM stock_take_lines
+                    WHERE stock_take_id = $1 AND productid = $2
+                      AND (batch_id IS NULL) <> ($3::UUID IS NULL)
+                 )"
+            )
+            .bind(stock_take_id)
+            .bind(count.product_id)
+            .bind(count.batch_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if mixed {
+                return Err(sqlx::Error::Protocol(format!(
+                    "Product {} is already counted {}",
+                    count.product_id,
+                    if count.batch_id.is_some() { "as a whole" } else { "by batch" }
+                )));
+            }
+
+            let system_quantity: Option<i32> = match count.batch_id {
+                Some(batch_id) => {
+                    sqlx::query_scalar(
+                        "SELECT quantity FROM stock_batches WHERE id = $1 AND productid = $2 AND warehouse_id = $3"
+                    )
+                    .bind(batch_id)
+                    .bind(count.product_id)
+                    .bind(warehouse_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                }
+                None => {
+                    sqlx::query_scalar(
+                        "SELECT COALESCE((
+                            SELECT quantity FROM warehouse_stock WHERE productid = p.id AND warehouse_id = $2
+                         ), 0)
+                         FROM products p WHERE p.id = $1"
+                    )
+                    .bind(count.product_id)
+                    .bind(warehouse_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                }
+            };
+
+            let Some(system_quantity) = system_quantity else {
+                return Err(sqlx::Error::RowNotFound);
+            };
+
             sqlx::query(
-                "INSERT INTO sales_product (salesid, productid, quantity)
-                 VALUES ($1, $2, $3)"
+                "DELETE FROM stock_take_lines
+                 WHERE stock_take_id = $1 AND productid = $2 AND batch_id IS NOT DISTINCT FROM $3"
             )
-            .bind(sale.salesid)
-            .bind(product_id)
-            .bind(quantity)
+            .bind(stock_take_id)
+            .bind(count.product_id)
+            .bind(count.batch_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "INSERT INTO stock_take_lines (stock_take_id, productid, batch_id, system_quantity, counted_quantity, counted_by)
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(stock_take_id)
+            .bind(count.product_id)
+            .bind(count.batch_id)
+            .bind(system_quantity)
+            .bind(count.counted_quantity)
+            .bind(counted_by)
             .execute(&mut *tx)
             .await?;
         }
 
-        // ✅ Step 4: Commit transaction
         tx.commit().await?;
-
-        Ok(sale)
+        Ok(())
     }
 
-   async fn get_daily_product_sales(
-        &self,
-        date: NaiveDate,
-    ) -> Result<Vec<DailyProductSaleResponse>, sqlx::Error> {
-        let sales = sqlx::query_as::<_, DailyProductSaleResponse>(
-            r#"
-            SELECT 
-                p.name AS product_name,
-                SUM(sp.quantity) AS total_quantity,
-                SUM(sp.quantity * p.price) AS total_amount
-            FROM sales s
-            JOIN sales_product sp ON s.salesid = sp.salesid
-            JOIN products p ON sp.productid = p.id
-            WHERE s.date = $1
-            GROUP BY p.name
-            ORDER BY total_quantity DESC
-            "#
+    async fn get_stock_take_lines(&self, stock_take_id: Uuid) -> Result<Vec<StockTakeLineDto>, sqlx::Error> {
+        let lines = sqlx::query_as::<_, StockTakeLineDto>(
+            "SELECT l.productid AS product_id, p.name AS product_name, p.sku,
+                    l.batch_id, sb.batch_number, l.system_quantity, l.counted_quantity,
+                    (l.counted_quantity - l.system_quantity) AS variance,
+                    pp.price AS unit_price,
+                    ROUND((l.counted_quantity - l.system_quantity) * pp.price, 2) AS variance_value
+             FROM stock_take_lines l
+             JOIN products p ON p.id = l.productid
+             -- Valued at the price in force on the day the line was counted
+             JOIN LATERAL (
+                 SELECT price FROM product_prices
+                 WHERE product_id = l.productid AND effective_from <= l.counted_at::DATE
+                 ORDER BY effective_from DESC
+                 LIMIT 1
+             ) pp ON TRUE
+             LEFT JOIN stock_batches sb ON sb.id = l.batch_id
+             WHERE l.stock_take_id = $1
+             ORDER BY p.name, sb.expiry_date NULLS FIRST, sb.batch_number"
         )
-        .bind(date)
+        .bind(stock_take_id)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(sales)
+        Ok(lines)
     }
 
-    async fn get_daily_total_sales_revenue(
+    async fn post_stock_take(
         &self,
-        date: NaiveDate,
-    ) -> Result<f64, sqlx::Error> {
-        let total_revenue: f64 = sqlx::query_scalar(
-            r#"
-            SELECT COALESCE(SUM(paid_amount), 0)
-            FROM sales
-            WHERE date = $1
-            "#
+        stock_take_id: Uuid,
+        posted_by: Uuid,
+        approval_limit: Decimal,
+    ) -> Result<(StockTake, Vec<StockAdjustment>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let stock_take = sqlx::query_as::<_, StockTake>(
+            "UPDATE stock_takes
+             SET status = $2, closed_by = $3, closed_at = NOW()
+             WHERE id = $1 AND status = 'open'
+             RETURNING *"
         )
-        .bind(date)
-        .fetch_one(&self.pool)
+        .bind(stock_take_id)
+        .bind(StockTakeStatus::Posted.to_str())
+        .bind(posted_by)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+        let variances: Vec<(Uuid, Option<Uuid>, i32, NaiveDate)> = sqlx::query_as(
+            "SELECT productid, batch_id, counted_quantity - system_quantity, counted_at::DATE
+             FROM stock_take_lines
+             WHERE stock_take_id = $1 AND counted_quantity <> system_quantity"
+        )
+        .bind(stock_take_id)
+        .fetch_all(&mut *tx)
         .await?;
 
-        Ok(total_revenue)
+        let mut adjustments = Vec::new();
+        for (product_id, batch_id, variance, counted_on) in variances {
+            // A shortfall counted for the whole product comes off the batches
+            // soonest to expire; a surplus goes into the LEGACY batch
+            let corrections: Vec<(Option<Uuid>, i32)> = if batch_id.is_some() || variance > 0 {
+                vec![(batch_id, variance)]
+            } else {
+                let batches: Vec<(Uuid, i32)> = sqlx::query_as(
+                    "SELECT id, quantity FROM stock_batches
+                     WHERE productid = $1 AND warehouse_id = $2 AND quantity > 0
+                     ORDER BY expiry_date NULLS LAST, created_at
+                     FOR UPDATE"
+                )
+                .bind(product_id)
+                .bind(stock_take.warehouse_id)
+                .fetch_all(&mut *tx)
+                .await?;
+
+                // If the batches can't cover it, the LEGACY batch's CHECK fails the post
+                match fefo::allocate(&batches, -variance) {
+                    Some(picks) => picks.into_iter().map(|(id, taken)| (Some(id), -taken)).collect(),
+                    None => vec![(None, variance)],
+                }
+            };
+
+            // Valued as on the variance report, and approved or held as one
+            // line however many batches it's split across
+            let unit_price = price_on(&mut tx, product_id, counted_on)
+                .await?
+                .ok_or(sqlx::Error::RowNotFound)?;
+            let quantities: Vec<i32> = corrections.iter().map(|&(_, quantity)| quantity).collect();
+            let status = approval::adjustment_status(unit_price, &quantities, approval_limit);
+
+            for (batch_id, quantity) in corrections {
+                let correction = CreateStockAdjustmentDto {
+                    product_id,
+                    warehouse_id: Some(stock_take.warehouse_id),
+                    batch_id,
+                    quantity,
+                    reason: AdjustmentReason::CountCorrection,
+                    notes: Some(format!("Stock take {}", stock_take_id)),
+                };
+                adjustments.push(
+                    raise_adjustment(
+                        &mut tx,
+                        &correction,
+                        stock_take.warehouse_id,
+                        Some((stock_take_id, counted_on)),
+                        posted_by,
+                        unit_price,
+                        status,
+                    )
+                    .await?
+                );
+            }
+        }
+
+        tx.commit().await?;
+        Ok((stock_take, adjustments))
+    }
+
+    async fn cancel_stock_take(
+        &self,
+        stock_take_id: Uuid,
+        cancelled_by: Uuid,
+    ) -> Result<StockTake, sqlx::Error> {
+        let stock_take = sqlx::query_as::<_, StockTake>(
+            "UPDATE stock_takes
+             SET status = $2, closed_by = $3, closed_at = NOW()
+             WHERE id = $1 AND status = 'open'
+             RETURNING *"
+        )
+        .bind(stock_take_id)
+        .bind(StockTakeStatus::Cancelled.to_str())
+        .bind(cancelled_by)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(stock_take)
     }
+}
 
+#[async_trait]
+pub trait WarehouseExt {
+    async fn create_warehouse(&self, name: &str, address: Option<&str>) -> Result<Warehouse, sqlx::Error>;
 
-    async fn get_daily_commission(
+    async fn get_warehouses(&self) -> Result<Vec<Warehouse>, sqlx::Error>;
+
+    // `None` looks up the default warehouse
+    async fn get_warehouse(&self, warehouse_id: Option<Uuid>) -> Result<Option<Warehouse>, sqlx::Error>;
+
+    // Moves stock between warehouses in one transaction. Each line keeps its
+    // batch identity: stock leaves a source batch and lands in the batch with
+    // the same number and expiry at the destination.
+    async fn create_stock_transfer(
         &self,
-        date: NaiveDate,
-    ) -> Result<f64, sqlx::Error> {
-        let total_commission: Option<f64> = sqlx::query_scalar(
-            r#"
-            SELECT 
-                SUM(sp.quantity * p.commission) AS total_commission
-            FROM sales s
-            JOIN sales_product sp ON s.salesid = sp.salesid
-            JOIN products p ON sp.productid = p.id
-            WHERE s.date = $1
-            "#,
+        transfer: &CreateStockTransferDto,
+        created_by: Uuid,
+    ) -> Result<(StockTransfer, Vec<StockTransferLineResponse>), sqlx::Error>;
+
+    async fn get_stock_transfers(&self, warehouse_id: Option<Uuid>) -> Result<Vec<StockTransfer>, sqlx::Error>;
+
+    async fn get_stock_transfer(&self, transfer_id: Uuid) -> Result<Option<StockTransfer>, sqlx::Error>;
+
+    async fn get_stock_transfer_lines(&self, transfer_id: Uuid) -> Result<Vec<StockTransferLineResponse>, sqlx::Error>;
+}
+
+#[async_trait]
+impl WarehouseExt for DBClient {
+    async fn create_warehouse(&self, name: &str, address: Option<&str>) -> Result<Warehouse, sqlx::Error> {
+        let warehouse = sqlx::query_as::<_, Warehouse>(
+            "INSERT INTO warehouses (name, address) VALUES ($1, $2) RETURNING *"
         )
-        .bind(date)
+        .bind(name)
+        .bind(address)
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(total_commission.unwrap_or(0.0))
+        Ok(warehouse)
     }
 
-    async fn get_pending_payments(&self) -> Result<Vec<PendingPaymentResponse>, sqlx::Error> {
-        let payments = sqlx::query_as::<_, PendingPaymentResponse>(
-            r#"
-            SELECT 
-                s.salesid,
-                s.total_amount,
-                s.paid_amount,
-                (s.total_amount - s.paid_amount) AS remaining_amount,
-                sh.name AS shop_name,
-                sh.address AS shop_address
-            FROM sales s
-            JOIN shops sh ON s.shopid = sh.shopid
-            WHERE s.status = 'pending'
-            ORDER BY s.date DESC
-            "#
+    async fn get_warehouses(&self) -> Result<Vec<Warehouse>, sqlx::Error> {
+        let warehouses = sqlx::query_as::<_, Warehouse>(
+            "SELECT * FROM warehouses ORDER BY is_default DESC, name"
         )
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(payments)
+        Ok(warehouses)
     }
 
-    async fn get_all_sales(&self) -> Result<Vec<SaleDto>, sqlx::Error> {
-        let rows = sqlx::query!(
-            r#"
-            SELECT salesid, truckloadid, shopid, date, total_amount, paid_amount, status
-            FROM sales
-            ORDER BY date DESC
-            "#
+    async fn get_warehouse(&self, warehouse_id: Option<Uuid>) -> Result<Option<Warehouse>, sqlx::Error> {
+        let warehouse = sqlx::query_as::<_, Warehouse>(
+            "SELECT * FROM warehouses WHERE ($1::UUID IS NULL AND is_default) OR id = $1"
         )
-        .fetch_all(&self.pool)
+        .bind(warehouse_id)
+        .fetch_optional(&self.pool)
         .await?;
 
-        // Map SQL rows to DTO
-        let sales = rows.into_iter().map(|r| SaleDto {
-            salesid: r.salesid,
-            truckload_id: r.truckloadid,
-            shop_id: r.shopid,
-            date: r.date,
-            total_amount: r.total_amount.unwrap_or(0.0), 
-            paid_amount: r.paid_amount.unwrap_or(0.0), 
-            status: r.status,
-        }).collect();
-
-        Ok(sales)
+        Ok(warehouse)
     }
 
-}
+    async fn create_stock_transfer(
+        &self,
+        transfer: &CreateStockTransferDto,
+        created_by: Uuid,
+    ) -> Result<(StockTransfer, Vec<StockTransferLineResponse>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
 
+        let created = sqlx::query_as::<_, StockTransfer>(
+            "INSERT INTO stock_transfers (from_warehouse_id, to_warehouse_id, date, notes, created_by)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING *"
+        )
+        .bind(transfer.from_warehouse_id)
+        .bind(transfer.to_warehouse_id)
+        .bind(transfer.date)
+        .bind(&transfer.notes)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
 
+        for line in &transfer.lines {
+            // The named batch, or unexpired batches soonest expiry first
+            let batches: Vec<(Uuid, i32)> = sqlx::query_as(
+                "SELECT id, quantity FROM stock_batches
+                 WHERE productid = $1 AND warehouse_id = $2 AND quantity > 0
+                   AND ($3::UUID IS NULL OR id = $3)
+                   AND ($3::UUID IS NOT NULL OR expiry_date IS NULL OR expiry_date >= $4)
+                 ORDER BY expiry_date NULLS LAST, created_at
+                 FOR UPDATE"
+            )
+            .bind(line.product_id)
+            .bind(transfer.from_warehouse_id)
+            .bind(line.batch_id)
+            .bind(transfer.date)
+            .fetch_all(&mut *tx)
+            .await?;
 
+            let Some(picks) = fefo::allocate(&batches, line.quantity) else {
+                return Err(sqlx::Error::Protocol(format!(
+                    "Not enough stock of product {} in the source warehouse",
+                    line.product_id
+                )));
+            };
+
+            for (from_batch_id, taken) in picks {
+                sqlx::query("UPDATE stock_batches SET quantity = quantity - $2 WHERE id = $1")
+                    .bind(from_batch_id)
+                    .bind(taken)
+                    .execute(&mut *tx)
+                    .await?;
+
+                let to_batch_id: Option<Uuid> = sqlx::query_scalar(
+                    "INSERT INTO stock_batches (warehouse_id, productid, batch_number, production_date, expiry_date, quantity)
+                     SELECT $1, productid, batch_number, production_date, expiry_date, $3
+                     FROM stock_batches WHERE id = $2
+                     ON CONFLICT (warehouse_id, productid, batch_number) DO UPDATE
+                     SET quantity = stock_batches.quantity + EXCLUDED.quantity
+                     WHERE stock_batches.expiry_date IS NOT DISTINCT FROM EXCLUDED.expiry_date
+                     RETURNING id"
+                )
+                .bind(transfer.to_warehouse_id)
+                .bind(from_batch_id)
+                .bind(taken)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                let Some(to_batch_id) = to_batch_id else {
+                    return Err(sqlx::Error::Protocol(format!(
+                        "A batch of product {} has a different expiry date in the destination warehouse",
+                        line.product_id
+                    )));
+                };
+
+                sqlx::query(
+                    "INSERT INTO stock_transfer_lines (transfer_id, productid, from_batch_id, to_batch_id, quantity)
+                     VALUES ($1, $2, $3, $4, $5)"
+                )
+                .bind(created.id)
+                .bind(line.product_id)
+                .bind(from_batch_id)
+                .bind(to_batch_id)
+                .bind(taken)
+                .execute(&mut *tx)
+                .await?;
+
+                for (warehouse_id, batch_id, quantity, reason) in [
+                    (transfer.from_warehouse_id, from_batch_id, -taken, StockMovementReason::TransferOut),
+                    (transfer.to_warehouse_id, to_batch_id, taken, StockMovementReason::TransferIn),
+                ] {
+                    record_movement(&mut tx, NewStockMovement {
+                        productid: line.product_id,
+                        warehouse_id,
+                        batch_id: Some(batch_id),
+                        quantity,
+                        reason,
+                        reference: MovementRef::Transfer(created.id),
+                        user_id: created_by,
+                        occurred_on: transfer.date,
+                    })
+                    .await?;
+                }
+            }
 
-#[async_trait]
-pub trait PaymentExt {
-    async fn create_payment(
-        &self,
-        salesid: Uuid,
-        amount: f64,
-        method: String,
-        date: NaiveDate,
-    ) -> Result<Payment, sqlx::Error>;
+            add_warehouse_stock(&mut tx, transfer.from_warehouse_id, line.product_id, -line.quantity).await?;
+            add_warehouse_stock(&mut tx, transfer.to_warehouse_id, line.product_id, line.quantity).await?;
+        }
 
-}
+        tx.commit().await?;
 
-#[async_trait]
-impl PaymentExt for DBClient {
-   async fn create_payment(
-    &self,
-    salesid: Uuid,
-    amount: f64,
-    method: String,
-    date: NaiveDate,
-) -> Result<Payment, sqlx::Error> {
-    let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
+        let lines = self.get_stock_transfer_lines(created.id).await?;
+        Ok((created, lines))
+    }
 
-    // 1️⃣ Insert payment
-    let payment = sqlx::query_as::<_, Payment>(
-        "INSERT INTO payment (salesid, amount, method, date, created_at, updated_at)
-         VALUES ($1, $2, $3, $4, NOW(), NOW())
-         RETURNING *"
-    )
-    .bind(salesid)
-    .bind(amount)
-    .bind(method)
-    .bind(date)
-    .fetch_one(&mut *tx)
-    .await?;
+    async fn get_stock_transfers(&self, warehouse_id: Option<Uuid>) -> Result<Vec<StockTransfer>, sqlx::Error> {
+        let transfers = sqlx::query_as::<_, StockTransfer>(
+            "SELECT * FROM stock_transfers
+             WHERE $1::UUID IS NULL OR from_warehouse_id = $1 OR to_warehouse_id = $1
+             ORDER BY date DESC, created_at DESC"
+        )
+        .bind(warehouse_id)
+        .fetch_all(&self.pool)
+        .await?;
 
-    // 2️⃣ Update paid_amount in sales table
-    sqlx::query(
-        "UPDATE sales
-         SET paid_amount = COALESCE(paid_amount, 0) + $1,
-             updated_at = NOW()
-         WHERE salesid = $2"
-    )
-    .bind(amount)
-    .bind(salesid)
-    .execute(&mut *tx)
-    .await?;
+        Ok(transfers)
+    }
 
-     sqlx::query(
-        "UPDATE sales
-         SET status = 'paid', updated_at = NOW()
-         WHERE salesid = $1 AND COALESCE(paid_amount, 0) >= COALESCE(total_amount, 0)"
-    )
-    .bind(salesid)
-    .execute(&mut *tx)
-    .await?;
+    async fn get_stock_transfer(&self, transfer_id: Uuid) -> Result<Option<StockTransfer>, sqlx::Error> {
+        let transfer = sqlx::query_as::<_, StockTransfer>("SELECT * FROM stock_transfers WHERE id = $1")
+            .bind(transfer_id)
+            .fetch_optional(&self.pool)
+            .await?;
 
-    // 3️⃣ Commit transaction
-    tx.commit().await?;
+        Ok(transfer)
+    }
 
-    Ok(payment)
+    async fn get_stock_transfer_lines(&self, transfer_id: Uuid) -> Result<Vec<StockTransferLineResponse>, sqlx::Error> {
+        let lines = sqlx::query_as::<_, StockTransferLineResponse>(
+            "SELECT l.productid AS product_id, p.name AS product_name, sb.batch_number, sb.expiry_date,
+                    l.from_batch_id, l.to_batch_id, l.quantity
+             FROM stock_transfer_lines l
+             JOIN products p ON p.id = l.productid
+             JOIN stock_batches sb ON sb.id = l.from_batch_id
+             WHERE l.transfer_id = $1
+             ORDER BY p.name, sb.expiry_date NULLS LAST"
+        )
+        .bind(transfer_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
 }
 
+#[async_trait]
+pub trait SupplierExt {
+    async fn create_supplier(&self, supplier: &CreateSupplierDto) -> Result<Supplier, sqlx::Error>;
 
-}
+    async fn get_suppliers(
+        &self,
+        kind: Option<SupplierKind>,
+        active: Option<bool>,
+    ) -> Result<Vec<Supplier>, sqlx::Error>;
 
+    async fn get_supplier(&self, supplier_id: Uuid) -> Result<Option<Supplier>, sqlx::Error>;
 
-#[async_trait]
-pub trait AllowanceExt {
-    async fn create_allowance(
+    async fn update_supplier(
         &self,
-        date: NaiveDate,
-        amount: f64,
-        notes: Option<String>,
-    ) -> Result<Allowance, sqlx::Error>;
-    async fn create_truck_allowance(
-    &self,
-    truckid: Uuid,
-    date: NaiveDate,
-    amount: f64,
-) -> Result<TruckAllowance, sqlx::Error>;
+        supplier_id: Uuid,
+        changes: &UpdateSupplierDto,
+    ) -> Result<Option<Supplier>, sqlx::Error>;
 
+    // A supplier's deliveries between the optional dates, newest first
+    async fn get_supplier_deliveries(
+        &self,
+        supplier_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Delivery>, sqlx::Error>;
 
-    async fn get_allowance_distribution_by_date(
+    // Units received from a supplier per product over the same range
+    async fn get_supplier_product_totals(
         &self,
-        date: chrono::NaiveDate,
-    ) -> Result<AllowanceDistributionResponse, sqlx::Error>;
+        supplier_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<SupplierProductTotalDto>, sqlx::Error>;
+
+    // Delivery count and units per supplier; suppliers with nothing in the
+    // range are included with zeroes
+    async fn get_supplier_totals(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<SupplierTotalDto>, sqlx::Error>;
 }
-  
 
 #[async_trait]
-impl AllowanceExt for DBClient {
-    async fn create_allowance(
-        &self,
-        date: NaiveDate,
-        amount: f64,
-        notes: Option<String>,
-    ) -> Result<Allowance, sqlx::Error> {
-        let mut tx: Transaction<'_, Postgres> = self.pool.begin().await?;
-
-        let allowance = sqlx::query_as::<_, Allowance>(
-            "INSERT INTO allowance (date, amount, notes, created_at, updated_at)
-             VALUES ($1, $2, $3, NOW(), NOW())
+impl SupplierExt for DBClient {
+    async fn create_supplier(&self, supplier: &CreateSupplierDto) -> Result<Supplier, sqlx::Error> {
+        let created = sqlx::query_as::<_, Supplier>(
+            "INSERT INTO suppliers (name, kind, contact_number, address)
+             VALUES ($1, $2, $3, $4)
              RETURNING *"
         )
-        .bind(date)
-        .bind(amount)
-        .bind(notes)
-        .fetch_one(&mut *tx)
+        .bind(supplier.name.trim())
+        .bind(supplier.kind.to_str())
+        .bind(&supplier.contact_number)
+        .bind(&supplier.address)
+        .fetch_one(&self.pool)
         .await?;
 
-        tx.commit().await?;
-
-        Ok(allowance)
+        Ok(created)
     }
 
+    async fn get_suppliers(
+        &self,
+        kind: Option<SupplierKind>,
+        active: Option<bool>,
+    ) -> Result<Vec<Supplier>, sqlx::Error> {
+        let suppliers = sqlx::query_as::<_, Supplier>(
+            "SELECT * FROM suppliers
+             WHERE ($1::VARCHAR IS NULL OR kind = $1)
+               AND ($2::BOOLEAN IS NULL OR is_active = $2)
+             ORDER BY name"
+        )
+        .bind(kind.map(SupplierKind::to_str))
+        .bind(active)
+        .fetch_all(&self.pool)
+        .await?;
 
-    async fn create_truck_allowance(
-    &self,
-    truckid: Uuid,
-    date: NaiveDate,
-    amount: f64,
-) -> Result<TruckAllowance, SqlxError> {
-    let mut tx = self.pool.begin().await?;
-
-    // 1. Get truck max_allowance
-    let max_allowance: f64 = sqlx::query_scalar(
-        "SELECT max_allowance FROM trucks WHERE truckid = $1"
-    )
-    .bind(truckid)
-    .fetch_one(&mut *tx)
-    .await?;
-
-    if amount > max_allowance {
-        return Err(SqlxError::Protocol(
-            "Allowance exceeds truck max limit".into()
-        ));
+        Ok(suppliers)
     }
 
-    // 2. Get allowanceid from allowance table using date
-    let allowanceid: Uuid = sqlx::query_scalar(
-        "SELECT allowanceid FROM allowance WHERE date = $1"
-    )
-    .bind(date)
-    .fetch_one(&mut *tx)
-    .await?;
-
-    // 3. Insert into truck_allowance
-    let truck_allowance = sqlx::query_as::<_, TruckAllowance>(
-        "INSERT INTO truck_allowance (allowanceid, truckid, amount, created_at, updated_at)
-         VALUES ($1, $2, $3, NOW(), NOW())
-         RETURNING *"
-    )
-    .bind(allowanceid)
-    .bind(truckid)
-    .bind(amount)
-    .fetch_one(&mut *tx)
-    .await?;
+    async fn get_supplier(&self, supplier_id: Uuid) -> Result<Option<Supplier>, sqlx::Error> {
+        let supplier = sqlx::query_as::<_, Supplier>("SELECT * FROM suppliers WHERE id = $1")
+            .bind(supplier_id)
+            .fetch_optional(&self.pool)
+            .await?;
 
-    tx.commit().await?;
-    Ok(truck_allowance)
-}
+        Ok(supplier)
+    }
 
+    async fn update_supplier(
+        &self,
+        supplier_id: Uuid,
+        changes: &UpdateSupplierDto,
+    ) -> Result<Option<Supplier>, sqlx::Error> {
+        let supplier = sqlx::query_as::<_, Supplier>(
+            "UPDATE suppliers
+             SET name = COALESCE($2, name),
+                 kind = COALESCE($3, kind),
+                 contact_number = COALESCE($4, contact_number),
+                 address = COALESCE($5, address),
+                 is_active = COALESCE($6, is_active)
+             WHERE id = $1
+             RETURNING *"
+        )
+        .bind(supplier_id)
+        .bind(changes.name.as_deref().map(str::trim))
+        .bind(changes.kind.map(SupplierKind::to_str))
+        .bind(&changes.contact_number)
+        .bind(&changes.address)
+        .bind(changes.is_active)
+        .fetch_optional(&self.pool)
+        .await?;
 
+        Ok(supplier)
+    }
 
-    async fn get_allowance_distribution_by_date(
+    async fn get_supplier_deliveries(
         &self,
-        date: chrono::NaiveDate,
-    ) -> Result<AllowanceDistributionResponse, sqlx::Error> {
-        // Get the allowance record for the given date
-        let row = sqlx::query!(
-            "SELECT allowanceid, amount FROM allowance WHERE date = $1",
-            date
+        supplier_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<Delivery>, sqlx::Error> {
+        let deliveries = sqlx::query_as::<_, Delivery>(
+            "SELECT * FROM deliveries
+             WHERE supplier_id = $1
+               AND ($2::DATE IS NULL OR date >= $2)
+               AND ($3::DATE IS NULL OR date <= $3)
+             ORDER BY date DESC"
         )
-        .fetch_one(&self.pool)
+        .bind(supplier_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
         .await?;
 
-        let allowanceid = row.allowanceid;
-        let total_amount = row.amount;
+        Ok(deliveries)
+    }
 
-        // Get truck allocations
-        let distributed: Vec<TruckAllowanceInfo> = sqlx::query_as!(
-            TruckAllowanceInfo,
-            r#"
-            SELECT t.trucknumber, ta.amount
-            FROM truck_allowance ta
-            JOIN trucks t ON ta.truckid = t.truckid
-            WHERE ta.allowanceid = $1
-            ORDER BY t.trucknumber
-            "#,
-            allowanceid
+    async fn get_supplier_product_totals(
+        &self,
+        supplier_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<SupplierProductTotalDto>, sqlx::Error> {
+        let totals = sqlx::query_as::<_, SupplierProductTotalDto>(
+            "SELECT dp.productid AS product_id, p.name AS product_name,
+                    COUNT(DISTINCT d.deliveryid) AS deliveries,
+                    SUM(dp.quantity)::BIGINT AS quantity
+             FROM deliveries d
+             JOIN delivery_product dp ON dp.deliveryid = d.deliveryid
+             JOIN products p ON p.id = dp.productid
+             WHERE d.supplier_id = $1
+               AND ($2::DATE IS NULL OR d.date >= $2)
+               AND ($3::DATE IS NULL OR d.date <= $3)
+             GROUP BY dp.productid, p.name
+             ORDER BY p.name"
         )
+        .bind(supplier_id)
+        .bind(from)
+        .bind(to)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(AllowanceDistributionResponse {
-            date,
-            total_amount,
-            distributed,
-        })
+        Ok(totals)
     }
 
+    async fn get_supplier_totals(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<SupplierTotalDto>, sqlx::Error> {
+        let totals = sqlx::query_as::<_, SupplierTotalDto>(
+            "SELECT s.id AS supplier_id, s.name, s.kind,
+                    COUNT(DISTINCT d.deliveryid) AS deliveries,
+                    COALESCE(SUM(dp.quantity), 0)::BIGINT AS quantity,
+                    MAX(d.date) AS last_delivery
+             FROM suppliers s
+             LEFT JOIN deliveries d ON d.supplier_id = s.id
+                 AND ($1::DATE IS NULL OR d.date >= $1)
+                 AND ($2::DATE IS NULL OR d.date <= $2)
+             LEFT JOIN delivery_product dp ON dp.deliveryid = d.deliveryid
+             GROUP BY s.id, s.name, s.kind
+             ORDER BY quantity DESC, s.name"
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
 
+        Ok(totals)
+    }
 }
 
 #[async_trait]
-pub trait TruckExt {
-    async fn create_truck(
+pub trait MilkExt {
+    async fn create_milk_rate_chart(
         &self,
-        trucknumber: &str,
-        model: &str,
-        max_allowance: Option<f64>,
-    ) -> Result<Truck, sqlx::Error>;
+        chart: &CreateMilkRateChartDto,
+        created_by: Uuid,
+    ) -> Result<(MilkRateChart, Vec<MilkRateChartEntry>), sqlx::Error>;
 
-    async fn get_all_trucks(&self) -> Result<Vec<Truck>, sqlx::Error>;
+    async fn get_milk_rate_charts(&self) -> Result<Vec<MilkRateChart>, sqlx::Error>;
 
-    async fn update_max_allowance(
+    async fn get_milk_rate_chart(
         &self,
-        trucknumber: &str,
-        max_allowance: f64,
-    ) -> Result<Truck, sqlx::Error>;
+        chart_id: Uuid,
+    ) -> Result<Option<(MilkRateChart, Vec<MilkRateChartEntry>)>, sqlx::Error>;
+
+    // Records a collection priced from the chart in force on the day.
+    // Protocol error when accepted milk has no chart to price it.
+    async fn create_milk_collection(
+        &self,
+        collection: &CreateMilkCollectionDto,
+        recorded_by: Uuid,
+    ) -> Result<MilkCollection, sqlx::Error>;
+
+    async fn get_milk_collections(&self, filter: &MilkCollectionQuery) -> Result<Vec<MilkCollection>, sqlx::Error>;
 }
 
 #[async_trait]
-impl TruckExt for DBClient {
-    async fn create_truck(
+impl MilkExt for DBClient {
+    async fn create_milk_rate_chart(
         &self,
-        trucknumber: &str,
-        model: &str,
-        max_allowance: Option<f64>,
-    ) -> Result<Truck, sqlx::Error> {
+        chart: &CreateMilkRateChartDto,
+        created_by: Uuid,
+    ) -> Result<(MilkRateChart, Vec<MilkRateChartEntry>), sqlx::Error> {
         let mut tx = self.pool.begin().await?;
 
-        let truck = sqlx::query_as::<_, Truck>(
-            r#"
-            INSERT INTO trucks (trucknumber, model, max_allowance, created_at, updated_at)
-            VALUES ($1, $2, $3, NOW(), NOW())
-            RETURNING *
-            "#
+        let created = sqlx::query_as::<_, MilkRateChart>(
+            "INSERT INTO milk_rate_charts (name, valid_from, created_by)
+             VALUES ($1, $2, $3)
+             RETURNING *"
         )
-        .bind(trucknumber)
-        .bind(model)
-        .bind(max_allowance)
+        .bind(chart.name.trim())
+        .bind(chart.valid_from)
+        .bind(created_by)
         .fetch_one(&mut *tx)
         .await?;
 
+        let mut entries = Vec::with_capacity(chart.entries.len());
+        for entry in &chart.entries {
+            let row = sqlx::query_as::<_, MilkRateChartEntry>(
+                "INSERT INTO milk_rate_chart_entries (chart_id, min_fat, min_snf, rate)
+                 VALUES ($1, $2, $3, $4)
+                 RETURNING *"
+            )
+            .bind(created.id)
+            .bind(money::round(entry.min_fat))
+            .bind(money::round(entry.min_snf))
+            .bind(money::round(entry.rate))
+            .fetch_one(&mut *tx)
+            .await?;
+            entries.push(row);
+        }
+
         tx.commit().await?;
-        Ok(truck)
+        Ok((created, entries))
     }
 
-    async fn get_all_trucks(&self) -> Result<Vec<Truck>, sqlx::Error> {
-        let trucks = sqlx::query_as::<_, Truck>(
-            "SELECT * FROM trucks ORDER BY created_at DESC"
+    async fn get_milk_rate_charts(&self) -> Result<Vec<MilkRateChart>, sqlx::Error> {
+        let charts = sqlx::query_as::<_, MilkRateChart>(
+            "SELECT * FROM milk_rate_charts ORDER BY valid_from DESC"
         )
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(trucks)
+        Ok(charts)
     }
 
-    async fn update_max_allowance(
+    async fn get_milk_rate_chart(
         &self,
-        trucknumber: &str,
-        max_allowance: f64,
-    ) -> Result<Truck, sqlx::Error> {
-        let updated_truck = sqlx::query_as::<_, Truck>(
-            r#"
-            UPDATE trucks
-            SET max_allowance = $1, updated_at = NOW()
-            WHERE trucknumber = $2
-            RETURNING *
-            "#
+        chart_id: Uuid,
+    ) -> Result<Option<(MilkRateChart, Vec<MilkRateChartEntry>)>, sqlx::Error> {
+        let chart = sqlx::query_as::<_, MilkRateChart>("SELECT * FROM milk_rate_charts WHERE id = $1")
+            .bind(chart_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(chart) = chart else {
+            return Ok(None);
+        };
+
+        let entries = sqlx::query_as::<_, MilkRateChartEntry>(
+            "SELECT * FROM milk_rate_chart_entries WHERE chart_id = $1 ORDER BY min_fat, min_snf"
         )
-        .bind(max_allowance)
-        .bind(trucknumber)
-        .fetch_one(&self.pool)
+        .bind(chart.id)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(updated_truck)
+        Ok(Some((chart, entries)))
     }
-}
-
 
-#[async_trait]
-pub trait ShopExt {
-    async fn create_shop(
+    async fn create_milk_collection(
         &self,
-        name: &str,
-        address: &str,
-        city: Option<&str>,
-        district: Option<&str>,
-        contact_number: Option<&str>,
-    ) -> Result<Shop, sqlx::Error>;
+        collection: &CreateMilkCollectionDto,
+        recorded_by: Uuid,
+    ) -> Result<MilkCollection, sqlx::Error> {
+        // Priced on the readings as stored, to two places
+        let litres = money::round(collection.litres);
+        let fat = money::round(collection.fat);
+        let snf = money::round(collection.snf);
+
+        let mut rejection = if collection.rejected {
+            Some(collection.rejection_reason.clone().unwrap_or_else(|| "Rejected at collection".to_string()))
+        } else if collection.adulteration_result == AdulterationResult::Positive {
+            Some("Adulteration test positive".to_string())
+        } else {
+            None
+        };
 
-    async fn get_all_shops(&self) -> Result<Vec<Shop>, sqlx::Error>;
+        let mut priced: Option<(Uuid, Decimal, Decimal)> = None;
+        if rejection.is_none() {
+            let chart_id: Option<Uuid> = sqlx::query_scalar(
+                "SELECT id FROM milk_rate_charts WHERE valid_from <= $1 ORDER BY valid_from DESC LIMIT 1"
+            )
+            .bind(collection.collected_on)
+            .fetch_optional(&self.pool)
+            .await?;
 
-   
-}
+            let Some(chart_id) = chart_id else {
+                return Err(sqlx::Error::Protocol(format!(
+                    "No milk rate chart is in force on {}",
+                    collection.collected_on
+                )));
+            };
 
-#[async_trait]
-impl ShopExt for DBClient {
-    async fn create_shop(
-        &self,
-        name: &str,
-        address: &str,
-        city: Option<&str>,
-        district: Option<&str>,
-        contact_number: Option<&str>,
-    ) -> Result<Shop, sqlx::Error> {
-        let shop = sqlx::query_as::<_, Shop>(
-            r#"
-            INSERT INTO shops (name, address, city, district, contact_number, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
-            RETURNING *
-            "#
+            let entries = sqlx::query_as::<_, MilkRateChartEntry>(
+                "SELECT * FROM milk_rate_chart_entries WHERE chart_id = $1"
+            )
+            .bind(chart_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+            match milk_rate::rate_for(&entries, fat, snf) {
+                Some(rate) => priced = Some((chart_id, rate, milk_rate::amount(rate, litres))),
+                None => rejection = Some("Below the minimum fat/SNF on the rate chart".to_string()),
+            }
+
+            // amount is NUMERIC(12,2)
+            if priced.is_some_and(|(_, _, amount)| amount > Decimal::new(999_999_999_999, 2)) {
+                return Err(sqlx::Error::Protocol("Collection amount is too large".to_string()));
+            }
+        }
+
+        let created = sqlx::query_as::<_, MilkCollection>(
+            "INSERT INTO milk_collections
+                (supplier_id, collected_on, litres, fat, snf, temperature, adulteration_result,
+                 rejected, rejection_reason, rate_chart_id, rate_per_litre, amount, recorded_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             RETURNING *"
         )
-        .bind(name)
-        .bind(address)
-        .bind(city)
-        .bind(district)
-        .bind(contact_number)
+        .bind(collection.supplier_id)
+        .bind(collection.collected_on)
+        .bind(litres)
+        .bind(fat)
+        .bind(snf)
+        .bind(collection.temperature)
+        .bind(collection.adulteration_result.to_str())
+        .bind(rejection.is_some())
+        .bind(&rejection)
+        .bind(priced.map(|(chart_id, _, _)| chart_id))
+        .bind(priced.map(|(_, rate, _)| rate))
+        .bind(priced.map(|(_, _, amount)| amount))
+        .bind(recorded_by)
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(shop)
+        Ok(created)
     }
 
-    async fn get_all_shops(&self) -> Result<Vec<Shop>, sqlx::Error> {
-        let shops = sqlx::query_as::<_, Shop>(
-            "SELECT * FROM shops ORDER BY created_at DESC"
+    async fn get_milk_collections(&self, filter: &MilkCollectionQuery) -> Result<Vec<MilkCollection>, sqlx::Error> {
+        let collections = sqlx::query_as::<_, MilkCollection>(
+            "SELECT * FROM milk_collections
+             WHERE ($1::UUID IS NULL OR supplier_id = $1)
+               AND ($2::DATE IS NULL OR collected_on >= $2)
+               AND ($3::DATE IS NULL OR collected_on <= $3)
+               AND ($4::BOOLEAN IS NULL OR rejected = $4)
+             ORDER BY collected_on DESC, created_at DESC"
         )
+        .bind(filter.supplier_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.rejected)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(shops)
+        Ok(collections)
     }
-
-
 }
-
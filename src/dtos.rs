@@ -1,14 +1,18 @@
 use core::str;
+use std::fmt;
 use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use validator::Validate;
 use uuid::Uuid;
-use crate::models::{User, UserRole, Product, Delivery};
+use crate::models::{User, UserRole, Product, ProductCategory, ProductPrice, Delivery, PriceList, PriceListItem, StockBatch, StockMovementReason, StockAdjustment, AdjustmentReason, AdjustmentStatus, StockTake, StockTransfer, Supplier, SupplierKind, Warehouse, MilkRateChart, MilkRateChartEntry, MilkCollection, AdulterationResult};
+use crate::utils::barcode::validate_ean;
+use crate::utils::money::{self, validate_non_negative};
 
 
 // Registration, login, user filtering & user responses.
-#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
 pub struct RegisterUserDto {
     #[validate(length(min = 1, message = "First name is required"))]
     pub first_name: String,
@@ -25,11 +29,47 @@ pub struct RegisterUserDto {
     pub city: Option<String>,
     pub district: Option<String>,
     pub contact_number: Option<String>,
-    pub role: Option<UserRole>, 
 }
 
+// Invitations: an admin invites an email with a role, the invitee completes registration.
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct CreateInviteDto {
+    #[validate(length(min = 1, message = "Email is required"), email(message = "Email is invalid"))]
+    pub email: String,
+    pub role: UserRole,
+}
 
-#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InviteResponseDto {
+    pub status: String,
+    pub invite_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+    pub token: String,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct AcceptInviteDto {
+    #[validate(length(min = 1, message = "Invitation token is required"))]
+    pub token: String,
+    #[validate(length(min = 1, message = "First name is required"))]
+    pub first_name: String,
+    #[validate(length(min = 1, message = "Last name is required"))]
+    pub last_name: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub password: String,
+    #[validate(must_match(other = "password", message = "Passwords do not match"))]
+    #[serde(rename = "passwordConfirm")]
+    pub password_confirm: String,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub district: Option<String>,
+    pub contact_number: Option<String>,
+}
+
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
 pub struct LoginUserDto {
     #[validate(length(min = 1, message = "Email is required"), email(message = "Email is invalid"))]
     pub email: String,
@@ -40,6 +80,126 @@ pub struct LoginUserDto {
     pub password: String,
 }
 
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UpdateUserDto {
+    #[validate(length(min = 1, message = "First name cannot be empty"))]
+    pub first_name: Option<String>,
+    #[validate(length(min = 1, message = "Last name cannot be empty"))]
+    pub last_name: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub district: Option<String>,
+    pub contact_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateUserRoleDto {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockIpDto {
+    pub ip: std::net::IpAddr,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct UserPasswordUpdateDto {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub old_password: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub new_password: String,
+    #[validate(must_match(other = "new_password", message = "Passwords do not match"))]
+    pub new_password_confirm: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct VerifyEmailQueryDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+}
+
+#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ForgotPasswordRequestDto {
+    #[validate(length(min = 1, message = "Email is required"), email(message = "Email is invalid"))]
+    pub email: String,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct ResetPasswordRequestDto {
+    #[validate(length(min = 1, message = "Token is required."))]
+    pub token: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub password: String,
+    #[validate(must_match(other = "password", message = "Passwords do not match"))]
+    #[serde(rename = "passwordConfirm")]
+    pub password_confirm: String,
+}
+
+// Passwords and tokens must never reach the logs, so these DTOs only show
+// their non-secret fields when debug-printed.
+impl fmt::Debug for RegisterUserDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisterUserDto")
+            .field("first_name", &self.first_name)
+            .field("last_name", &self.last_name)
+            .field("email", &self.email)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for AcceptInviteDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcceptInviteDto")
+            .field("first_name", &self.first_name)
+            .field("last_name", &self.last_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for LoginUserDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginUserDto")
+            .field("email", &self.email)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for UserPasswordUpdateDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserPasswordUpdateDto").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ResetPasswordRequestDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResetPasswordRequestDto").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for TwoFactorLoginDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TwoFactorLoginDto").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for TwoFactorSetupDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TwoFactorSetupDto").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for TwoFactorCodeDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TwoFactorCodeDto").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for RefreshTokenRequestDto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshTokenRequestDto").finish_non_exhaustive()
+    }
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct RequestQueryDto {
     #[validate(range(min = 1))]
@@ -59,6 +219,8 @@ pub struct FilterUserDto {
     pub city: Option<String>,
     pub district: Option<String>,
     pub contact_number: Option<String>,
+    pub verified: bool,
+    pub is_active: bool,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
     #[serde(rename = "updatedAt")]
@@ -77,6 +239,8 @@ impl FilterUserDto {
             city: user.city.clone(),
             district: user.district.clone(),
             contact_number: user.contact_number.clone(),
+            verified: user.verified,
+            is_active: user.is_active,
             created_at: user.created_at.unwrap(),
             updated_at: user.updated_at.unwrap(),
         }
@@ -106,6 +270,60 @@ pub struct UserListResponseDto {
 pub struct UserLoginResponseDto {
     pub status: String,
     pub token: String,
+    pub refresh_token: String,
+}
+
+// Returned instead of tokens when the password was right but a second factor
+// is still needed; `status` is "2fa_required" or "2fa_setup_required".
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorChallengeDto {
+    pub status: String,
+    pub challenge_token: String,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct TwoFactorLoginDto {
+    #[validate(length(min = 1, message = "Challenge token is required"))]
+    pub challenge_token: String,
+    // A current TOTP code or one of the recovery codes
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct TwoFactorSetupDto {
+    #[validate(length(min = 1, message = "Challenge token is required"))]
+    pub challenge_token: String,
+}
+
+#[derive(Validate, Default, Clone, Serialize, Deserialize)]
+pub struct TwoFactorCodeDto {
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorEnrolmentDto {
+    pub status: String,
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesDto {
+    pub status: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RefreshTokenRequestDto {
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeSessionsResponseDto {
+    pub status: String,
+    pub revoked: u64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -115,12 +333,113 @@ pub struct Response {
 }
 // Product creation and product list responses.
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, Validate)]
 pub struct CreateProductDto {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
     pub name: String,
-    pub price: f64,
+    #[validate(custom(function = "validate_non_negative", message = "Price cannot be negative"))]
+    pub price: Decimal,
+    #[validate(length(min = 1, message = "Unit type cannot be empty"))]
     pub unit_type: String,
-    pub commission: Option<f64>,
+    #[validate(custom(function = "validate_non_negative", message = "Commission cannot be negative"))]
+    pub commission: Option<Decimal>,
+    pub category_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 64, message = "SKU must be 1-64 characters"))]
+    pub sku: String,
+    #[validate(custom(function = "validate_ean", message = "Barcode must be a valid EAN-8 or EAN-13"))]
+    pub barcode: Option<String>,
+    #[validate(custom(function = "validate_pack_size", message = "Pack size must be greater than zero"))]
+    pub pack_size: Option<Decimal>,
+    // Defaults to unit_type
+    #[validate(length(min = 1, max = 20, message = "Base unit must be 1-20 characters"))]
+    pub base_unit: Option<String>,
+    // VAT percent (default 0, zero-rated)
+    #[validate(custom(function = "validate_tax_rate", message = "Tax rate must be between 0 and 100"))]
+    pub tax_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProductDto {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, message = "Unit type cannot be empty"))]
+    pub unit_type: Option<String>,
+    #[validate(custom(function = "validate_non_negative", message = "Commission cannot be negative"))]
+    pub commission: Option<Decimal>,
+    #[validate(custom(function = "validate_non_negative", message = "Price cannot be negative"))]
+    pub price: Option<Decimal>,
+    // Date the new price starts to apply (default: today); earlier sales keep the old price
+    pub price_effective_from: Option<NaiveDate>,
+    pub category_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 64, message = "SKU must be 1-64 characters"))]
+    pub sku: Option<String>,
+    #[validate(custom(function = "validate_ean", message = "Barcode must be a valid EAN-8 or EAN-13"))]
+    pub barcode: Option<String>,
+    #[validate(custom(function = "validate_pack_size", message = "Pack size must be greater than zero"))]
+    pub pack_size: Option<Decimal>,
+    #[validate(length(min = 1, max = 20, message = "Base unit must be 1-20 characters"))]
+    pub base_unit: Option<String>,
+    #[validate(custom(function = "validate_tax_rate", message = "Tax rate must be between 0 and 100"))]
+    pub tax_rate: Option<Decimal>,
+}
+
+fn validate_tax_rate(rate: &Decimal) -> Result<(), validator::ValidationError> {
+    if *rate < Decimal::ZERO || *rate > Decimal::ONE_HUNDRED {
+        return Err(validator::ValidationError::new("invalid_tax_rate"));
+    }
+    Ok(())
+}
+
+// Both levels are replaced; leaving one out clears it
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReorderLevelsDto {
+    #[validate(range(min = 0, message = "Reorder point cannot be negative"))]
+    pub reorder_point: Option<i32>,
+    #[validate(range(min = 1, message = "Target level must be at least 1"))]
+    pub target_level: Option<i32>,
+}
+
+fn validate_pack_size(pack_size: &Decimal) -> Result<(), validator::ValidationError> {
+    if *pack_size <= Decimal::ZERO {
+        return Err(validator::ValidationError::new("invalid_pack_size"));
+    }
+    Ok(())
+}
+
+// Archived products are hidden unless ?active=false or ?include_archived=true
+#[derive(Debug, Deserialize)]
+pub struct ProductListQuery {
+    pub category_id: Option<Uuid>,
+    // Case-insensitive name substring
+    pub name: Option<String>,
+    pub barcode: Option<String>,
+    pub active: Option<bool>,
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateProductCategoryDto {
+    #[validate(length(min = 1, max = 100, message = "Category name must be 1-100 characters"))]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductCategoryResponseDto {
+    pub status: String,
+    pub category: ProductCategory,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductCategoriesListResponseDto {
+    pub status: String,
+    pub results: usize,
+    pub categories: Vec<ProductCategory>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProductPriceHistoryDto {
+    pub status: String,
+    pub prices: Vec<ProductPrice>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -137,16 +456,26 @@ pub struct ProductsListResponseDto {
 }
 
 // Delivery creation and delivery list responses.
-#[derive(Debug, Deserialize)] 
+#[derive(Debug, Deserialize, Validate)] 
 pub struct CreateDeliveryDto {
     pub date: String,
+    pub supplier_id: Uuid,
+    // Receiving warehouse; the default warehouse when left out
+    pub warehouse_id: Option<Uuid>,
+    #[validate]
     pub products: Vec<DeliveryProductDto>,
 }
 
-#[derive(Debug, Deserialize)]
+// Each line is one batch; repeating a batch number adds to that batch
+#[derive(Debug, Deserialize, Validate)]
 pub struct DeliveryProductDto {
     pub product_id: uuid::Uuid,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
     pub quantity: i32,
+    #[validate(length(min = 1, max = 64, message = "Batch number must be 1-64 characters"))]
+    pub batch_number: String,
+    pub production_date: Option<NaiveDate>,
+    pub expiry_date: NaiveDate,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -167,18 +496,22 @@ pub struct DeliveryListResponseDto {
 }
 
 // Creating truck loads and adding products to a load.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateTruckLoadRequest {
     pub truck_id: Uuid,              
     pub driver_id: Uuid,               
     pub date: NaiveDate,              
+    // Dispatching warehouse; the default warehouse when left out
+    pub warehouse_id: Option<Uuid>,
+    #[validate]
     pub products: Vec<TruckLoadProductItem>,
 }
 
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct TruckLoadProductItem {
     pub product_id: Uuid,              
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
     pub quantity: i32,
 }
 
@@ -189,6 +522,23 @@ pub struct CreateTruckLoadResponse {
     pub message: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct TruckLoadProductDto {
+    pub productid: Uuid,
+    pub product_name: String,
+    pub unit_type: String,
+    pub loaded_quantity: i32,
+    pub remaining_quantity: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MyTruckLoadResponse {
+    pub truckloadid: Uuid,
+    pub truck_id: Uuid,
+    pub date: NaiveDate,
+    pub products: Vec<TruckLoadProductDto>,
+}
+
 // Creating sales and sale product items.
 
 #[derive(Debug, Deserialize)]
@@ -208,17 +558,41 @@ pub struct CreateSaleRequest {
 #[derive(Debug, Serialize)]
 pub struct CreateSaleResponse {
     pub salesid: Uuid,
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub total_amount: Decimal,
+    pub lines: Vec<SaleLineDto>,
     pub message: String,
 }
 
+// How a sale line was priced; price_list_id is None for the product's own price.
+// line_total is the gross (net_amount + tax_amount).
+#[derive(Debug, Serialize, Clone)]
+pub struct SaleLineDto {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub unit_price: Decimal,
+    pub tax_rate: Decimal,
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub line_total: Decimal,
+    pub price_list_id: Option<Uuid>,
+    pub price_list_name: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreatePaymentRequest {
     pub salesid: Uuid,
-    pub amount: f64,
+    pub amount: Decimal,
     pub method: String, // e.g., "cash", "card", "online"
     pub date: NaiveDate,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MySalesQuery {
+    pub date: Option<NaiveDate>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetAllSalesResponse {
     pub sales: Vec<SaleDto>,
@@ -230,8 +604,10 @@ pub struct SaleDto {
     pub truckload_id: Uuid,
     pub shop_id: Uuid,
     pub date: NaiveDate,
-    pub total_amount: f64,
-    pub paid_amount: f64,
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub total_amount: Decimal,
+    pub paid_amount: Decimal,
     pub status: String,
 }
 
@@ -247,7 +623,7 @@ pub struct CreatePaymentResponse {
 #[derive(Debug, Deserialize)]
 pub struct CreateAllowanceRequest {
     pub date: NaiveDate,
-    pub amount: f64,  
+    pub amount: Decimal,  
     pub notes: Option<String>, 
 }
 
@@ -269,7 +645,7 @@ pub struct CreateAllowanceResponse {
 pub struct CreateTruckAllowanceRequest {
     pub truckid: Uuid,     
     pub date: NaiveDate,  
-    pub amount: f64,        
+    pub amount: Decimal,        
 }
 
 #[derive(Debug, Serialize)]
@@ -289,7 +665,7 @@ pub struct DailyProductSaleRequest {
 pub struct DailyProductSaleResponse {
     pub product_name: String,
     pub total_quantity: i64,
-    pub total_amount: f64,
+    pub total_amount: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -302,7 +678,34 @@ pub struct DailyProductSaleListResponse {
 #[derive(Debug, Serialize)]
 pub struct DailySalesRevenueResponse {
     pub date: NaiveDate,
-    pub total_revenue: f64,
+    pub total_revenue: Decimal,
+}
+
+// Tax collected per rate over an inclusive date range, for VAT returns
+#[derive(Debug, Deserialize)]
+pub struct TaxSummaryQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TaxRateSummary {
+    pub tax_rate: Decimal,
+    pub lines: i64,
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub gross_amount: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaxSummaryResponse {
+    pub status: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub gross_amount: Decimal,
+    pub rates: Vec<TaxRateSummary>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -313,7 +716,7 @@ pub struct DailyCommissionRequest {
 #[derive(Debug, Serialize)]
 pub struct DailyCommissionResponse {
     pub date: NaiveDate,
-    pub total_commission: f64,
+    pub total_commission: Decimal,
 }
 
 // Allowances, truck allowances, and distribution data.
@@ -326,22 +729,22 @@ pub struct AllowanceDistributionRequest {
 #[derive(Debug, Serialize)]
 pub struct TruckAllowanceInfo {
     pub trucknumber: String,
-    pub amount: f64,
+    pub amount: Decimal,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AllowanceDistributionResponse {
     pub date: NaiveDate,
-    pub total_amount: f64,
+    pub total_amount: Decimal,
     pub distributed: Vec<TruckAllowanceInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct PendingPaymentResponse {
     pub salesid: uuid::Uuid,
-    pub total_amount: f64,
-    pub paid_amount: f64,
-    pub remaining_amount: f64,
+    pub total_amount: Decimal,
+    pub paid_amount: Decimal,
+    pub remaining_amount: Decimal,
     pub shop_name: String,
     pub shop_address: String,
 }
@@ -378,7 +781,7 @@ pub struct TruckLoadQuantityItemResponse {
 pub struct CreateTruckRequest {
     pub trucknumber: String,
     pub model: String,
-    pub max_allowance: Option<f64>,
+    pub max_allowance: Option<Decimal>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -398,14 +801,14 @@ pub struct CreateTruckResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTruckMaxAllowanceRequest {
     pub trucknumber: String,
-    pub max_allowance: f64,
+    pub max_allowance: Decimal,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTruckMaxAllowanceResponse {
     pub truckid: uuid::Uuid,
     pub trucknumber: String,
-    pub max_allowance: f64,
+    pub max_allowance: Decimal,
 }
 
 // Creating shop records.
@@ -417,6 +820,7 @@ pub struct CreateShopRequest {
     pub city: Option<String>,
     pub district: Option<String>,
     pub contact_number: Option<String>,
+    pub group_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -424,3 +828,609 @@ pub struct CreateShopResponse {
     pub shopid: uuid::Uuid,
     pub message: String,
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateShopGroupDto {
+    #[validate(length(min = 1, max = 100, message = "Group name must be 1-100 characters"))]
+    pub name: String,
+}
+
+// group_id null takes the shop out of its group
+#[derive(Debug, Deserialize)]
+pub struct SetShopGroupDto {
+    pub group_id: Option<Uuid>,
+}
+
+// Price lists: default (no shop or group), per shop group, or per shop.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PriceListItemDto {
+    pub product_id: Uuid,
+    #[validate(range(min = 1, message = "Minimum quantity must be at least 1"))]
+    pub min_quantity: Option<i32>,
+    #[validate(custom(function = "validate_non_negative", message = "Price cannot be negative"))]
+    pub price: Decimal,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePriceListDto {
+    #[validate(length(min = 1, max = 100, message = "Price list name must be 1-100 characters"))]
+    pub name: String,
+    pub shop_id: Option<Uuid>,
+    pub shop_group_id: Option<Uuid>,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+    #[validate]
+    pub items: Vec<PriceListItemDto>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReplacePriceListItemsDto {
+    #[validate]
+    pub items: Vec<PriceListItemDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceListResponseDto {
+    pub status: String,
+    pub price_list: PriceList,
+    pub items: Vec<PriceListItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceListsResponseDto {
+    pub status: String,
+    pub results: usize,
+    pub price_lists: Vec<PriceList>,
+}
+
+// Warehouse stock levels; as_of gives the level at the end of that day.
+#[derive(Debug, Deserialize)]
+pub struct StockQuery {
+    pub as_of: Option<NaiveDate>,
+    // Leave out for totals across all warehouses
+    pub warehouse_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StockLevelDto {
+    pub product_id: Uuid,
+    pub name: String,
+    pub sku: String,
+    pub category_id: Option<Uuid>,
+    pub unit_type: String,
+    pub pack_size: Decimal,
+    pub base_unit: String,
+    pub archived: bool,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockListResponse {
+    pub status: String,
+    pub as_of: Option<NaiveDate>,
+    pub warehouse_id: Option<Uuid>,
+    pub results: usize,
+    pub stock: Vec<StockLevelDto>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct WarehouseStockDto {
+    pub warehouse_id: Uuid,
+    pub warehouse_name: String,
+    pub quantity: i64,
+}
+
+// batches and warehouses describe current stock, so they're empty for an as_of view
+#[derive(Debug, Serialize)]
+pub struct ProductStockResponse {
+    pub status: String,
+    pub as_of: Option<NaiveDate>,
+    pub warehouse_id: Option<Uuid>,
+    pub stock: StockLevelDto,
+    pub warehouses: Vec<WarehouseStockDto>,
+    pub batches: Vec<StockBatch>,
+}
+
+// Warehouse stock by batch.
+#[derive(Debug, Deserialize)]
+pub struct ExpiringStockQuery {
+    pub days: Option<i32>,
+    pub warehouse_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ExpiringBatchDto {
+    pub batch_id: Uuid,
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub warehouse_id: Uuid,
+    pub warehouse_name: String,
+    pub batch_number: String,
+    pub production_date: Option<NaiveDate>,
+    pub expiry_date: NaiveDate,
+    pub quantity: i32,
+    // Negative once the batch has expired
+    pub days_left: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpiringStockResponse {
+    pub status: String,
+    pub days: i32,
+    pub results: usize,
+    pub batches: Vec<ExpiringBatchDto>,
+}
+
+// Stock movement ledger; from/to filter on the business date of the movement.
+#[derive(Debug, Deserialize, Validate)]
+pub struct StockMovementQuery {
+    pub product_id: Option<Uuid>,
+    pub warehouse_id: Option<Uuid>,
+    pub batch_id: Option<Uuid>,
+    pub reason: Option<StockMovementReason>,
+    pub user_id: Option<Uuid>,
+    pub delivery_id: Option<Uuid>,
+    pub truck_load_id: Option<Uuid>,
+    pub transfer_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[validate(range(min = 1))]
+    pub page: Option<usize>,
+    #[validate(range(min = 1, max = 500))]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StockMovementDto {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub warehouse_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    pub batch_number: Option<String>,
+    // Positive into the warehouse, negative out of it
+    pub quantity: i32,
+    pub reason: String,
+    pub delivery_id: Option<Uuid>,
+    pub truck_load_id: Option<Uuid>,
+    pub adjustment_id: Option<Uuid>,
+    pub transfer_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub occurred_on: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockMovementListResponse {
+    pub status: String,
+    pub results: usize,
+    pub movements: Vec<StockMovementDto>,
+}
+
+// Manual stock adjustments; quantity is the change (negative removes stock).
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateStockAdjustmentDto {
+    pub product_id: Uuid,
+    // A batch is adjusted in its own warehouse; otherwise warehouse_id or the default
+    pub warehouse_id: Option<Uuid>,
+    pub batch_id: Option<Uuid>,
+    #[validate(custom(function = "validate_adjustment_quantity", message = "Quantity must be non-zero and at most 1000000 either way"))]
+    pub quantity: i32,
+    pub reason: AdjustmentReason,
+    #[validate(length(max = 1000, message = "Notes must not be more than 1000 characters"))]
+    pub notes: Option<String>,
+}
+
+// Caps a single adjustment well inside i32 so its value can't overflow
+const MAX_ADJUSTMENT_QUANTITY: u32 = 1_000_000;
+
+fn validate_adjustment_quantity(quantity: i32) -> Result<(), validator::ValidationError> {
+    if quantity == 0 || quantity.unsigned_abs() > MAX_ADJUSTMENT_QUANTITY {
+        return Err(validator::ValidationError::new("invalid_adjustment_quantity"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockAdjustmentQuery {
+    pub status: Option<AdjustmentStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockAdjustmentResponse {
+    pub status: String,
+    pub message: String,
+    pub adjustment: StockAdjustment,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockAdjustmentListResponse {
+    pub status: String,
+    pub results: usize,
+    pub adjustments: Vec<StockAdjustment>,
+}
+
+// Stock takes: counts are per product, or per batch when the batch is given.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateStockTakeDto {
+    // The default warehouse when left out
+    pub warehouse_id: Option<Uuid>,
+    #[validate(length(max = 1000, message = "Notes must not be more than 1000 characters"))]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct StockCountDto {
+    pub product_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    #[validate(range(min = 0, message = "Counted quantity cannot be negative"))]
+    pub counted_quantity: i32,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubmitStockCountsDto {
+    #[validate]
+    pub counts: Vec<StockCountDto>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StockTakeLineDto {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub sku: String,
+    pub batch_id: Option<Uuid>,
+    pub batch_number: Option<String>,
+    pub system_quantity: i32,
+    pub counted_quantity: i32,
+    // counted - system; negative means stock is missing
+    pub variance: i32,
+    pub unit_price: Decimal,
+    pub variance_value: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTakeResponse {
+    pub status: String,
+    pub stock_take: StockTake,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTakeListResponse {
+    pub status: String,
+    pub results: usize,
+    pub stock_takes: Vec<StockTake>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTakeVarianceResponse {
+    pub status: String,
+    pub stock_take: StockTake,
+    pub results: usize,
+    pub total_variance_value: Decimal,
+    pub lines: Vec<StockTakeLineDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostStockTakeResponse {
+    pub status: String,
+    pub message: String,
+    pub stock_take: StockTake,
+    pub adjustments: Vec<StockAdjustment>,
+}
+
+// Reorder suggestions; days is the truck-load lookback used for the outflow.
+#[derive(Debug, Deserialize)]
+pub struct ReorderSuggestionQuery {
+    pub days: Option<i32>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ReorderSuggestionDto {
+    pub product_id: Uuid,
+    pub name: String,
+    pub sku: String,
+    pub unit_type: String,
+    pub reorder_point: i32,
+    pub target_level: Option<i32>,
+    pub on_hand: i64,
+    // Loaded minus returned over the lookback window
+    pub outflow: i64,
+    #[sqlx(skip)]
+    pub daily_outflow: Decimal,
+    // None when nothing has gone out
+    #[sqlx(skip)]
+    pub days_of_cover: Option<Decimal>,
+    #[sqlx(skip)]
+    pub suggested_quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReorderSuggestionsResponse {
+    pub status: String,
+    pub days: i32,
+    pub results: usize,
+    pub suggestions: Vec<ReorderSuggestionDto>,
+}
+
+// Warehouses and transfers between them.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWarehouseDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: String,
+    #[validate(length(max = 255, message = "Address must not be more than 255 characters"))]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WarehouseResponse {
+    pub status: String,
+    pub warehouse: Warehouse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WarehouseListResponse {
+    pub status: String,
+    pub results: usize,
+    pub warehouses: Vec<Warehouse>,
+}
+
+// Without batch_id a line is taken from the source warehouse's batches, soonest expiry first
+#[derive(Debug, Deserialize, Validate)]
+pub struct StockTransferLineDto {
+    pub product_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
+    pub quantity: i32,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateStockTransferDto {
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub date: NaiveDate,
+    #[validate(length(max = 1000, message = "Notes must not be more than 1000 characters"))]
+    pub notes: Option<String>,
+    #[validate]
+    pub lines: Vec<StockTransferLineDto>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct StockTransferLineResponse {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub batch_number: String,
+    pub expiry_date: Option<NaiveDate>,
+    pub from_batch_id: Uuid,
+    pub to_batch_id: Uuid,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTransferResponse {
+    pub status: String,
+    pub transfer: StockTransfer,
+    pub lines: Vec<StockTransferLineResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTransferListResponse {
+    pub status: String,
+    pub results: usize,
+    pub transfers: Vec<StockTransfer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTransferQuery {
+    // Transfers into or out of this warehouse
+    pub warehouse_id: Option<Uuid>,
+}
+
+// Suppliers and what they've delivered.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateSupplierDto {
+    #[validate(length(min = 1, max = 150, message = "Name must be 1 to 150 characters"))]
+    pub name: String,
+    pub kind: SupplierKind,
+    #[validate(length(max = 30, message = "Contact number must not be more than 30 characters"))]
+    pub contact_number: Option<String>,
+    #[validate(length(max = 255, message = "Address must not be more than 255 characters"))]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSupplierDto {
+    #[validate(length(min = 1, max = 150, message = "Name must be 1 to 150 characters"))]
+    pub name: Option<String>,
+    pub kind: Option<SupplierKind>,
+    #[validate(length(max = 30, message = "Contact number must not be more than 30 characters"))]
+    pub contact_number: Option<String>,
+    #[validate(length(max = 255, message = "Address must not be more than 255 characters"))]
+    pub address: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SupplierQuery {
+    pub kind: Option<SupplierKind>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupplierResponse {
+    pub status: String,
+    pub supplier: Supplier,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupplierListResponse {
+    pub status: String,
+    pub results: usize,
+    pub suppliers: Vec<Supplier>,
+}
+
+// Both ends are inclusive; either can be left open
+#[derive(Debug, Deserialize)]
+pub struct SupplierDeliveriesQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SupplierProductTotalDto {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub deliveries: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupplierDeliveriesResponse {
+    pub status: String,
+    pub supplier: Supplier,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub results: usize,
+    pub deliveries: Vec<Delivery>,
+    pub totals: Vec<SupplierProductTotalDto>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SupplierTotalDto {
+    pub supplier_id: Uuid,
+    pub name: String,
+    pub kind: String,
+    pub deliveries: i64,
+    pub quantity: i64,
+    pub last_delivery: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupplierTotalsResponse {
+    pub status: String,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub results: usize,
+    pub suppliers: Vec<SupplierTotalDto>,
+}
+
+// Raw milk collection and the rate charts that price it.
+#[derive(Debug, Deserialize, Validate)]
+pub struct MilkRateEntryDto {
+    #[validate(custom(function = "validate_percentage", message = "Minimum fat must be between 0 and 100"))]
+    pub min_fat: Decimal,
+    #[validate(custom(function = "validate_percentage", message = "Minimum SNF must be between 0 and 100"))]
+    pub min_snf: Decimal,
+    #[validate(custom(function = "validate_milk_rate", message = "Rate must be between 0 and 99999999.99"))]
+    pub rate: Decimal,
+}
+
+// Rates are stored rounded to 2 dp in NUMERIC(10,2), so the rounded value is checked
+fn validate_milk_rate(rate: &Decimal) -> Result<(), validator::ValidationError> {
+    let rounded = money::round(*rate);
+    if rounded < Decimal::ZERO || rounded > Decimal::new(9_999_999_999, 2) {
+        return Err(validator::ValidationError::new("invalid_milk_rate"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateMilkRateChartDto {
+    #[validate(custom(function = "validate_rate_chart_name", message = "Rate chart name must be 1-100 characters"))]
+    pub name: String,
+    pub valid_from: NaiveDate,
+    #[validate]
+    pub entries: Vec<MilkRateEntryDto>,
+}
+
+// The name is stored trimmed, so that's what must fit
+fn validate_rate_chart_name(name: &str) -> Result<(), validator::ValidationError> {
+    let length = name.trim().chars().count();
+    if !(1..=100).contains(&length) {
+        return Err(validator::ValidationError::new("invalid_rate_chart_name"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilkRateChartResponse {
+    pub status: String,
+    pub chart: MilkRateChart,
+    pub entries: Vec<MilkRateChartEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilkRateChartListResponse {
+    pub status: String,
+    pub results: usize,
+    pub charts: Vec<MilkRateChart>,
+}
+
+// Milk is rejected when `rejected` is set, the adulteration test is positive,
+// or it's below every slab of the rate chart
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateMilkCollectionDto {
+    pub supplier_id: Uuid,
+    pub collected_on: NaiveDate,
+    #[validate(custom(function = "validate_litres", message = "Litres must be between 0.01 and 99999999.99"))]
+    pub litres: Decimal,
+    #[validate(custom(function = "validate_percentage", message = "Fat must be between 0 and 100"))]
+    pub fat: Decimal,
+    #[validate(custom(function = "validate_percentage", message = "SNF must be between 0 and 100"))]
+    pub snf: Decimal,
+    #[validate(custom(function = "validate_temperature", message = "Temperature must be between -10 and 60 °C"))]
+    pub temperature: Decimal,
+    pub adulteration_result: AdulterationResult,
+    #[serde(default)]
+    pub rejected: bool,
+    #[validate(length(max = 500, message = "Rejection reason must not be more than 500 characters"))]
+    pub rejection_reason: Option<String>,
+}
+
+fn validate_percentage(value: &Decimal) -> Result<(), validator::ValidationError> {
+    if *value < Decimal::ZERO || *value > Decimal::ONE_HUNDRED {
+        return Err(validator::ValidationError::new("invalid_percentage"));
+    }
+    Ok(())
+}
+
+// Litres are stored rounded to 2 dp in NUMERIC(10,2), so the rounded value is checked
+fn validate_litres(litres: &Decimal) -> Result<(), validator::ValidationError> {
+    let rounded = money::round(*litres);
+    if rounded < Decimal::new(1, 2) || rounded > Decimal::new(9_999_999_999, 2) {
+        return Err(validator::ValidationError::new("invalid_litres"));
+    }
+    Ok(())
+}
+
+fn validate_temperature(temperature: &Decimal) -> Result<(), validator::ValidationError> {
+    if *temperature < Decimal::from(-10) || *temperature > Decimal::from(60) {
+        return Err(validator::ValidationError::new("invalid_temperature"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilkCollectionResponse {
+    pub status: String,
+    pub message: String,
+    pub collection: MilkCollection,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MilkCollectionQuery {
+    pub supplier_id: Option<Uuid>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub rejected: Option<bool>,
+}
+
+// Only accepted milk is paid for, so total_amount covers accepted_litres
+#[derive(Debug, Serialize)]
+pub struct MilkCollectionListResponse {
+    pub status: String,
+    pub results: usize,
+    pub accepted_litres: Decimal,
+    pub rejected_litres: Decimal,
+    pub total_amount: Decimal,
+    pub collections: Vec<MilkCollection>,
+}
@@ -25,26 +25,29 @@ pub enum ErrorMessage {
     InvalidHashFormat,
     HashingError,
     InvalidToken,
-    ServerError,
-    WrongCredentials,
     EmailExist,
     UserNoLongerExist,
     TokenNotProvided,
     PermissionDenied,
     UserNotAuthenticated,
+    RegistrationClosed,
+    InvalidInvite,
+    EmailNotVerified,
+    SessionRevoked,
+    AccountDeactivated,
+    AccountLocked,
+    InvalidTwoFactorCode,
 }
 
-impl ToString for ErrorMessage {
-    fn to_string(&self) -> String {
-        self.to_str().to_owned()
+impl fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
     }
 }
 
 impl ErrorMessage {
     fn to_str(&self) -> String {
         match self {
-            ErrorMessage::ServerError => "Server Error. Please try again later".to_string(),
-            ErrorMessage::WrongCredentials => "Email or password is wrong".to_string(),
             ErrorMessage::EmailExist => "A user with this email already exists".to_string(),
             ErrorMessage::UserNoLongerExist => "User belonging to this token no longer exists".to_string(),
             ErrorMessage::EmptyPassword => "Password cannot be empty".to_string(),
@@ -55,6 +58,13 @@ impl ErrorMessage {
             ErrorMessage::TokenNotProvided => "You are not logged in, please provide a token".to_string(),
             ErrorMessage::PermissionDenied => "You are not allowed to perform this action".to_string(),
             ErrorMessage::UserNotAuthenticated => "Authentication required. Please log in.".to_string(),
+            ErrorMessage::RegistrationClosed => "Registration is by invitation only".to_string(),
+            ErrorMessage::InvalidInvite => "Invitation is invalid, expired or already used".to_string(),
+            ErrorMessage::EmailNotVerified => "Please verify your email address before logging in".to_string(),
+            ErrorMessage::SessionRevoked => "Your session has ended, please log in again".to_string(),
+            ErrorMessage::AccountDeactivated => "This account has been deactivated".to_string(),
+            ErrorMessage::AccountLocked => "Too many failed login attempts, please try again later".to_string(),
+            ErrorMessage::InvalidTwoFactorCode => "Invalid two-factor authentication code".to_string(),
         }
     }
 }
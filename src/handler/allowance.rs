@@ -1,38 +1,28 @@
 use axum::{
     extract::Extension,
-    http::StatusCode,
     Json,
 };
 use std::sync::Arc;
 use crate::dtos::{CreateAllowanceRequest, CreateAllowanceResponse, CreateTruckAllowanceRequest, CreateTruckAllowanceResponse, AllowanceDistributionRequest, AllowanceDistributionResponse};
-use crate::error::{HttpError, ErrorMessage};
+use crate::error::HttpError;
 use crate::db::AllowanceExt;
-use crate::middleware::JWTAuthMiddeware;
+use crate::middleware::require_roles;
+use crate::permissions::Permission;
 use crate::AppState;
 use axum::routing::{get,post};
 use axum::Router;
 
 pub fn allowance_handler() -> Router {
     Router::new()
-        .route("/create", post(create_allowance))
-        .route("/truck-create", post(create_truck_allowance))
-        .route("/distribution", get(get_allowance_distribution))
+        .route("/create", post(create_allowance).route_layer(require_roles(Permission::ManageAllowances.roles())))
+        .route("/truck-create", post(create_truck_allowance).route_layer(require_roles(Permission::ManageAllowances.roles())))
+        .route("/distribution", get(get_allowance_distribution).route_layer(require_roles(Permission::ManageAllowances.roles())))
 }
 
 pub async fn create_allowance(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<CreateAllowanceRequest>,
 ) -> Result<Json<CreateAllowanceResponse>, HttpError> {
-    // Only Manager or Admin can create allowance
-    if jwt_auth.user.role != crate::models::UserRole::Manager &&
-       jwt_auth.user.role != crate::models::UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     let allowance = app_state.db_client
         .create_allowance(body.date, body.amount, body.notes.clone())
         .await
@@ -45,18 +35,10 @@ pub async fn create_allowance(
 }
 
 pub async fn create_truck_allowance(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<CreateTruckAllowanceRequest>,
 ) -> Result<Json<CreateTruckAllowanceResponse>, HttpError> {
 
-    if jwt_auth.user.role != crate::models::UserRole::Manager &&
-       jwt_auth.user.role != crate::models::UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let truck_allowance = app_state.db_client
         .create_truck_allowance(body.truckid,body.date, body.amount)
         .await
@@ -69,17 +51,9 @@ pub async fn create_truck_allowance(
 }
 
 pub async fn get_allowance_distribution(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<AllowanceDistributionRequest>,
 ) -> Result<Json<AllowanceDistributionResponse>, HttpError> {
-    if jwt_auth.user.role != crate::models::UserRole::Manager &&
-       jwt_auth.user.role != crate::models::UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let distribution = app_state.db_client
         .get_allowance_distribution_by_date(body.date)
         .await
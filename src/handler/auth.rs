@@ -1,25 +1,35 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{http::{ StatusCode}, response::{IntoResponse}, routing::{ post}, Extension, Json, Router};
+use axum::{extract::{ConnectInfo, Query}, http::{ StatusCode}, response::{IntoResponse}, routing::{get, post}, Extension, Json, Router};
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
 
 use validator::Validate;
-use crate::models::UserRole;
+use crate::models::{User, UserRole};
+use super::two_factor::{self, two_factor_handler};
 
 
-use crate::{db::UserExt, dtos::{LoginUserDto, RegisterUserDto, Response, UserLoginResponseDto}, error::{HttpError},  utils::{password, token}, AppState};
+use crate::{db::{InviteExt, LoginAttemptExt, SessionExt, UserExt}, dtos::{AcceptInviteDto, ForgotPasswordRequestDto, LoginUserDto, RefreshTokenRequestDto, RegisterUserDto, ResetPasswordRequestDto, Response, RevokeSessionsResponseDto, UserLoginResponseDto, VerifyEmailQueryDto}, error::{ErrorMessage, HttpError}, mail::mails::{send_forgot_password_email, send_verification_email, send_welcome_email}, middleware::{auth, JWTAuthMiddeware},  utils::{lockout, password, token}, AppState};
 
 pub fn auth_handler() -> Router {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/accept-invite", post(accept_invite))
+        .route("/verify", get(verify_email))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout).route_layer(axum::middleware::from_fn(auth)))
+        .route("/logout-all", post(logout_all).route_layer(axum::middleware::from_fn(auth)))
+        .nest("/2fa", two_factor_handler())
 }
 
 pub async fn register(
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<RegisterUserDto>
 ) -> Result<impl IntoResponse, HttpError> {
-    println!("Request body: {:?}", body);
-
     // Validate input
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
@@ -28,33 +38,84 @@ pub async fn register(
     let hash_password = password::hash(&body.password)
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
-    // Set default role to Manager if not provided
-    let role = body.role.unwrap_or(UserRole::Manager);
+    // The very first account bootstraps the system as Admin; everyone else
+    // is invited, or self-registers as a driver when open registration is on
+    let user_count = app_state.db_client
+        .get_user_count()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let role = if user_count == 0 {
+        UserRole::Admin
+    } else if app_state.env.open_registration {
+        UserRole::Driver
+    } else {
+        return Err(HttpError::new(ErrorMessage::RegistrationClosed.to_string(), StatusCode::FORBIDDEN));
+    };
+
+    let (verification_token, verification_token_hash) = token::generate_opaque_token();
+    let expires_at = Utc::now() + Duration::hours(24);
 
     // Save user in database
     let result = app_state.db_client
-        .save_user(
-            &body.first_name,
-            &body.last_name,
-            &body.email,
-            &hash_password,
-            role,
-            body.address.as_deref(),
-            body.city.as_deref(),
-            body.district.as_deref(),
-            body.contact_number.as_deref(),
-        )
+        .save_user(&body, &hash_password, role, &verification_token_hash, expires_at)
+        .await;
+
+    match result {
+        Ok(user) => {
+            if let Err(e) = send_verification_email(&user.email, &user.first_name, &verification_token).await {
+                eprintln!("Failed to send verification email to {}: {:?}", user.email, e);
+            }
+
+            Ok((StatusCode::CREATED, Json(Response {
+                status: "success",
+                message: "Registration successful! Please check your email to verify your account.".to_string()
+            })))
+        }
+        Err(sqlx::Error::Database(db_err)) => {
+            if db_err.is_unique_violation() {
+                Err(HttpError::unique_constraint_violation(
+                    "Email already exists".to_string(),
+                ))
+            } else {
+                Err(HttpError::server_error(db_err.to_string()))
+            }
+        }
+        Err(e) => Err(HttpError::server_error(e.to_string())),
+    }
+}
+
+pub async fn accept_invite(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<AcceptInviteDto>
+) -> Result<impl IntoResponse, HttpError> {
+    // Validate input
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let claims = token::decode_invite_token(&body.token, app_state.env.jwt_secret.as_bytes())?;
+
+    let invite_id = uuid::Uuid::parse_str(&claims.sub)
+        .map_err(|_| HttpError::bad_request(ErrorMessage::InvalidInvite.to_string()))?;
+
+    // Hash password
+    let hash_password = password::hash(&body.password)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let result = app_state.db_client
+        .accept_invite(invite_id, &body, &hash_password)
         .await;
 
     match result {
-        Ok(_user) => Ok((StatusCode::CREATED, Json(Response {
+        Ok(Some(_user)) => Ok((StatusCode::CREATED, Json(Response {
             status: "success",
             message: "Registration successful!".to_string()
         }))),
+        Ok(None) => Err(HttpError::bad_request(ErrorMessage::InvalidInvite.to_string())),
         Err(sqlx::Error::Database(db_err)) => {
             if db_err.is_unique_violation() {
                 Err(HttpError::unique_constraint_violation(
-                    "Email already exists".to_string(),
+                    ErrorMessage::EmailExist.to_string(),
                 ))
             } else {
                 Err(HttpError::server_error(db_err.to_string()))
@@ -65,44 +126,321 @@ pub async fn register(
 }
 
 pub async fn login(
+    cookie_jar: CookieJar,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<LoginUserDto>,
 ) -> Result<impl IntoResponse, HttpError> {
-    println!("Login request body: {:?}", body);
-
     // Validate input
     body.validate()
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    // Locks are keyed by email, so unknown addresses lock the same way real ones do
+    let account_key = body.email.to_lowercase();
+    let ip_key = client_addr.ip().to_string();
+
+    ensure_not_locked(&app_state, &account_key, &ip_key).await?;
+
     // Fetch user by email
     let user = app_state
         .db_client
         .get_user_by_email(&body.email)
         .await
-        .map_err(|_| HttpError::bad_request("Invalid email or password".to_string()))?
-        .ok_or_else(|| HttpError::bad_request("Invalid email or password".to_string()))?;
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
 
     // Verify password
-    let is_valid = password::compare(&body.password, &user.password)
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+    let is_valid = match &user {
+        Some(user) => password::compare(&body.password, &user.password)
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+        None => false,
+    };
+
+    let user = match user {
+        Some(user) if is_valid => user,
+        _ => {
+            record_failed_login(&app_state, &account_key, &ip_key).await?;
+            return Err(HttpError::bad_request("Invalid email or password".to_string()));
+        }
+    };
 
-    if !is_valid {
-        return Err(HttpError::bad_request("Invalid email or password".to_string()));
+    if !user.is_active {
+        return Err(HttpError::new(ErrorMessage::AccountDeactivated.to_string(), StatusCode::FORBIDDEN));
     }
 
-    // Generate JWT token
-    let secret_key = app_state.env.jwt_secret.as_bytes();
+    if !user.verified {
+        return Err(HttpError::new(ErrorMessage::EmailNotVerified.to_string(), StatusCode::FORBIDDEN));
+    }
 
-    let token = token::create_token(&user.id.to_string(), &user.role.to_str(),secret_key,21600)
+    // The failure count is only reset once the second factor is checked too,
+    // otherwise re-entering the password would allow unlimited code guesses
+    if let Some(challenge) = two_factor::two_factor_challenge(&app_state, &user).await? {
+        return Ok((StatusCode::OK, Json(challenge)).into_response());
+    }
+
+    app_state.db_client
+        .clear_failed_logins(lockout::ACCOUNT_SCOPE, &account_key)
+        .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
+    let (cookie_jar, response) = start_session(&app_state, &user, cookie_jar).await?;
+
     println!("User '{}' logged in successfully", user.email);
 
-    Ok((StatusCode::OK, Json(UserLoginResponseDto {
+    Ok((StatusCode::OK, cookie_jar, response).into_response())
+}
+
+pub async fn ensure_not_locked(
+    app_state: &AppState,
+    account_key: &str,
+    ip_key: &str,
+) -> Result<(), HttpError> {
+    for (scope, key) in [(lockout::ACCOUNT_SCOPE, account_key), (lockout::IP_SCOPE, ip_key)] {
+        let locked_until = app_state.db_client
+            .get_locked_until(scope, key)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if locked_until.is_some() {
+            return Err(HttpError::new(ErrorMessage::AccountLocked.to_string(), StatusCode::TOO_MANY_REQUESTS));
+        }
+    }
+
+    Ok(())
+}
+
+// Counts a failure against both the account and the client IP, locking either
+// one once it passes its limit.
+pub async fn record_failed_login(
+    app_state: &AppState,
+    account_key: &str,
+    ip_key: &str,
+) -> Result<(), HttpError> {
+    let limits = [
+        (lockout::ACCOUNT_SCOPE, account_key, app_state.env.login_max_attempts),
+        (lockout::IP_SCOPE, ip_key, app_state.env.login_ip_max_attempts),
+    ];
+
+    for (scope, key, max_attempts) in limits {
+        let failed_attempts = app_state.db_client
+            .record_failed_login(scope, key)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        if let Some(seconds) = lockout::lockout_seconds(failed_attempts, max_attempts, app_state.env.login_lockout_seconds) {
+            app_state.db_client
+                .lock_login(scope, key, Utc::now() + Duration::seconds(seconds))
+                .await
+                .map_err(|e| HttpError::server_error(e.to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn refresh(
+    cookie_jar: CookieJar,
+    Extension(app_state): Extension<Arc<AppState>>,
+    body: Option<Json<RefreshTokenRequestDto>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let presented = body
+        .and_then(|Json(body)| body.refresh_token)
+        .or_else(|| cookie_jar.get("refresh_token").map(|cookie| cookie.value().to_string()))
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::TokenNotProvided.to_string()))?;
+
+    // Rotate: the presented refresh token is spent and a new one is issued
+    let (refresh_token, refresh_token_hash) = token::generate_opaque_token();
+    let session = app_state.db_client
+        .rotate_session(
+            &token::hash_opaque_token(&presented),
+            &refresh_token_hash,
+            Utc::now() + Duration::seconds(app_state.env.refresh_token_maxage),
+        )
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::SessionRevoked.to_string()))?;
+
+    let user = app_state.db_client
+        .get_user(Some(session.user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    if !user.is_active {
+        return Err(HttpError::unauthorized(ErrorMessage::AccountDeactivated.to_string()));
+    }
+
+    let (cookie_jar, response) = session_response(&app_state, &user, session.id, refresh_token, cookie_jar)?;
+
+    Ok((StatusCode::OK, cookie_jar, response))
+}
+
+pub async fn logout(
+    cookie_jar: CookieJar,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    app_state.db_client
+        .revoke_session(jwt_auth.session_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok((StatusCode::OK, clear_auth_cookies(cookie_jar), Json(Response {
+        status: "success",
+        message: "Logged out successfully".to_string()
+    })))
+}
+
+pub async fn logout_all(
+    cookie_jar: CookieJar,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    let revoked = app_state.db_client
+        .revoke_user_sessions(jwt_auth.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok((StatusCode::OK, clear_auth_cookies(cookie_jar), Json(RevokeSessionsResponseDto {
         status: "success".to_string(),
-        token,
+        revoked,
     })))
 }
 
+// Opens a session for this device and returns its tokens.
+pub async fn start_session(
+    app_state: &AppState,
+    user: &User,
+    cookie_jar: CookieJar,
+) -> Result<(CookieJar, Json<UserLoginResponseDto>), HttpError> {
+    let (refresh_token, refresh_token_hash) = token::generate_opaque_token();
+    let session = app_state.db_client
+        .create_session(user.id, &refresh_token_hash, Utc::now() + Duration::seconds(app_state.env.refresh_token_maxage))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    session_response(app_state, user, session.id, refresh_token, cookie_jar)
+}
+
+// Issues a short-lived access token for a session and mirrors both tokens into
+// HttpOnly cookies for browser clients; mobile clients use the JSON body.
+fn session_response(
+    app_state: &AppState,
+    user: &User,
+    session_id: Uuid,
+    refresh_token: String,
+    cookie_jar: CookieJar,
+) -> Result<(CookieJar, Json<UserLoginResponseDto>), HttpError> {
+    let token = token::create_token(
+        &user.id.to_string(),
+        user.role.to_str(),
+        &session_id.to_string(),
+        app_state.env.jwt_secret.as_bytes(),
+        app_state.env.jwt_maxage,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let access_cookie = Cookie::build(("token", token.clone()))
+        .path("/")
+        .http_only(true)
+        .max_age(time::Duration::seconds(app_state.env.jwt_maxage));
+
+    let refresh_cookie = Cookie::build(("refresh_token", refresh_token.clone()))
+        .path("/api/auth")
+        .http_only(true)
+        .max_age(time::Duration::seconds(app_state.env.refresh_token_maxage));
+
+    Ok((
+        cookie_jar.add(access_cookie).add(refresh_cookie),
+        Json(UserLoginResponseDto {
+            status: "success".to_string(),
+            token,
+            refresh_token,
+        }),
+    ))
+}
+
+fn clear_auth_cookies(cookie_jar: CookieJar) -> CookieJar {
+    cookie_jar
+        .remove(Cookie::build("token").path("/"))
+        .remove(Cookie::build("refresh_token").path("/api/auth"))
+}
 
+pub async fn verify_email(
+    Query(query_params): Query<VerifyEmailQueryDto>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<impl IntoResponse, HttpError> {
+    query_params.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = app_state.db_client
+        .verify_email_token(&token::hash_opaque_token(&query_params.token))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request("Invalid or expired verification token".to_string()))?;
 
+    if let Err(e) = send_welcome_email(&user.email, &user.first_name).await {
+        eprintln!("Failed to send welcome email to {}: {:?}", user.email, e);
+    }
+
+    Ok((StatusCode::OK, Json(Response {
+        status: "success",
+        message: "Email verified successfully. You can now log in.".to_string()
+    })))
+}
+
+pub async fn forgot_password(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<ForgotPasswordRequestDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = app_state.db_client
+        .get_user_by_email(&body.email)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    // Same answer whether or not the account exists, so emails can't be probed
+    if let Some(user) = user {
+        let (reset_token, token_hash) = token::generate_opaque_token();
+        let expires_at = Utc::now() + Duration::minutes(30);
+
+        app_state.db_client
+            .create_password_reset_token(user.id, &token_hash, expires_at)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+        let reset_link = format!("http://localhost:3000/reset-password?token={}", reset_token);
+
+        if let Err(e) = send_forgot_password_email(&user.email, &reset_link, &user.first_name).await {
+            eprintln!("Failed to send password reset email to {}: {:?}", user.email, e);
+        }
+    }
+
+    Ok((StatusCode::OK, Json(Response {
+        status: "success",
+        message: "If an account exists for that email, a password reset link has been sent.".to_string()
+    })))
+}
+
+pub async fn reset_password(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<ResetPasswordRequestDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let hash_password = password::hash(&body.password)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state.db_client
+        .reset_password(&token::hash_opaque_token(&body.token), &hash_password)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request("Invalid or expired reset token".to_string()))?;
+
+    Ok((StatusCode::OK, Json(Response {
+        status: "success",
+        message: "Password has been reset successfully.".to_string()
+    })))
+}
@@ -1,24 +1,24 @@
 use axum::{
     extract::{ Extension},  
-    http::StatusCode,
     Json,
 };
 use std::sync::Arc;
 use crate::dtos::{CreateDeliveryDto, DeliveryResponseDto, DeliveryListResponseDto};
-use crate::error::{HttpError, ErrorMessage};
-use crate::db::{ DeliveryExt};
-use crate::models::UserRole;
-use crate::middleware::JWTAuthMiddeware;
+use crate::error::HttpError;
+use crate::db::{DeliveryExt, SupplierExt};
+use crate::handler::warehouses::resolve_warehouse;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
 use crate::AppState;
 use axum::routing::{get, post};
 use axum::Router;
-use uuid::Uuid;
+use validator::Validate;
 
 pub fn delivery_handler() -> Router { 
     Router::new()
-        .route("/create", post(create_delivery))
-        .route("/history", get(get_delivery_history))
-        .route("/all", get(get_all_delivery_history))
+        .route("/create", post(create_delivery).route_layer(require_roles(Permission::CreateDelivery.roles())))
+        .route("/history", get(get_delivery_history).route_layer(require_roles(Permission::ViewDeliveryHistory.roles())))
+        .route("/all", get(get_all_delivery_history).route_layer(require_roles(Permission::ViewAllDeliveries.roles())))
 }
 
 pub async fn create_delivery(
@@ -26,33 +26,40 @@ pub async fn create_delivery(
     Extension(app_state): Extension<Arc<AppState>>,    
     Json(body): Json<CreateDeliveryDto>,
 ) -> Result<Json<DeliveryResponseDto>, HttpError> {
-    // Check if user has Manager 
-    if jwt_auth.user.role != UserRole::Manager {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
     // Parse date
     let date = chrono::NaiveDate::parse_from_str(&body.date, "%Y-%m-%d")
         .map_err(|_| HttpError::bad_request("Invalid date format".to_string()))?;
 
-    // Prepare products vector
-    let products: Vec<(Uuid, i32)> = body
-        .products
-        .into_iter()
-        .map(|p| (p.product_id, p.quantity))
-        .collect();
+    if let Some(line) = body.products.iter().find(|p| p.production_date.is_some_and(|produced| produced > p.expiry_date)) {
+        return Err(HttpError::bad_request(format!("Batch {} expires before it was produced", line.batch_number)));
+    }
 
     // Get user id from JWT token
     let user_id = jwt_auth.user.id;
 
+    let supplier = app_state.db_client
+        .get_supplier(body.supplier_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Supplier not found".to_string()))?;
+    if !supplier.is_active {
+        return Err(HttpError::bad_request(format!("Supplier {} is inactive", supplier.name)));
+    }
+
+    let warehouse = resolve_warehouse(&app_state, body.warehouse_id).await?;
+
     // Create delivery
     let delivery = app_state.db_client
-        .create_delivery(user_id, date, products)
+        .create_delivery(user_id, supplier.id, warehouse.id, date, &body.products)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("A batch number is already recorded with a different expiry date".to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => HttpError::bad_request("Product not found".to_string()),
+            e => HttpError::server_error(e.to_string()),
+        })?;
 
     Ok(Json(DeliveryResponseDto {
         status: "success".to_string(),
@@ -79,19 +86,8 @@ pub async fn get_delivery_history(
 }
 
 pub async fn get_all_delivery_history(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
 ) -> Result<Json<DeliveryListResponseDto>, HttpError> {
-    // Only Admin or Manager should be able to see full history
-    if jwt_auth.user.role != crate::models::UserRole::Admin
-        && jwt_auth.user.role != crate::models::UserRole::Manager
-    {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     // Fetch all deliveries
     let deliveries = app_state.db_client
         .get_all_deliveries()
@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    Json,
+};
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::dtos::{CreateMilkCollectionDto, CreateMilkRateChartDto, MilkCollectionListResponse, MilkCollectionQuery, MilkCollectionResponse, MilkRateChartListResponse, MilkRateChartResponse};
+use crate::error::HttpError;
+use crate::db::{MilkExt, SupplierExt};
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::models::SupplierKind;
+use crate::permissions::Permission;
+use crate::utils::money;
+use crate::AppState;
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn milk_handler() -> Router {
+    Router::new()
+        // Fat/SNF slabs and their rate per litre; a chart applies from valid_from until the next one starts
+        .route("/rate-charts", post(create_milk_rate_chart).route_layer(require_roles(Permission::ManageMilkRates.roles())))
+        .route("/rate-charts", get(get_milk_rate_charts).route_layer(require_roles(Permission::ViewMilkCollections.roles())))
+        .route("/rate-charts/:id", get(get_milk_rate_chart).route_layer(require_roles(Permission::ViewMilkCollections.roles())))
+
+        // Raw milk received from farmers and co-ops, filterable by ?supplier_id=&from=&to=&rejected=
+        .route("/collections", post(create_milk_collection).route_layer(require_roles(Permission::RecordMilkCollection.roles())))
+        .route("/collections", get(get_milk_collections).route_layer(require_roles(Permission::ViewMilkCollections.roles())))
+}
+
+pub async fn create_milk_rate_chart(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateMilkRateChartDto>,
+) -> Result<Json<MilkRateChartResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.entries.is_empty() {
+        return Err(HttpError::bad_request("A rate chart needs at least one entry".to_string()));
+    }
+
+    // Slabs are compared as stored, to two places
+    let mut slabs = HashSet::new();
+    if let Some(entry) = body.entries.iter().find(|entry| {
+        !slabs.insert((money::round(entry.min_fat).normalize(), money::round(entry.min_snf).normalize()))
+    }) {
+        return Err(HttpError::bad_request(format!(
+            "Fat {} / SNF {} appears more than once",
+            entry.min_fat, entry.min_snf
+        )));
+    }
+
+    let (chart, entries) = app_state.db_client
+        .create_milk_rate_chart(&body, jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.constraint() == Some("milk_rate_charts_valid_from_key") => {
+                HttpError::unique_constraint_violation(format!("A rate chart already starts on {}", body.valid_from))
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(MilkRateChartResponse {
+        status: "success".to_string(),
+        chart,
+        entries,
+    }))
+}
+
+pub async fn get_milk_rate_charts(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<MilkRateChartListResponse>, HttpError> {
+    let charts = app_state.db_client
+        .get_milk_rate_charts()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(MilkRateChartListResponse {
+        status: "success".to_string(),
+        results: charts.len(),
+        charts,
+    }))
+}
+
+pub async fn get_milk_rate_chart(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(chart_id): Path<String>,
+) -> Result<Json<MilkRateChartResponse>, HttpError> {
+    let chart_uuid = Uuid::parse_str(&chart_id)
+        .map_err(|_| HttpError::bad_request("Invalid rate chart ID".to_string()))?;
+
+    let (chart, entries) = app_state.db_client
+        .get_milk_rate_chart(chart_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Rate chart not found".to_string()))?;
+
+    Ok(Json(MilkRateChartResponse {
+        status: "success".to_string(),
+        chart,
+        entries,
+    }))
+}
+
+pub async fn create_milk_collection(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateMilkCollectionDto>,
+) -> Result<Json<MilkCollectionResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.rejection_reason.is_some() && !body.rejected {
+        return Err(HttpError::bad_request("rejection_reason is only allowed when the milk is rejected".to_string()));
+    }
+
+    let supplier = app_state.db_client
+        .get_supplier(body.supplier_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Supplier not found".to_string()))?;
+    if !supplier.is_active {
+        return Err(HttpError::bad_request(format!("Supplier {} is inactive", supplier.name)));
+    }
+    if supplier.kind == SupplierKind::Vendor.to_str() {
+        return Err(HttpError::bad_request("Milk is only collected from farmers and co-ops".to_string()));
+    }
+
+    let collection = app_state.db_client
+        .create_milk_collection(&body, jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Protocol(msg) => HttpError::bad_request(msg),
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    let message = match &collection.rejection_reason {
+        Some(reason) if collection.rejected => format!("Milk rejected: {}", reason),
+        _ => "Milk collection recorded".to_string(),
+    };
+
+    Ok(Json(MilkCollectionResponse {
+        status: "success".to_string(),
+        message,
+        collection,
+    }))
+}
+
+pub async fn get_milk_collections(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<MilkCollectionQuery>,
+) -> Result<Json<MilkCollectionListResponse>, HttpError> {
+    if matches!((params.from, params.to), (Some(from), Some(to)) if to < from) {
+        return Err(HttpError::bad_request("'to' must not be before 'from'".to_string()));
+    }
+
+    let collections = app_state.db_client
+        .get_milk_collections(&params)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let (rejected, accepted): (Vec<_>, Vec<_>) = collections.iter().partition(|c| c.rejected);
+
+    Ok(Json(MilkCollectionListResponse {
+        status: "success".to_string(),
+        results: collections.len(),
+        accepted_litres: accepted.iter().map(|c| c.litres).sum(),
+        rejected_litres: rejected.iter().map(|c| c.litres).sum(),
+        total_amount: accepted.iter().filter_map(|c| c.amount).sum::<Decimal>(),
+        collections,
+    }))
+}
@@ -7,4 +7,11 @@ pub mod sales;
 pub mod payment;
 pub mod allowance;
 pub mod trucks;
-pub mod shops;
\ No newline at end of file
+pub mod shops;
+pub mod two_factor;
+pub mod price_lists;
+pub mod stock;
+pub mod stock_takes;
+pub mod warehouses;
+pub mod suppliers;
+pub mod milk;
\ No newline at end of file
@@ -0,0 +1,123 @@
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use std::sync::Arc;
+use validator::Validate;
+use crate::dtos::{CreatePriceListDto, PriceListResponseDto, PriceListsResponseDto, ReplacePriceListItemsDto};
+use crate::error::HttpError;
+use crate::db::PriceListExt;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
+use crate::AppState;
+use axum::routing::{get, post, put};
+use axum::Router;
+use uuid::Uuid;
+
+pub fn price_lists_handler() -> Router {
+    Router::new()
+        // A list without shop_id or shop_group_id applies to every shop
+        .route("/create", post(create_price_list).route_layer(require_roles(Permission::ManagePriceLists.roles())))
+        .route("/all", get(get_price_lists).route_layer(require_roles(Permission::ManagePriceLists.roles())))
+        .route("/:id", get(get_price_list).route_layer(require_roles(Permission::ManagePriceLists.roles())))
+        .route("/:id/items", put(replace_price_list_items).route_layer(require_roles(Permission::ManagePriceLists.roles())))
+}
+
+pub async fn create_price_list(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreatePriceListDto>,
+) -> Result<Json<PriceListResponseDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.shop_id.is_some() && body.shop_group_id.is_some() {
+        return Err(HttpError::bad_request("A price list applies to a shop or a shop group, not both".to_string()));
+    }
+
+    if matches!((body.valid_from, body.valid_to), (Some(from), Some(to)) if to < from) {
+        return Err(HttpError::bad_request("valid_to must not be before valid_from".to_string()));
+    }
+
+    let (price_list, items) = app_state.db_client
+        .create_price_list(&body, jwt_auth.user.id)
+        .await
+        .map_err(price_list_write_error)?;
+
+    Ok(Json(PriceListResponseDto {
+        status: "success".to_string(),
+        price_list,
+        items,
+    }))
+}
+
+pub async fn get_price_lists(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<PriceListsResponseDto>, HttpError> {
+    let price_lists = app_state.db_client
+        .get_price_lists()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(PriceListsResponseDto {
+        status: "success".to_string(),
+        results: price_lists.len(),
+        price_lists,
+    }))
+}
+
+pub async fn get_price_list(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(price_list_id): Path<String>,
+) -> Result<Json<PriceListResponseDto>, HttpError> {
+    let price_list_uuid = Uuid::parse_str(&price_list_id)
+        .map_err(|_| HttpError::bad_request("Invalid price list ID".to_string()))?;
+
+    let (price_list, items) = app_state.db_client
+        .get_price_list(price_list_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Price list not found".to_string()))?;
+
+    Ok(Json(PriceListResponseDto {
+        status: "success".to_string(),
+        price_list,
+        items,
+    }))
+}
+
+pub async fn replace_price_list_items(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(price_list_id): Path<String>,
+    Json(body): Json<ReplacePriceListItemsDto>,
+) -> Result<Json<PriceListResponseDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let price_list_uuid = Uuid::parse_str(&price_list_id)
+        .map_err(|_| HttpError::bad_request("Invalid price list ID".to_string()))?;
+
+    let (price_list, items) = app_state.db_client
+        .replace_price_list_items(price_list_uuid, &body.items)
+        .await
+        .map_err(price_list_write_error)?
+        .ok_or(HttpError::bad_request("Price list not found".to_string()))?;
+
+    Ok(Json(PriceListResponseDto {
+        status: "success".to_string(),
+        price_list,
+        items,
+    }))
+}
+
+fn price_list_write_error(e: sqlx::Error) -> HttpError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            HttpError::unique_constraint_violation("A product can have only one price per minimum quantity in a list".to_string())
+        }
+        sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+            HttpError::bad_request("Unknown shop, shop group or product".to_string())
+        }
+        e => HttpError::server_error(e.to_string()),
+    }
+}
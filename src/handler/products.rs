@@ -1,50 +1,61 @@
 use axum::{
-    extract::{Path, Extension},
-    http::StatusCode,
+    extract::{Path, Query, Extension},
     Json,
 };
 use std::sync::Arc;
-use crate::dtos::{CreateProductDto, ProductResponseDto, ProductsListResponseDto};
-use crate::error::{HttpError, ErrorMessage};
+use validator::Validate;
+use crate::dtos::{CreateProductCategoryDto, CreateProductDto, ProductCategoriesListResponseDto, ProductCategoryResponseDto, ProductListQuery, ProductPriceHistoryDto, ProductResponseDto, ProductsListResponseDto, ReorderLevelsDto, UpdateProductDto};
+use crate::error::HttpError;
 use crate::db::{ ProductExt};
-use crate::models::UserRole;
-use crate::middleware::JWTAuthMiddeware;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
 use crate::AppState;
-use axum::routing::{get, post};
+use axum::routing::{get, post, put};
 use axum::Router;
 use uuid::Uuid;
 
 pub fn products_handler() -> Router {
     Router::new()
         // Create a new product
-        .route("/create", post(create_product))
+        .route("/create", post(create_product).route_layer(require_roles(Permission::CreateProduct.roles())))
         
         // Get a single product by ID
-        .route("/:id", get(get_product))
+        .route("/:id", get(get_product).route_layer(require_roles(Permission::ViewProducts.roles())))
 
-        // Get all products
-        .route("/all", get(get_all_products))
+        // Get all products, filtered by ?category_id=, ?name=, ?barcode= and ?active=
+        .route("/all", get(get_all_products).route_layer(require_roles(Permission::ViewProducts.roles())))
+
+        // Product categories
+        .route("/categories", get(get_product_categories).route_layer(require_roles(Permission::ViewProducts.roles())))
+        .route("/categories", post(create_product_category).route_layer(require_roles(Permission::ManageProductCategories.roles())))
+
+        // Update details and/or schedule a new price
+        .route("/:id", put(update_product).route_layer(require_roles(Permission::UpdateProduct.roles())))
+
+        // Price history, newest first
+        .route("/:id/prices", get(get_product_prices).route_layer(require_roles(Permission::ViewProducts.roles())))
+
+        // Archived products can't be loaded or sold but keep their history
+        .route("/:id/archive", post(archive_product).route_layer(require_roles(Permission::ArchiveProduct.roles())))
+        .route("/:id/unarchive", post(unarchive_product).route_layer(require_roles(Permission::ArchiveProduct.roles())))
+
+        // Reorder point and target level used by /stock/reorder-suggestions
+        .route("/:id/reorder-levels", put(set_reorder_levels).route_layer(require_roles(Permission::UpdateProduct.roles())))
 
 }
 
 pub async fn create_product(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<CreateProductDto>,
 ) -> Result<Json<ProductResponseDto>, HttpError> {
-    // Check if user has Manager or Admin role
-    if jwt_auth.user.role != UserRole::Manager && jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
     // Create product
     let product = app_state.db_client
-        .create_product(&body.name, body.price, &body.unit_type, body.commission)
+        .create_product(&body)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(product_write_error)?;
 
     Ok(Json(ProductResponseDto {
         status: "success".to_string(),
@@ -73,9 +84,22 @@ pub async fn get_product(
 
 pub async fn get_all_products(
     Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<ProductListQuery>,
 ) -> Result<Json<ProductsListResponseDto >, HttpError> {
+    // Without an explicit filter only active products are listed
+    let active = match (params.active, params.include_archived) {
+        (Some(active), _) => Some(active),
+        (None, Some(true)) => None,
+        (None, _) => Some(true),
+    };
+
     let products = app_state.db_client
-        .get_all_products()
+        .get_all_products(
+            params.category_id,
+            params.name.as_deref().filter(|name| !name.is_empty()),
+            params.barcode.as_deref(),
+            active,
+        )
         .await
         .map_err(|e| HttpError::server_error(e.to_string()))?;
 
@@ -86,5 +110,166 @@ pub async fn get_all_products(
     }))
 }
 
+pub async fn update_product(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+    Json(body): Json<UpdateProductDto>,
+) -> Result<Json<ProductResponseDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let product_uuid = Uuid::parse_str(&product_id)
+        .map_err(|_| HttpError::bad_request("Invalid product ID".to_string()))?;
+
+    let new_price = body.price.map(|price| {
+        (price, body.price_effective_from.unwrap_or_else(|| chrono::Local::now().date_naive()))
+    });
+
+    let product = app_state.db_client
+        .update_product(product_uuid, &body, new_price, jwt_auth.user.id)
+        .await
+        .map_err(product_write_error)?
+        .ok_or(HttpError::bad_request("Product not found".to_string()))?;
+
+    Ok(Json(ProductResponseDto {
+        status: "success".to_string(),
+        product,
+    }))
+}
+
+pub async fn get_product_prices(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+) -> Result<Json<ProductPriceHistoryDto>, HttpError> {
+    let product_uuid = Uuid::parse_str(&product_id)
+        .map_err(|_| HttpError::bad_request("Invalid product ID".to_string()))?;
+
+    let prices = app_state.db_client
+        .get_product_prices(product_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(ProductPriceHistoryDto {
+        status: "success".to_string(),
+        prices,
+    }))
+}
+
+pub async fn archive_product(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+) -> Result<Json<ProductResponseDto>, HttpError> {
+    set_archived(&app_state, &product_id, true).await
+}
+
+pub async fn unarchive_product(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+) -> Result<Json<ProductResponseDto>, HttpError> {
+    set_archived(&app_state, &product_id, false).await
+}
+
+async fn set_archived(
+    app_state: &AppState,
+    product_id: &str,
+    archived: bool,
+) -> Result<Json<ProductResponseDto>, HttpError> {
+    let product_uuid = Uuid::parse_str(product_id)
+        .map_err(|_| HttpError::bad_request("Invalid product ID".to_string()))?;
+
+    let product = app_state.db_client
+        .set_product_archived(product_uuid, archived)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Product not found".to_string()))?;
+
+    Ok(Json(ProductResponseDto {
+        status: "success".to_string(),
+        product,
+    }))
+}
+
+pub async fn set_reorder_levels(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+    Json(body): Json<ReorderLevelsDto>,
+) -> Result<Json<ProductResponseDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let product_uuid = Uuid::parse_str(&product_id)
+        .map_err(|_| HttpError::bad_request("Invalid product ID".to_string()))?;
+
+    match (body.reorder_point, body.target_level) {
+        (None, Some(_)) => {
+            return Err(HttpError::bad_request("A target level needs a reorder point".to_string()));
+        }
+        (Some(point), Some(target)) if target < point => {
+            return Err(HttpError::bad_request("Target level must not be below the reorder point".to_string()));
+        }
+        _ => {}
+    }
+
+    let product = app_state.db_client
+        .set_reorder_levels(product_uuid, body.reorder_point, body.target_level)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Product not found".to_string()))?;
+
+    Ok(Json(ProductResponseDto {
+        status: "success".to_string(),
+        product,
+    }))
+}
+
+pub async fn create_product_category(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateProductCategoryDto>,
+) -> Result<Json<ProductCategoryResponseDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
 
+    let category = app_state.db_client
+        .create_product_category(body.name.trim())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation("Category already exists".to_string())
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
 
+    Ok(Json(ProductCategoryResponseDto {
+        status: "success".to_string(),
+        category,
+    }))
+}
+
+pub async fn get_product_categories(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<ProductCategoriesListResponseDto>, HttpError> {
+    let categories = app_state.db_client
+        .get_product_categories()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(ProductCategoriesListResponseDto {
+        status: "success".to_string(),
+        results: categories.len(),
+        categories,
+    }))
+}
+
+// SKU and barcode are unique; category_id must name an existing category
+fn product_write_error(e: sqlx::Error) -> HttpError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+            HttpError::unique_constraint_violation("SKU or barcode is already used by another product".to_string())
+        }
+        sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+            HttpError::bad_request("Category not found".to_string())
+        }
+        e => HttpError::server_error(e.to_string()),
+    }
+}
@@ -1,15 +1,14 @@
 use axum::{
-    extract::Extension,
-    http::StatusCode,
+    extract::{Extension, Query},
     Json,
 };
 use std::sync::Arc;
 use crate::dtos::{CreateSaleRequest, CreateSaleResponse, DailyProductSaleRequest, DailyProductSaleListResponse, DailySalesRevenueResponse, DailyCommissionRequest, DailyCommissionResponse,
-                    PendingPaymentResponse, GetAllSalesResponse, SaleDto};
-use crate::error::{HttpError, ErrorMessage};
+                    PendingPaymentResponse, GetAllSalesResponse, SaleDto, MySalesQuery, TaxSummaryQuery, TaxSummaryResponse};
+use crate::error::HttpError;
 use crate::db::{SalesExt};
-use crate::models::UserRole;
-use crate::middleware::JWTAuthMiddeware;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
 use crate::AppState;
 use axum::routing::{get,post};
 use axum::Router;
@@ -17,27 +16,20 @@ use uuid::Uuid;
 
 pub fn sales_handler() -> Router {
     Router::new()
-        .route("/create", post(create_sale))
-        .route("/daily-product-sales", get(get_daily_product_sales))
-        .route("/daily-sales-revenue", get(get_daily_sales_revenue))
-        .route("/daily-commission", get(get_daily_commission))
-        .route("/pending-payments", get(get_pending_payments))
-        .route("/all", get(get_all_sales))
+        .route("/create", post(create_sale).route_layer(require_roles(Permission::CreateSale.roles())))
+        .route("/daily-product-sales", get(get_daily_product_sales).route_layer(require_roles(Permission::ViewSalesReports.roles())))
+        .route("/daily-sales-revenue", get(get_daily_sales_revenue).route_layer(require_roles(Permission::ViewSalesReports.roles())))
+        .route("/daily-commission", get(get_daily_commission).route_layer(require_roles(Permission::ViewSalesReports.roles())))
+        .route("/tax-summary", get(get_tax_summary).route_layer(require_roles(Permission::ViewSalesReports.roles())))
+        .route("/pending-payments", get(get_pending_payments).route_layer(require_roles(Permission::ViewPendingPayments.roles())))
+        .route("/all", get(get_all_sales).route_layer(require_roles(Permission::ViewSalesReports.roles())))
+        .route("/mine", get(get_my_sales).route_layer(require_roles(Permission::ViewOwnSales.roles())))
 }
 
 pub async fn create_sale(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<CreateSaleRequest>,
 ) -> Result<Json<CreateSaleResponse>, HttpError> {
-    // ✅ Only drivers can create sales
-    if jwt_auth.user.role != UserRole::Driver {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     // Use date from request or default to today
     let date = body.date;
 
@@ -50,28 +42,28 @@ pub async fn create_sale(
 
 
     // Create sale in DB
-    let sale = app_state.db_client
-        .create_sale( body.truckload_id, body.shop_id, date, products)
+    let (sale, lines) = app_state.db_client
+        .create_sale(body.truckload_id, body.shop_id, date, products, app_state.env.prices_include_tax)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("A product is archived or has no price on the sale date".to_string()),
+            e => HttpError::server_error(e.to_string()),
+        })?;
 
     Ok(Json(CreateSaleResponse {
         salesid: sale.salesid,
+        net_amount: sale.net_amount,
+        tax_amount: sale.tax_amount,
+        total_amount: sale.total_amount,
+        lines,
         message: "Sale recorded successfully".to_string(),
     }))
 }
 
 pub async fn get_daily_product_sales(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<DailyProductSaleRequest>,
 ) -> Result<Json<DailyProductSaleListResponse>, HttpError> {
-    if jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let sales = app_state.db_client
         .get_daily_product_sales(body.date)
         .await
@@ -85,16 +77,9 @@ pub async fn get_daily_product_sales(
 }
 
 pub async fn get_daily_sales_revenue(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<DailyProductSaleRequest>, 
 ) -> Result<Json<DailySalesRevenueResponse>, HttpError> {
-    if jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let total_revenue = app_state.db_client
         .get_daily_total_sales_revenue(body.date)
         .await
@@ -108,16 +93,9 @@ pub async fn get_daily_sales_revenue(
 
 
 pub async fn get_daily_commission(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<DailyCommissionRequest>,
 ) -> Result<Json<DailyCommissionResponse>, HttpError> {
-    if jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let total_commission = app_state.db_client
         .get_daily_commission(body.date)
         .await
@@ -129,16 +107,34 @@ pub async fn get_daily_commission(
     }))
 }
 
+// GET /sales/tax-summary?from=YYYY-MM-DD&to=YYYY-MM-DD (both inclusive)
+pub async fn get_tax_summary(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<TaxSummaryQuery>,
+) -> Result<Json<TaxSummaryResponse>, HttpError> {
+    if params.to < params.from {
+        return Err(HttpError::bad_request("'to' must not be before 'from'".to_string()));
+    }
+
+    let rates = app_state.db_client
+        .get_tax_summary(params.from, params.to)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(TaxSummaryResponse {
+        status: "success".to_string(),
+        from: params.from,
+        to: params.to,
+        net_amount: rates.iter().map(|r| r.net_amount).sum(),
+        tax_amount: rates.iter().map(|r| r.tax_amount).sum(),
+        gross_amount: rates.iter().map(|r| r.gross_amount).sum(),
+        rates,
+    }))
+}
+
 pub async fn get_pending_payments(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
 ) -> Result<Json<Vec<PendingPaymentResponse>>, HttpError> {
-    if jwt_auth.user.role != UserRole::Admin  && jwt_auth.user.role != crate::models::UserRole::Manager && jwt_auth.user.role != crate::models::UserRole::Driver{
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
     let payments = app_state.db_client
         .get_pending_payments()
         .await
@@ -148,16 +144,8 @@ pub async fn get_pending_payments(
 }
 
 pub async fn get_all_sales(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
 ) -> Result<Json<GetAllSalesResponse>, HttpError> {
-    if jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     let sales = app_state.db_client
         .get_all_sales()
         .await
@@ -170,6 +158,8 @@ pub async fn get_all_sales(
             truckload_id: r.truckload_id,
             shop_id: r.shop_id,
             date: r.date,
+            net_amount: r.net_amount,
+            tax_amount: r.tax_amount,
             total_amount: r.total_amount,
             paid_amount: r.paid_amount,
             status: r.status,
@@ -179,3 +169,17 @@ pub async fn get_all_sales(
     Ok(Json(response))
 }
 
+pub async fn get_my_sales(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<MySalesQuery>,
+) -> Result<Json<GetAllSalesResponse>, HttpError> {
+    let date = params.date.unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    let sales = app_state.db_client
+        .get_driver_sales(jwt_auth.user.id, date)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(GetAllSalesResponse { sales }))
+}
@@ -1,42 +1,39 @@
 use axum::{
-    extract::Extension,
-    http::StatusCode,
+    extract::{Extension, Path},
     Json,
 };
 use std::sync::Arc;
-use crate::dtos::{CreateShopRequest,CreateShopResponse};
-use crate::error::{HttpError, ErrorMessage};
+use uuid::Uuid;
+use validator::Validate;
+use crate::dtos::{CreateShopGroupDto, CreateShopRequest, CreateShopResponse, SetShopGroupDto};
+use crate::models::{Shop, ShopGroup};
+use crate::error::HttpError;
 use crate::db::ShopExt;
-use crate::middleware::JWTAuthMiddeware;
+use crate::middleware::require_roles;
+use crate::permissions::Permission;
 use crate::AppState;
-use axum::routing::{post, get};
+use axum::routing::{post, get, put};
 use axum::Router;
 
 pub fn shop_handler() -> Router {
     Router::new()
-        .route("/create", post(create_shop))
-        .route("/all", get(get_all_shops))
+        .route("/create", post(create_shop).route_layer(require_roles(Permission::ManageShops.roles())))
+        .route("/all", get(get_all_shops).route_layer(require_roles(Permission::ManageShops.roles())))
+
+        // Shop groups share group price lists
+        .route("/groups", post(create_shop_group).route_layer(require_roles(Permission::ManageShops.roles())))
+        .route("/groups", get(get_shop_groups).route_layer(require_roles(Permission::ManageShops.roles())))
+        .route("/:id/group", put(set_shop_group).route_layer(require_roles(Permission::ManageShops.roles())))
 }
 
 pub async fn create_shop(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
     Json(body): Json<CreateShopRequest>,
 ) -> Result<Json<CreateShopResponse>, HttpError> {
-    // Only Admin or Manager
-    if jwt_auth.user.role != crate::models::UserRole::Manager
-        && jwt_auth.user.role != crate::models::UserRole::Admin
-    {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     let shop = app_state.db_client
-        .create_shop(&body.name, &body.address, body.city.as_deref(), body.district.as_deref(), body.contact_number.as_deref())
+        .create_shop(&body.name, &body.address, body.city.as_deref(), body.district.as_deref(), body.contact_number.as_deref(), body.group_id)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(shop_group_error)?;
 
     Ok(Json(CreateShopResponse {
         shopid: shop.shopid,
@@ -45,18 +42,8 @@ pub async fn create_shop(
 }
 
 pub async fn get_all_shops(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
 ) -> Result<Json<Vec<CreateShopResponse>>, HttpError> {
-    if jwt_auth.user.role != crate::models::UserRole::Manager
-        && jwt_auth.user.role != crate::models::UserRole::Admin
-    {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     let shops = app_state.db_client
         .get_all_shops()
         .await
@@ -72,3 +59,60 @@ pub async fn get_all_shops(
 
     Ok(Json(response))
 }
+
+pub async fn create_shop_group(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateShopGroupDto>,
+) -> Result<Json<ShopGroup>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let group = app_state.db_client
+        .create_shop_group(body.name.trim())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation("Shop group already exists".to_string())
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(group))
+}
+
+pub async fn get_shop_groups(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<Vec<ShopGroup>>, HttpError> {
+    let groups = app_state.db_client
+        .get_shop_groups()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(groups))
+}
+
+pub async fn set_shop_group(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(shop_id): Path<String>,
+    Json(body): Json<SetShopGroupDto>,
+) -> Result<Json<Shop>, HttpError> {
+    let shop_uuid = Uuid::parse_str(&shop_id)
+        .map_err(|_| HttpError::bad_request("Invalid shop ID".to_string()))?;
+
+    let shop = app_state.db_client
+        .set_shop_group(shop_uuid, body.group_id)
+        .await
+        .map_err(shop_group_error)?
+        .ok_or(HttpError::bad_request("Shop not found".to_string()))?;
+
+    Ok(Json(shop))
+}
+
+fn shop_group_error(e: sqlx::Error) -> HttpError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+            HttpError::bad_request("Shop group not found".to_string())
+        }
+        e => HttpError::server_error(e.to_string()),
+    }
+}
@@ -0,0 +1,281 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::dtos::{CreateStockAdjustmentDto, ExpiringStockQuery, ExpiringStockResponse, ProductStockResponse, ReorderSuggestionQuery, ReorderSuggestionsResponse, StockAdjustmentListResponse, StockAdjustmentQuery, StockAdjustmentResponse, StockListResponse, StockMovementListResponse, StockMovementQuery, StockQuery};
+use crate::error::HttpError;
+use crate::db::StockExt;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
+use crate::models::{AdjustmentReason, AdjustmentStatus};
+use crate::AppState;
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn stock_handler() -> Router {
+    Router::new()
+        // Per-product levels; ?as_of=YYYY-MM-DD for the level at the end of that day,
+        // ?warehouse_id= for one warehouse instead of the total
+        .route("/", get(get_stock).route_layer(require_roles(Permission::ViewStock.roles())))
+        .route("/:product_id", get(get_product_stock).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Batches expiring within ?days=N (default 3), already expired ones included
+        .route("/expiring", get(get_expiring_stock).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Products at or below their reorder point with days of cover at recent outflow (?days=N, default 14)
+        .route("/reorder-suggestions", get(get_reorder_suggestions).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Append-only ledger of every stock change, filterable and paged
+        .route("/movements", get(get_stock_movements).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Manual corrections; large ones wait for an admin to approve or reject them
+        .route("/adjustments", post(create_stock_adjustment).route_layer(require_roles(Permission::CreateStockAdjustment.roles())))
+        .route("/adjustments", get(get_stock_adjustments).route_layer(require_roles(Permission::ViewStock.roles())))
+        .route("/adjustments/:id/approve", post(approve_stock_adjustment).route_layer(require_roles(Permission::ApproveStockAdjustment.roles())))
+        .route("/adjustments/:id/reject", post(reject_stock_adjustment).route_layer(require_roles(Permission::ApproveStockAdjustment.roles())))
+}
+
+pub async fn get_stock(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<StockQuery>,
+) -> Result<Json<StockListResponse>, HttpError> {
+    let stock = app_state.db_client
+        .get_stock_levels(None, params.as_of, params.warehouse_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    // Archived products only matter while they still have stock
+    let stock: Vec<_> = stock
+        .into_iter()
+        .filter(|level| !level.archived || level.quantity != 0)
+        .collect();
+
+    Ok(Json(StockListResponse {
+        status: "success".to_string(),
+        as_of: params.as_of,
+        warehouse_id: params.warehouse_id,
+        results: stock.len(),
+        stock,
+    }))
+}
+
+pub async fn get_product_stock(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(product_id): Path<String>,
+    Query(params): Query<StockQuery>,
+) -> Result<Json<ProductStockResponse>, HttpError> {
+    let product_uuid = Uuid::parse_str(&product_id)
+        .map_err(|_| HttpError::bad_request("Invalid product ID".to_string()))?;
+
+    let stock = app_state.db_client
+        .get_stock_levels(Some(product_uuid), params.as_of, params.warehouse_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .pop()
+        .ok_or(HttpError::bad_request("Product not found".to_string()))?;
+
+    let batches = match params.as_of {
+        Some(_) => Vec::new(),
+        None => app_state.db_client
+            .get_product_batches(product_uuid, params.warehouse_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+    };
+
+    let warehouses = match params.as_of {
+        Some(_) => Vec::new(),
+        None => app_state.db_client
+            .get_product_warehouse_stock(product_uuid)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?,
+    };
+
+    Ok(Json(ProductStockResponse {
+        status: "success".to_string(),
+        as_of: params.as_of,
+        warehouse_id: params.warehouse_id,
+        stock,
+        warehouses,
+        batches,
+    }))
+}
+
+pub async fn get_expiring_stock(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<ExpiringStockQuery>,
+) -> Result<Json<ExpiringStockResponse>, HttpError> {
+    let days = params.days.unwrap_or(3);
+    if !(1..=365).contains(&days) {
+        return Err(HttpError::bad_request("days must be between 1 and 365".to_string()));
+    }
+
+    let today = chrono::Local::now().date_naive();
+    let batches = app_state.db_client
+        .get_expiring_batches(today, today + chrono::Days::new(days as u64), params.warehouse_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(ExpiringStockResponse {
+        status: "success".to_string(),
+        days,
+        results: batches.len(),
+        batches,
+    }))
+}
+
+pub async fn get_reorder_suggestions(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<ReorderSuggestionQuery>,
+) -> Result<Json<ReorderSuggestionsResponse>, HttpError> {
+    let days = params.days.unwrap_or(14);
+    if !(1..=365).contains(&days) {
+        return Err(HttpError::bad_request("days must be between 1 and 365".to_string()));
+    }
+
+    let today = chrono::Local::now().date_naive();
+    let suggestions = app_state.db_client
+        .get_reorder_suggestions(today, days)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(ReorderSuggestionsResponse {
+        status: "success".to_string(),
+        days,
+        results: suggestions.len(),
+        suggestions,
+    }))
+}
+
+pub async fn get_stock_movements(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<StockMovementQuery>,
+) -> Result<Json<StockMovementListResponse>, HttpError> {
+    params.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if matches!((params.from, params.to), (Some(from), Some(to)) if to < from) {
+        return Err(HttpError::bad_request("'to' must not be before 'from'".to_string()));
+    }
+
+    let page = params.page.unwrap_or(1) as u32;
+    let limit = params.limit.unwrap_or(100);
+
+    let movements = app_state.db_client
+        .get_stock_movements(&params, page, limit)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockMovementListResponse {
+        status: "success".to_string(),
+        results: movements.len(),
+        movements,
+    }))
+}
+
+pub async fn create_stock_adjustment(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Json(body): Json<CreateStockAdjustmentDto>,
+) -> Result<Json<StockAdjustmentResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    // Only a count correction can add stock
+    if body.quantity > 0 && body.reason != AdjustmentReason::CountCorrection {
+        return Err(HttpError::bad_request(format!(
+            "A {} adjustment must remove stock",
+            body.reason.to_str()
+        )));
+    }
+
+    if body.quantity < 0 && body.batch_id.is_none() {
+        return Err(HttpError::bad_request("batch_id is required when removing stock".to_string()));
+    }
+
+    let adjustment = app_state.db_client
+        .create_stock_adjustment(&body, jwt_auth.user.id, app_state.env.stock_adjustment_approval_limit)
+        .await
+        .map_err(adjustment_error)?;
+
+    let message = if adjustment.status == AdjustmentStatus::Pending.to_str() {
+        "Adjustment is awaiting approval"
+    } else {
+        "Adjustment applied"
+    };
+
+    Ok(Json(StockAdjustmentResponse {
+        status: "success".to_string(),
+        message: message.to_string(),
+        adjustment,
+    }))
+}
+
+pub async fn get_stock_adjustments(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<StockAdjustmentQuery>,
+) -> Result<Json<StockAdjustmentListResponse>, HttpError> {
+    let adjustments = app_state.db_client
+        .get_stock_adjustments(params.status)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockAdjustmentListResponse {
+        status: "success".to_string(),
+        results: adjustments.len(),
+        adjustments,
+    }))
+}
+
+pub async fn approve_stock_adjustment(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Path(adjustment_id): Path<String>,
+) -> Result<Json<StockAdjustmentResponse>, HttpError> {
+    decide_stock_adjustment(&app_state, &adjustment_id, AdjustmentStatus::Approved, jwt_auth.user.id).await
+}
+
+pub async fn reject_stock_adjustment(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Path(adjustment_id): Path<String>,
+) -> Result<Json<StockAdjustmentResponse>, HttpError> {
+    decide_stock_adjustment(&app_state, &adjustment_id, AdjustmentStatus::Rejected, jwt_auth.user.id).await
+}
+
+async fn decide_stock_adjustment(
+    app_state: &AppState,
+    adjustment_id: &str,
+    decision: AdjustmentStatus,
+    user_id: Uuid,
+) -> Result<Json<StockAdjustmentResponse>, HttpError> {
+    let adjustment_uuid = Uuid::parse_str(adjustment_id)
+        .map_err(|_| HttpError::bad_request("Invalid adjustment ID".to_string()))?;
+
+    let adjustment = app_state.db_client
+        .decide_stock_adjustment(adjustment_uuid, decision, user_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("Adjustment not found or already decided".to_string()),
+            e => adjustment_error(e),
+        })?;
+
+    Ok(Json(StockAdjustmentResponse {
+        status: "success".to_string(),
+        message: format!("Adjustment {}", decision.to_str()),
+        adjustment,
+    }))
+}
+
+fn adjustment_error(e: sqlx::Error) -> HttpError {
+    match e {
+        sqlx::Error::RowNotFound => HttpError::bad_request("Product, batch or warehouse not found".to_string()),
+        sqlx::Error::Database(db_err) if db_err.is_check_violation() => {
+            HttpError::new("Adjustment would take stock below zero".to_string(), StatusCode::CONFLICT)
+        }
+        e => HttpError::server_error(e.to_string()),
+    }
+}
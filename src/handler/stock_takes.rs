@@ -0,0 +1,247 @@
+use axum::{
+    extract::{Extension, Path},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use validator::Validate;
+use crate::dtos::{CreateStockTakeDto, PostStockTakeResponse, StockTakeListResponse, StockTakeResponse, StockTakeVarianceResponse, SubmitStockCountsDto};
+use crate::error::HttpError;
+use crate::db::StockTakeExt;
+use crate::handler::warehouses::resolve_warehouse;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::models::{StockTake, StockTakeStatus};
+use crate::permissions::Permission;
+use crate::utils::csv;
+use crate::AppState;
+use axum::routing::{get, post, put};
+use axum::Router;
+use uuid::Uuid;
+
+pub fn stock_takes_handler() -> Router {
+    Router::new()
+        // Only one stock take can be open per warehouse at a time
+        .route("/open", post(open_stock_take).route_layer(require_roles(Permission::ManageStockTakes.roles())))
+        .route("/all", get(get_stock_takes).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Variance so far (system vs counted); /report is the same as a CSV download
+        .route("/:id", get(get_stock_take_variance).route_layer(require_roles(Permission::ViewStock.roles())))
+        .route("/:id/report", get(get_stock_take_report).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        .route("/:id/counts", put(submit_stock_counts).route_layer(require_roles(Permission::ManageStockTakes.roles())))
+        .route("/:id/post", post(post_stock_take).route_layer(require_roles(Permission::ManageStockTakes.roles())))
+        .route("/:id/cancel", post(cancel_stock_take).route_layer(require_roles(Permission::ManageStockTakes.roles())))
+}
+
+pub async fn open_stock_take(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Json(body): Json<CreateStockTakeDto>,
+) -> Result<Json<StockTakeResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let warehouse = resolve_warehouse(&app_state, body.warehouse_id).await?;
+
+    let stock_take = app_state.db_client
+        .open_stock_take(warehouse.id, body.notes.as_deref(), jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation(format!("A stock take is already open in {}", warehouse.name))
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(StockTakeResponse {
+        status: "success".to_string(),
+        stock_take,
+    }))
+}
+
+pub async fn get_stock_takes(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<StockTakeListResponse>, HttpError> {
+    let stock_takes = app_state.db_client
+        .get_stock_takes()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockTakeListResponse {
+        status: "success".to_string(),
+        results: stock_takes.len(),
+        stock_takes,
+    }))
+}
+
+pub async fn get_stock_take_variance(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(stock_take_id): Path<String>,
+) -> Result<Json<StockTakeVarianceResponse>, HttpError> {
+    let stock_take = find_stock_take(&app_state, &stock_take_id).await?;
+
+    let lines = app_state.db_client
+        .get_stock_take_lines(stock_take.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockTakeVarianceResponse {
+        status: "success".to_string(),
+        stock_take,
+        results: lines.len(),
+        total_variance_value: lines.iter().map(|line| line.variance_value).sum(),
+        lines,
+    }))
+}
+
+pub async fn get_stock_take_report(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(stock_take_id): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    let stock_take = find_stock_take(&app_state, &stock_take_id).await?;
+
+    let lines = app_state.db_client
+        .get_stock_take_lines(stock_take.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let mut report = csv::row(&[
+        "Product", "SKU", "Batch", "System", "Counted", "Variance", "Unit price", "Variance value",
+    ]);
+    for line in &lines {
+        report.push_str(&csv::row(&[
+            line.product_name.clone(),
+            line.sku.clone(),
+            line.batch_number.clone().unwrap_or_default(),
+            line.system_quantity.to_string(),
+            line.counted_quantity.to_string(),
+            line.variance.to_string(),
+            line.unit_price.to_string(),
+            line.variance_value.to_string(),
+        ]));
+    }
+    let total: Decimal = lines.iter().map(|line| line.variance_value).sum();
+    report.push_str(&csv::row(&["Total", "", "", "", "", "", "", &total.to_string()]));
+
+    let disposition = format!(
+        "attachment; filename=\"stock-take-{}-{}.csv\"",
+        stock_take.opened_at.format("%Y-%m-%d"),
+        stock_take.status
+    );
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        report,
+    ))
+}
+
+pub async fn submit_stock_counts(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Path(stock_take_id): Path<String>,
+    Json(body): Json<SubmitStockCountsDto>,
+) -> Result<Json<StockTakeVarianceResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.counts.is_empty() {
+        return Err(HttpError::bad_request("At least one count is required".to_string()));
+    }
+
+    let stock_take = find_stock_take(&app_state, &stock_take_id).await?;
+    ensure_open(&stock_take)?;
+
+    app_state.db_client
+        .submit_stock_counts(stock_take.id, &body.counts, jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("Product or batch not found in this warehouse, or the stock take is closed".to_string()),
+            sqlx::Error::Protocol(message) => HttpError::bad_request(message),
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    get_stock_take_variance(Extension(app_state), Path(stock_take_id)).await
+}
+
+pub async fn post_stock_take(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Path(stock_take_id): Path<String>,
+) -> Result<Json<PostStockTakeResponse>, HttpError> {
+    let stock_take = find_stock_take(&app_state, &stock_take_id).await?;
+    ensure_open(&stock_take)?;
+
+    let (stock_take, adjustments) = app_state.db_client
+        .post_stock_take(stock_take.id, jwt_auth.user.id, app_state.env.stock_adjustment_approval_limit)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("Stock take is no longer open".to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_check_violation() => HttpError::new(
+                "Batch stock no longer covers a counted shortfall; recount and post again".to_string(),
+                StatusCode::CONFLICT,
+            ),
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    let pending = adjustments
+        .iter()
+        .filter(|adjustment| adjustment.status == "pending")
+        .count();
+
+    Ok(Json(PostStockTakeResponse {
+        status: "success".to_string(),
+        message: format!(
+            "Stock take posted with {} adjustments, {} awaiting approval",
+            adjustments.len(),
+            pending
+        ),
+        stock_take,
+        adjustments,
+    }))
+}
+
+pub async fn cancel_stock_take(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Path(stock_take_id): Path<String>,
+) -> Result<Json<StockTakeResponse>, HttpError> {
+    let stock_take = find_stock_take(&app_state, &stock_take_id).await?;
+    ensure_open(&stock_take)?;
+
+    let stock_take = app_state.db_client
+        .cancel_stock_take(stock_take.id, jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request("Stock take is no longer open".to_string()),
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(StockTakeResponse {
+        status: "success".to_string(),
+        stock_take,
+    }))
+}
+
+async fn find_stock_take(app_state: &AppState, stock_take_id: &str) -> Result<StockTake, HttpError> {
+    let stock_take_uuid = Uuid::parse_str(stock_take_id)
+        .map_err(|_| HttpError::bad_request("Invalid stock take ID".to_string()))?;
+
+    app_state.db_client
+        .get_stock_take(stock_take_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Stock take not found".to_string()))
+}
+
+fn ensure_open(stock_take: &StockTake) -> Result<(), HttpError> {
+    if stock_take.status != StockTakeStatus::Open.to_str() {
+        return Err(HttpError::bad_request(format!("Stock take is {}", stock_take.status)));
+    }
+    Ok(())
+}
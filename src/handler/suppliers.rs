@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use chrono::NaiveDate;
+use crate::dtos::{CreateSupplierDto, SupplierDeliveriesQuery, SupplierDeliveriesResponse, SupplierListResponse, SupplierQuery, SupplierResponse, SupplierTotalsResponse, UpdateSupplierDto};
+use crate::error::HttpError;
+use crate::db::SupplierExt;
+use crate::middleware::require_roles;
+use crate::permissions::Permission;
+use crate::AppState;
+use axum::routing::{get, post, put};
+use axum::Router;
+
+pub fn suppliers_handler() -> Router {
+    Router::new()
+        .route("/create", post(create_supplier).route_layer(require_roles(Permission::ManageSuppliers.roles())))
+        .route("/all", get(get_suppliers).route_layer(require_roles(Permission::ManageSuppliers.roles())))
+
+        // Deliveries and units received per supplier over ?from=&to=
+        .route("/totals", get(get_supplier_totals).route_layer(require_roles(Permission::ViewAllDeliveries.roles())))
+
+        .route("/:id", get(get_supplier).route_layer(require_roles(Permission::ManageSuppliers.roles())))
+        .route("/:id", put(update_supplier).route_layer(require_roles(Permission::ManageSuppliers.roles())))
+        .route("/:id/deliveries", get(get_supplier_deliveries).route_layer(require_roles(Permission::ViewAllDeliveries.roles())))
+}
+
+pub async fn create_supplier(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateSupplierDto>,
+) -> Result<Json<SupplierResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let supplier = app_state.db_client
+        .create_supplier(&body)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(SupplierResponse {
+        status: "success".to_string(),
+        supplier,
+    }))
+}
+
+pub async fn get_suppliers(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<SupplierQuery>,
+) -> Result<Json<SupplierListResponse>, HttpError> {
+    let suppliers = app_state.db_client
+        .get_suppliers(params.kind, params.active)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(SupplierListResponse {
+        status: "success".to_string(),
+        results: suppliers.len(),
+        suppliers,
+    }))
+}
+
+pub async fn get_supplier(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(supplier_id): Path<String>,
+) -> Result<Json<SupplierResponse>, HttpError> {
+    let supplier_uuid = parse_supplier_id(&supplier_id)?;
+
+    let supplier = app_state.db_client
+        .get_supplier(supplier_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Supplier not found".to_string()))?;
+
+    Ok(Json(SupplierResponse {
+        status: "success".to_string(),
+        supplier,
+    }))
+}
+
+pub async fn update_supplier(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(supplier_id): Path<String>,
+    Json(body): Json<UpdateSupplierDto>,
+) -> Result<Json<SupplierResponse>, HttpError> {
+    let supplier_uuid = parse_supplier_id(&supplier_id)?;
+
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let supplier = app_state.db_client
+        .update_supplier(supplier_uuid, &body)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Supplier not found".to_string()))?;
+
+    Ok(Json(SupplierResponse {
+        status: "success".to_string(),
+        supplier,
+    }))
+}
+
+pub async fn get_supplier_deliveries(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(supplier_id): Path<String>,
+    Query(params): Query<SupplierDeliveriesQuery>,
+) -> Result<Json<SupplierDeliveriesResponse>, HttpError> {
+    let supplier_uuid = parse_supplier_id(&supplier_id)?;
+    check_range(params.from, params.to)?;
+
+    let supplier = app_state.db_client
+        .get_supplier(supplier_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Supplier not found".to_string()))?;
+
+    let deliveries = app_state.db_client
+        .get_supplier_deliveries(supplier.id, params.from, params.to)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let totals = app_state.db_client
+        .get_supplier_product_totals(supplier.id, params.from, params.to)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(SupplierDeliveriesResponse {
+        status: "success".to_string(),
+        supplier,
+        from: params.from,
+        to: params.to,
+        results: deliveries.len(),
+        deliveries,
+        totals,
+    }))
+}
+
+pub async fn get_supplier_totals(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<SupplierDeliveriesQuery>,
+) -> Result<Json<SupplierTotalsResponse>, HttpError> {
+    check_range(params.from, params.to)?;
+
+    let suppliers = app_state.db_client
+        .get_supplier_totals(params.from, params.to)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(SupplierTotalsResponse {
+        status: "success".to_string(),
+        from: params.from,
+        to: params.to,
+        results: suppliers.len(),
+        suppliers,
+    }))
+}
+
+fn parse_supplier_id(supplier_id: &str) -> Result<Uuid, HttpError> {
+    Uuid::parse_str(supplier_id)
+        .map_err(|_| HttpError::bad_request("Invalid supplier ID".to_string()))
+}
+
+fn check_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(), HttpError> {
+    if matches!((from, to), (Some(from), Some(to)) if to < from) {
+        return Err(HttpError::bad_request("'to' must not be before 'from'".to_string()));
+    }
+    Ok(())
+}
@@ -3,12 +3,17 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use std::collections::HashSet;
 use std::sync::Arc;
-use crate::dtos::{CreateTruckLoadRequest, CreateTruckLoadResponse,UpdateTruckLoadQuantityResponse, UpdateTruckLoadQuantityRequest, TruckLoadQuantityItemResponse};
-use crate::error::{HttpError, ErrorMessage};
-use crate::db::{ TruckLoadExt};
-use crate::models::UserRole;
-use crate::middleware::JWTAuthMiddeware;
+use validator::Validate;
+use crate::dtos::{CreateTruckLoadRequest, CreateTruckLoadResponse,UpdateTruckLoadQuantityResponse, UpdateTruckLoadQuantityRequest, TruckLoadQuantityItemResponse, MyTruckLoadResponse};
+use crate::error::HttpError;
+use crate::db::{StockExt, TruckLoadExt};
+use crate::handler::warehouses::resolve_warehouse;
+use crate::mail::mails::send_low_stock_email;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
+use crate::utils::reorder;
 use crate::AppState;
 use axum::routing::{get, post, patch};
 use axum::Router;
@@ -16,27 +21,37 @@ use uuid::Uuid;
 
 pub fn truck_load_handler() -> Router {
     Router::new()
-        .route("/create", post(create_truck_load))
-        .route("/history", get(get_truck_load_history))
-        .route("/update-remaining", patch( update_remaining_quantity))
+        .route("/create", post(create_truck_load).route_layer(require_roles(Permission::CreateTruckLoad.roles())))
+        .route("/history", get(get_truck_load_history).route_layer(require_roles(Permission::ViewTruckLoads.roles())))
+        .route("/update-remaining", patch( update_remaining_quantity).route_layer(require_roles(Permission::UpdateRemainingQuantity.roles())))
+        .route("/mine", get(get_my_truck_load).route_layer(require_roles(Permission::ViewOwnTruckLoad.roles())))
         
 }
 pub async fn create_truck_load(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Json(body): Json<CreateTruckLoadRequest>,
 ) -> Result<Json<CreateTruckLoadResponse>, HttpError> {
-    // Only Manager can create truck loads
-    if jwt_auth.user.role != UserRole::Manager{
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.products.is_empty() {
+        return Err(HttpError::bad_request("A truck load needs at least one product".to_string()));
+    }
+
+    let mut seen = HashSet::new();
+    if let Some(item) = body.products.iter().find(|item| !seen.insert(item.product_id)) {
+        return Err(HttpError::bad_request(format!(
+            "Product {} appears more than once",
+            item.product_id
+        )));
     }
 
     // Use date from request
     let date = body.date;
 
+    let warehouse = resolve_warehouse(&app_state, body.warehouse_id).await?;
+
     // Convert products to Vec<(Uuid, i32)>
     let products: Vec<(Uuid, i32)> = body
         .products
@@ -49,9 +64,24 @@ pub async fn create_truck_load(
 
     // Create truck load in DB
     let truck_load = app_state.db_client
-        .create_truck_load(driver_id, body.truck_id, date, products)
+        .create_truck_load(driver_id, body.truck_id, date, products.clone(), warehouse.id, jwt_auth.user.id)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => HttpError::bad_request(format!(
+                "A product is archived or has insufficient stock in {}",
+                warehouse.name
+            )),
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    // The load is already committed, so the alert is sent in the background
+    // and a failure is only logged
+    let alert_state = app_state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = alert_low_stock(&alert_state, &products).await {
+            eprintln!("Failed to send low stock alert: {}", e);
+        }
+    });
 
     Ok(Json(CreateTruckLoadResponse {
         truckloadid: truck_load.truckloadid,
@@ -60,18 +90,43 @@ pub async fn create_truck_load(
     }))
 }
 
+// Emails managers about products this load took down to their reorder point.
+// Products that were already at or below it don't alert again.
+async fn alert_low_stock(
+    app_state: &AppState,
+    loaded: &[(Uuid, i32)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let today = chrono::Local::now().date_naive();
+    let crossed: Vec<_> = app_state.db_client
+        .get_reorder_suggestions(today, 14)
+        .await?
+        .into_iter()
+        .filter(|product| {
+            let taken: i64 = loaded
+                .iter()
+                .filter(|(product_id, _)| *product_id == product.product_id)
+                .map(|(_, quantity)| i64::from(*quantity))
+                .sum();
+            taken > 0 && !reorder::needs_reorder(product.on_hand + taken, product.reorder_point)
+        })
+        .collect();
+
+    if crossed.is_empty() {
+        return Ok(());
+    }
+
+    for user in app_state.db_client.get_reorder_alert_recipients().await? {
+        if let Err(e) = send_low_stock_email(&user.email, &user.first_name, &crossed).await {
+            eprintln!("Failed to send low stock alert to {}: {:?}", user.email, e);
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn get_truck_load_history(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
 ) -> Result<Json<Vec<CreateTruckLoadResponse>>, HttpError> {
-    // Only Admin or Manager can view all truck loads
-    if jwt_auth.user.role != UserRole::Manager && jwt_auth.user.role != UserRole::Admin {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     // Fetch all truck loads
     let truck_loads = app_state.db_client
         .get_all_truck_loads()
@@ -92,19 +147,11 @@ pub async fn get_truck_load_history(
 }
 
 pub async fn update_remaining_quantity(
-    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
     Json(body): Json<UpdateTruckLoadQuantityRequest>,
 ) -> Result<Json<UpdateTruckLoadQuantityResponse>, HttpError> {
 
-    // Only manager
-    if jwt_auth.user.role != UserRole::Manager {
-        return Err(HttpError::new(
-            ErrorMessage::PermissionDenied.to_string(),
-            StatusCode::FORBIDDEN,
-        ));
-    }
-
     // Convert DTO items → simple tuple for DB layer
     let list = body
         .items
@@ -114,9 +161,13 @@ pub async fn update_remaining_quantity(
 
     let result = app_state
         .db_client
-        .update_remaining_quantities(body.truckloadid, list)
+        .update_remaining_quantities(body.truckloadid, list, jwt_auth.user.id)
         .await
-        .map_err(|e| HttpError::server_error(e.to_string()))?;
+        .map_err(|e| match e {
+            sqlx::Error::Protocol(msg) => HttpError::bad_request(msg),
+            sqlx::Error::RowNotFound => HttpError::bad_request("Product is not on this truck load".to_string()),
+            e => HttpError::server_error(e.to_string()),
+        })?;
 
     // Build response DTO
     let response = UpdateTruckLoadQuantityResponse {
@@ -133,4 +184,27 @@ pub async fn update_remaining_quantity(
     Ok(Json(response))
 }
 
+pub async fn get_my_truck_load(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<MyTruckLoadResponse>, HttpError> {
+    let today = chrono::Local::now().date_naive();
+
+    let truck_load = app_state.db_client
+        .get_driver_truck_load(jwt_auth.user.id, today)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::new("No truck load assigned to you today".to_string(), StatusCode::NOT_FOUND))?;
 
+    let products = app_state.db_client
+        .get_truck_load_products(truck_load.truckloadid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(MyTruckLoadResponse {
+        truckloadid: truck_load.truckloadid,
+        truck_id: truck_load.truckid,
+        date: truck_load.date,
+        products,
+    }))
+}
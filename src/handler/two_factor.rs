@@ -0,0 +1,340 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Extension},
+    http::StatusCode,
+    middleware::from_fn,
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::{LoginAttemptExt, TwoFactorExt, UserExt},
+    dtos::{RecoveryCodesDto, Response, TwoFactorChallengeDto, TwoFactorCodeDto, TwoFactorEnrolmentDto, TwoFactorLoginDto, TwoFactorSetupDto},
+    error::{ErrorMessage, HttpError},
+    middleware::{auth, require_roles, JWTAuthMiddeware},
+    models::{User, UserRole, UserTwoFactor},
+    permissions::Permission,
+    utils::{lockout, token, totp},
+    AppState,
+};
+
+use super::auth::{ensure_not_locked, record_failed_login, start_session};
+
+// Seconds a login challenge stays valid between the password and code steps
+const CHALLENGE_MAXAGE: i64 = 300;
+
+pub fn two_factor_handler() -> Router {
+    Router::new()
+        // Second login step: trade the challenge token and a code for a session
+        .route("/login", post(login_two_factor))
+
+        // Enrolment for admins whose login demanded 2FA setup
+        .route("/login/setup", post(login_setup_two_factor))
+        .route("/login/enable", post(login_enable_two_factor))
+
+        // Self-service enrolment and management for logged-in admins
+        .route("/setup", post(setup_two_factor)
+            .route_layer(require_roles(Permission::ManageTwoFactor.roles()))
+            .route_layer(from_fn(auth)))
+        .route("/enable", post(enable_two_factor)
+            .route_layer(require_roles(Permission::ManageTwoFactor.roles()))
+            .route_layer(from_fn(auth)))
+        .route("/disable", post(disable_two_factor)
+            .route_layer(require_roles(Permission::ManageTwoFactor.roles()))
+            .route_layer(from_fn(auth)))
+        .route("/recovery-codes", post(regenerate_recovery_codes)
+            .route_layer(require_roles(Permission::ManageTwoFactor.roles()))
+            .route_layer(from_fn(auth)))
+}
+
+// Called by `auth::login` once the password checks out. Returns the challenge
+// to send back instead of tokens, or None when no second factor is needed.
+pub async fn two_factor_challenge(
+    app_state: &AppState,
+    user: &User,
+) -> Result<Option<TwoFactorChallengeDto>, HttpError> {
+    let two_factor = app_state.db_client
+        .get_two_factor(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let (status, purpose) = if two_factor.is_some_and(|two_factor| two_factor.enabled_at.is_some()) {
+        ("2fa_required", token::TWO_FACTOR_LOGIN)
+    } else if app_state.env.require_admin_2fa && user.role == UserRole::Admin {
+        ("2fa_setup_required", token::TWO_FACTOR_SETUP)
+    } else {
+        return Ok(None);
+    };
+
+    let challenge_token = token::create_challenge_token(
+        &user.id.to_string(),
+        purpose,
+        app_state.env.jwt_secret.as_bytes(),
+        CHALLENGE_MAXAGE,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Some(TwoFactorChallengeDto {
+        status: status.to_string(),
+        challenge_token,
+    }))
+}
+
+pub async fn login_two_factor(
+    cookie_jar: CookieJar,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorLoginDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = challenge_user(&app_state, &body.challenge_token, token::TWO_FACTOR_LOGIN).await?;
+
+    // Wrong codes count towards the same lockout as wrong passwords
+    let account_key = user.email.to_lowercase();
+    let ip_key = client_addr.ip().to_string();
+
+    ensure_not_locked(&app_state, &account_key, &ip_key).await?;
+
+    let two_factor = enabled_two_factor(&app_state, user.id)
+        .await?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    if !check_second_factor(&app_state, &user, &two_factor, &body.code).await? {
+        record_failed_login(&app_state, &account_key, &ip_key).await?;
+        return Err(HttpError::bad_request(ErrorMessage::InvalidTwoFactorCode.to_string()));
+    }
+
+    app_state.db_client
+        .clear_failed_logins(lockout::ACCOUNT_SCOPE, &account_key)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let (cookie_jar, response) = start_session(&app_state, &user, cookie_jar).await?;
+
+    Ok((StatusCode::OK, cookie_jar, response))
+}
+
+pub async fn login_setup_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorSetupDto>,
+) -> Result<Json<TwoFactorEnrolmentDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = challenge_user(&app_state, &body.challenge_token, token::TWO_FACTOR_SETUP).await?;
+
+    start_enrolment(&app_state, &user).await
+}
+
+// Enrolment via a setup challenge does not log the user in; they log in again
+// with their new code, having saved the recovery codes.
+pub async fn login_enable_two_factor(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorLoginDto>,
+) -> Result<Json<RecoveryCodesDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user = challenge_user(&app_state, &body.challenge_token, token::TWO_FACTOR_SETUP).await?;
+
+    finish_enrolment(&app_state, &user, &body.code).await
+}
+
+pub async fn setup_two_factor(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<TwoFactorEnrolmentDto>, HttpError> {
+    start_enrolment(&app_state, &jwt_auth.user).await
+}
+
+pub async fn enable_two_factor(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorCodeDto>,
+) -> Result<Json<RecoveryCodesDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    finish_enrolment(&app_state, &jwt_auth.user, &body.code).await
+}
+
+pub async fn disable_two_factor(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorCodeDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if app_state.env.require_admin_2fa && jwt_auth.user.role == UserRole::Admin {
+        return Err(HttpError::new(
+            "Two-factor authentication is required for admin accounts".to_string(),
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let two_factor = enabled_two_factor(&app_state, jwt_auth.user.id)
+        .await?
+        .ok_or_else(|| HttpError::bad_request("Two-factor authentication is not enabled".to_string()))?;
+
+    if !check_second_factor(&app_state, &jwt_auth.user, &two_factor, &body.code).await? {
+        return Err(HttpError::bad_request(ErrorMessage::InvalidTwoFactorCode.to_string()));
+    }
+
+    app_state.db_client
+        .disable_two_factor(jwt_auth.user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+pub async fn regenerate_recovery_codes(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<TwoFactorCodeDto>,
+) -> Result<Json<RecoveryCodesDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let two_factor = enabled_two_factor(&app_state, jwt_auth.user.id)
+        .await?
+        .ok_or_else(|| HttpError::bad_request("Two-factor authentication is not enabled".to_string()))?;
+
+    if !check_second_factor(&app_state, &jwt_auth.user, &two_factor, &body.code).await? {
+        return Err(HttpError::bad_request(ErrorMessage::InvalidTwoFactorCode.to_string()));
+    }
+
+    let (recovery_codes, code_hashes): (Vec<String>, Vec<String>) =
+        totp::generate_recovery_codes().into_iter().unzip();
+
+    app_state.db_client
+        .replace_recovery_codes(jwt_auth.user.id, &code_hashes)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(RecoveryCodesDto {
+        status: "success".to_string(),
+        recovery_codes,
+    }))
+}
+
+async fn challenge_user(
+    app_state: &AppState,
+    challenge_token: &str,
+    purpose: &str,
+) -> Result<User, HttpError> {
+    let claims = token::decode_challenge_token(challenge_token, purpose, app_state.env.jwt_secret.as_bytes())?;
+
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))?;
+
+    let user = app_state.db_client
+        .get_user(Some(user_id), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string()))?;
+
+    if !user.is_active {
+        return Err(HttpError::new(ErrorMessage::AccountDeactivated.to_string(), StatusCode::FORBIDDEN));
+    }
+
+    Ok(user)
+}
+
+async fn enabled_two_factor(
+    app_state: &AppState,
+    user_id: Uuid,
+) -> Result<Option<UserTwoFactor>, HttpError> {
+    let two_factor = app_state.db_client
+        .get_two_factor(user_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(two_factor.filter(|two_factor| two_factor.enabled_at.is_some()))
+}
+
+// Accepts a current TOTP code, or burns one of the user's recovery codes.
+async fn check_second_factor(
+    app_state: &AppState,
+    user: &User,
+    two_factor: &UserTwoFactor,
+    code: &str,
+) -> Result<bool, HttpError> {
+    if totp::verify_code(&two_factor.secret, &user.email, code) {
+        return Ok(true);
+    }
+
+    app_state.db_client
+        .use_recovery_code(user.id, &totp::hash_recovery_code(code))
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))
+}
+
+async fn start_enrolment(
+    app_state: &AppState,
+    user: &User,
+) -> Result<Json<TwoFactorEnrolmentDto>, HttpError> {
+    if enabled_two_factor(app_state, user.id).await?.is_some() {
+        return Err(HttpError::unique_constraint_violation("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = totp::generate_secret();
+    let otpauth_uri = totp::otpauth_uri(&secret, &user.email)
+        .ok_or_else(|| HttpError::server_error("Failed to build authenticator URI".to_string()))?;
+
+    app_state.db_client
+        .save_pending_two_factor(user.id, &secret)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(TwoFactorEnrolmentDto {
+        status: "success".to_string(),
+        secret,
+        otpauth_uri,
+    }))
+}
+
+// The first valid code proves the authenticator app is set up correctly.
+async fn finish_enrolment(
+    app_state: &AppState,
+    user: &User,
+    code: &str,
+) -> Result<Json<RecoveryCodesDto>, HttpError> {
+    let two_factor = app_state.db_client
+        .get_two_factor(user.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or_else(|| HttpError::bad_request("Start two-factor setup first".to_string()))?;
+
+    if two_factor.enabled_at.is_some() {
+        return Err(HttpError::unique_constraint_violation("Two-factor authentication is already enabled".to_string()));
+    }
+
+    if !totp::verify_code(&two_factor.secret, &user.email, code) {
+        return Err(HttpError::bad_request(ErrorMessage::InvalidTwoFactorCode.to_string()));
+    }
+
+    let (recovery_codes, code_hashes): (Vec<String>, Vec<String>) =
+        totp::generate_recovery_codes().into_iter().unzip();
+
+    app_state.db_client
+        .enable_two_factor(user.id, &code_hashes)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(RecoveryCodesDto {
+        status: "success".to_string(),
+        recovery_codes,
+    }))
+}
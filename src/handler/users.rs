@@ -1,35 +1,67 @@
 use axum::{
     extract::{Path, Query, Extension},
+    http::StatusCode,
+    response::IntoResponse,
     Json,
 };
 use std::sync::Arc;
-use crate::dtos::{FilterUserDto, UserListResponseDto, RequestQueryDto};
-use crate::error::HttpError;
+use validator::Validate;
+use crate::dtos::{CreateInviteDto, FilterUserDto, InviteResponseDto, RevokeSessionsResponseDto, UnlockIpDto, UserListResponseDto, RequestQueryDto, Response, UpdateUserDto, UpdateUserRoleDto, UserPasswordUpdateDto};
+use crate::error::{ErrorMessage, HttpError};
+use crate::mail::mails::send_invite_email;
+use crate::utils::{lockout, password, token};
 use crate::AppState;
-use axum::routing::get;
+use axum::routing::{get, post, put};
 use axum::Router;
-use crate::db::UserExt; 
+use crate::db::{InviteExt, LoginAttemptExt, SessionExt, UserExt}; 
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::permissions::Permission;
 
 
 pub fn users_handler() -> Router {
     Router::new()
+        // Profile of the logged-in user
+        .route("/me", get(get_me).route_layer(require_roles(Permission::ViewOwnProfile.roles())))
+
         // Get a single user by ID
-        .route("/:id", get(get_user))
+        .route("/:id", get(get_user).route_layer(require_roles(Permission::ViewUsers.roles())))
         
         // Get list of users (with pagination query params)
-        .route("/", get(get_users))
-        
-        // // Update user first & last name
-        // .route("/:id/name", put(update_user_name))
-        
-        // // Update user role
-        // .route("/:id/role", put(update_user_role))
-        
-        // // Update user password
-        // .route("/:id/password", put(update_user_password))
+        .route("/", get(get_users).route_layer(require_roles(Permission::ViewUsers.roles())))
+
+        // Log a user out of every device (e.g. a lost phone)
+        .route("/:id/revoke-sessions", post(revoke_user_sessions).route_layer(require_roles(Permission::RevokeUserSessions.roles())))
+
+        // Invite a new user with a pre-assigned role
+        .route("/invite", post(create_invite).route_layer(require_roles(Permission::InviteUsers.roles())))
+
+        // Update profile fields (name, address, contact details)
+        .route("/:id", put(update_user).route_layer(require_roles(Permission::UpdateUsers.roles())))
+
+        // Update user role
+        .route("/:id/role", put(update_user_role).route_layer(require_roles(Permission::ChangeUserRole.roles())))
+
+        // Change own password (requires the current one)
+        .route("/me/password", put(update_user_password).route_layer(require_roles(Permission::ChangeOwnPassword.roles())))
+
+        // Soft-deactivate / reactivate; users are never deleted so their history survives
+        .route("/:id/deactivate", put(deactivate_user).route_layer(require_roles(Permission::DeactivateUsers.roles())))
+        .route("/:id/activate", put(activate_user).route_layer(require_roles(Permission::DeactivateUsers.roles())))
+
+        // Lift a brute-force lockout on an account
+        .route("/:id/unlock", post(unlock_user).route_layer(require_roles(Permission::UnlockUsers.roles())))
+
+        // Lift an IP lockout, e.g. a shared office NAT locked by one user's typos
+        .route("/unlock-ip", post(unlock_ip).route_layer(require_roles(Permission::UnlockUsers.roles())))
 }
 
 
+pub async fn get_me(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+) -> Result<Json<FilterUserDto>, HttpError> {
+    Ok(Json(FilterUserDto::filter_user(&jwt_auth.user)))
+}
+
 pub async fn get_user(
     Extension(app_state): Extension<Arc<AppState>>,
     Path(user_id): Path<String>,
@@ -66,7 +98,222 @@ pub async fn get_users(
 
     Ok(Json(UserListResponseDto {
         status: "success".to_string(),
-        users: users.iter().map(|u| FilterUserDto::filter_user(u)).collect(),
+        users: users.iter().map(FilterUserDto::filter_user).collect(),
         results: total,
     }))
 }
+
+pub async fn create_invite(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateInviteDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let existing = app_state.db_client
+        .get_user_by_email(&body.email)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if existing.is_some() {
+        return Err(HttpError::unique_constraint_violation(ErrorMessage::EmailExist.to_string()));
+    }
+
+    let invite = app_state.db_client
+        .create_invite(&body.email, body.role, jwt_auth.user.id, app_state.env.invite_maxage)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    let invite_token = token::create_invite_token(
+        &invite.id.to_string(),
+        &invite.email,
+        invite.role.to_str(),
+        app_state.env.jwt_secret.as_bytes(),
+        app_state.env.invite_maxage,
+    )
+    .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    // The invite stays valid even if SMTP is down; the admin can share the token manually
+    if let Err(e) = send_invite_email(&invite.email, &invite_token).await {
+        eprintln!("Failed to send invite email to {}: {:?}", invite.email, e);
+    }
+
+    Ok((StatusCode::CREATED, Json(InviteResponseDto {
+        status: "success".to_string(),
+        invite_id: invite.id,
+        email: invite.email,
+        role: invite.role.to_str().to_string(),
+        expires_at: invite.expires_at,
+        token: invite_token,
+    })))
+}
+
+pub async fn revoke_user_sessions(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<RevokeSessionsResponseDto>, HttpError> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    let revoked = app_state.db_client
+        .revoke_user_sessions(user_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(RevokeSessionsResponseDto {
+        status: "success".to_string(),
+        revoked,
+    }))
+}
+
+pub async fn update_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(body): Json<UpdateUserDto>,
+) -> Result<Json<FilterUserDto>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    let user = app_state.db_client
+        .update_user(user_uuid, &body)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("User not found".to_string()))?;
+
+    Ok(Json(FilterUserDto::filter_user(&user)))
+}
+
+pub async fn update_user_role(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(body): Json<UpdateUserRoleDto>,
+) -> Result<Json<FilterUserDto>, HttpError> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    // Stops the last admin from locking everyone out of user management
+    if user_uuid == jwt_auth.user.id {
+        return Err(HttpError::bad_request("You cannot change your own role".to_string()));
+    }
+
+    let user = app_state.db_client
+        .update_user_role(user_uuid, body.role)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("User not found".to_string()))?;
+
+    Ok(Json(FilterUserDto::filter_user(&user)))
+}
+
+pub async fn update_user_password(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<UserPasswordUpdateDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let is_valid = password::compare(&body.old_password, &jwt_auth.user.password)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if !is_valid {
+        return Err(HttpError::bad_request("Current password is incorrect".to_string()));
+    }
+
+    let hash_password = password::hash(&body.new_password)
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    app_state.db_client
+        .update_user_password(jwt_auth.user.id, &hash_password, jwt_auth.session_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(Response {
+        status: "success",
+        message: "Password updated successfully".to_string(),
+    }))
+}
+
+pub async fn deactivate_user(
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<FilterUserDto>, HttpError> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    if user_uuid == jwt_auth.user.id {
+        return Err(HttpError::bad_request("You cannot deactivate your own account".to_string()));
+    }
+
+    let user = app_state.db_client
+        .set_user_active(user_uuid, false)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("User not found".to_string()))?;
+
+    Ok(Json(FilterUserDto::filter_user(&user)))
+}
+
+pub async fn activate_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<FilterUserDto>, HttpError> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    let user = app_state.db_client
+        .set_user_active(user_uuid, true)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("User not found".to_string()))?;
+
+    Ok(Json(FilterUserDto::filter_user(&user)))
+}
+
+pub async fn unlock_user(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    let user_uuid = uuid::Uuid::parse_str(&user_id)
+        .map_err(|_| HttpError::bad_request("Invalid user ID".to_string()))?;
+
+    let user = app_state.db_client
+        .get_user(Some(user_uuid), None, None)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("User not found".to_string()))?;
+
+    app_state.db_client
+        .clear_failed_logins(lockout::ACCOUNT_SCOPE, &user.email.to_lowercase())
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(Response {
+        status: "success",
+        message: format!("Account {} unlocked", user.email),
+    }))
+}
+
+pub async fn unlock_ip(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<UnlockIpDto>,
+) -> Result<impl IntoResponse, HttpError> {
+    // Same textual form the login handler keys the IP scope by
+    let ip_key = body.ip.to_string();
+
+    app_state.db_client
+        .clear_failed_logins(lockout::IP_SCOPE, &ip_key)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(Response {
+        status: "success",
+        message: format!("IP {} unlocked", ip_key),
+    }))
+}
@@ -0,0 +1,158 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+use crate::dtos::{CreateStockTransferDto, CreateWarehouseDto, StockTransferListResponse, StockTransferQuery, StockTransferResponse, WarehouseListResponse, WarehouseResponse};
+use crate::error::HttpError;
+use crate::db::WarehouseExt;
+use crate::middleware::{require_roles, JWTAuthMiddeware};
+use crate::models::Warehouse;
+use crate::permissions::Permission;
+use crate::AppState;
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn warehouses_handler() -> Router {
+    Router::new()
+        .route("/create", post(create_warehouse).route_layer(require_roles(Permission::ManageWarehouses.roles())))
+        .route("/all", get(get_warehouses).route_layer(require_roles(Permission::ViewStock.roles())))
+
+        // Transfer documents moving stock from one warehouse to another
+        .route("/transfers", post(create_stock_transfer).route_layer(require_roles(Permission::TransferStock.roles())))
+        .route("/transfers", get(get_stock_transfers).route_layer(require_roles(Permission::ViewStock.roles())))
+        .route("/transfers/:id", get(get_stock_transfer).route_layer(require_roles(Permission::ViewStock.roles())))
+}
+
+// The given warehouse, or the default one when none is given
+pub async fn resolve_warehouse(
+    app_state: &AppState,
+    warehouse_id: Option<Uuid>,
+) -> Result<Warehouse, HttpError> {
+    app_state.db_client
+        .get_warehouse(warehouse_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Warehouse not found".to_string()))
+}
+
+pub async fn create_warehouse(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Json(body): Json<CreateWarehouseDto>,
+) -> Result<Json<WarehouseResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    let warehouse = app_state.db_client
+        .create_warehouse(body.name.trim(), body.address.as_deref())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                HttpError::unique_constraint_violation("A warehouse with this name already exists".to_string())
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(WarehouseResponse {
+        status: "success".to_string(),
+        warehouse,
+    }))
+}
+
+pub async fn get_warehouses(
+    Extension(app_state): Extension<Arc<AppState>>,
+) -> Result<Json<WarehouseListResponse>, HttpError> {
+    let warehouses = app_state.db_client
+        .get_warehouses()
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(WarehouseListResponse {
+        status: "success".to_string(),
+        results: warehouses.len(),
+        warehouses,
+    }))
+}
+
+pub async fn create_stock_transfer(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(jwt_auth): Extension<JWTAuthMiddeware>,
+    Json(body): Json<CreateStockTransferDto>,
+) -> Result<Json<StockTransferResponse>, HttpError> {
+    body.validate()
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+
+    if body.lines.is_empty() {
+        return Err(HttpError::bad_request("A transfer needs at least one line".to_string()));
+    }
+    if body.from_warehouse_id == body.to_warehouse_id {
+        return Err(HttpError::bad_request("Cannot transfer stock to the same warehouse".to_string()));
+    }
+
+    resolve_warehouse(&app_state, Some(body.from_warehouse_id)).await?;
+    resolve_warehouse(&app_state, Some(body.to_warehouse_id)).await?;
+
+    let (transfer, lines) = app_state.db_client
+        .create_stock_transfer(&body, jwt_auth.user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Protocol(msg) => HttpError::bad_request(msg),
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                HttpError::bad_request("Product or warehouse not found".to_string())
+            }
+            sqlx::Error::Database(db_err) if db_err.is_check_violation() => {
+                HttpError::new("Transfer would take warehouse stock below zero".to_string(), StatusCode::CONFLICT)
+            }
+            e => HttpError::server_error(e.to_string()),
+        })?;
+
+    Ok(Json(StockTransferResponse {
+        status: "success".to_string(),
+        transfer,
+        lines,
+    }))
+}
+
+pub async fn get_stock_transfers(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Query(params): Query<StockTransferQuery>,
+) -> Result<Json<StockTransferListResponse>, HttpError> {
+    let transfers = app_state.db_client
+        .get_stock_transfers(params.warehouse_id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockTransferListResponse {
+        status: "success".to_string(),
+        results: transfers.len(),
+        transfers,
+    }))
+}
+
+pub async fn get_stock_transfer(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Path(transfer_id): Path<String>,
+) -> Result<Json<StockTransferResponse>, HttpError> {
+    let transfer_uuid = Uuid::parse_str(&transfer_id)
+        .map_err(|_| HttpError::bad_request("Invalid transfer ID".to_string()))?;
+
+    let transfer = app_state.db_client
+        .get_stock_transfer(transfer_uuid)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?
+        .ok_or(HttpError::bad_request("Transfer not found".to_string()))?;
+
+    let lines = app_state.db_client
+        .get_stock_transfer_lines(transfer.id)
+        .await
+        .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    Ok(Json(StockTransferResponse {
+        status: "success".to_string(),
+        transfer,
+        lines,
+    }))
+}
@@ -1,4 +1,5 @@
 use super::sendmail::send_email;
+use crate::dtos::ReorderSuggestionDto;
 
 pub async fn send_verification_email(
     to_email: &str,
@@ -61,4 +62,53 @@ pub async fn send_forgot_password_email(
     ];
 
     send_email(to_email, subject, template_path, &placeholders).await
-}
\ No newline at end of file
+}
+
+pub async fn send_invite_email(
+    to_email: &str,
+    token: &str
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "You have been invited to DairyX";
+    let template_path = "src/mail/templates/Verification-email.html";
+    let base_url = "http://localhost:3000/accept-invite";
+    let invite_link = create_verification_link(base_url, token);
+    let placeholders = vec![
+        ("{{username}}".to_string(), to_email.to_string()),
+        ("{{verification_link}}".to_string(), invite_link)
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+pub async fn send_low_stock_email(
+    to_email: &str,
+    username: &str,
+    products: &[ReorderSuggestionDto]
+) -> Result<(), Box<dyn std::error::Error>> {
+    let subject = "Low stock alert";
+    let template_path = "src/mail/templates/LowStock-email.html";
+    let rows: String = products
+        .iter()
+        .map(|product| format!(
+            "<tr><td style=\"padding: 6px;\">{} ({})</td><td style=\"text-align: right; padding: 6px;\">{}</td><td style=\"text-align: right; padding: 6px;\">{}</td><td style=\"text-align: right; padding: 6px;\">{}</td></tr>",
+            escape_html(&product.name),
+            escape_html(&product.sku),
+            product.on_hand,
+            product.reorder_point,
+            product.suggested_quantity
+        ))
+        .collect();
+    let placeholders = vec![
+        ("{{username}}".to_string(), username.to_string()),
+        ("{{products}}".to_string(), rows)
+    ];
+
+    send_email(to_email, subject, template_path, &placeholders).await
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
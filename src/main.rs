@@ -5,11 +5,12 @@ mod error;
 mod db;
 mod utils;
 mod middleware;
+mod permissions;
 mod mail;
 mod handler;
 mod routes;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::http::{header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE}, HeaderValue, Method};
 use config::Config;
@@ -66,14 +67,12 @@ async fn main() {
     let app = create_router(Arc::new(app_state.clone())).layer(cors.clone());
 
     
-    println!(
-        "{}",
-        format!("🚀 Server is running on http://localhost:{}", config.port)
-    );
+    println!("🚀 Server is running on http://localhost:{}", config.port);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", &config.port))
     .await
     .unwrap();
 
-    axum::serve(listener, app).await.unwrap();
+    // Client addresses feed per-IP login throttling
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
\ No newline at end of file
@@ -1,20 +1,22 @@
 use std::sync::Arc;
 
 use axum::{
+    body::Body,
     extract::Request,
-    http::{header},
+    http::{header, StatusCode},
     middleware::Next,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Extension
 };
 
 use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
+use tower_http::validate_request::{ValidateRequest, ValidateRequestHeaderLayer};
 
 use crate::{
-    db::UserExt,
+    db::{SessionExt, UserExt},
     error::{ErrorMessage, HttpError},
-    models::{User},
+    models::{User, UserRole},
     utils::token,
     AppState
 };
@@ -22,6 +24,7 @@ use crate::{
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JWTAuthMiddeware {
     pub user: User,
+    pub session_id: uuid::Uuid,
 }
 
 pub async fn auth(
@@ -38,12 +41,8 @@ pub async fn auth(
                     .get(header::AUTHORIZATION)
                     .and_then(|auth_header| auth_header.to_str().ok())
                     .and_then(|auth_value| {
-                        if auth_value.starts_with("Bearer ") {
-                            Some(auth_value[7..].to_owned())
-                        } else {
-                            None
-                        }
-                    })  
+                        auth_value.strip_prefix("Bearer ").map(|token| token.to_owned())
+                    })
             });
 
     let token = cookies.ok_or_else(|| {
@@ -58,11 +57,25 @@ pub async fn auth(
             }
         };
 
-    let user_id = uuid::Uuid::parse_str(&token_details.to_string())
+    let user_id = uuid::Uuid::parse_str(&token_details.sub)
             .map_err(|_| {
                 HttpError::unauthorized(ErrorMessage::InvalidToken.to_string())
             })?;
 
+    let session_id = uuid::Uuid::parse_str(&token_details.sid)
+            .map_err(|_| {
+                HttpError::unauthorized(ErrorMessage::InvalidToken.to_string())
+            })?;
+
+    // A revoked or expired session kills its access tokens immediately
+    let session = app_state.db_client.get_active_session(session_id)
+            .await
+            .map_err(|e| HttpError::server_error(e.to_string()))?;
+
+    if session.is_none_or(|session| session.user_id != user_id) {
+        return Err(HttpError::unauthorized(ErrorMessage::SessionRevoked.to_string()));
+    }
+
     let user = app_state.db_client.get_user(Some(user_id), None, None)
             .await
             .map_err(|_| {
@@ -73,8 +86,18 @@ pub async fn auth(
         HttpError::unauthorized(ErrorMessage::UserNoLongerExist.to_string())
     })?;
 
+    if !user.is_active {
+        return Err(HttpError::unauthorized(ErrorMessage::AccountDeactivated.to_string()));
+    }
+
+    // A role change invalidates tokens minted for the old role
+    if token_details.role != user.role.to_str() {
+        return Err(HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()));
+    }
+
     req.extensions_mut().insert(JWTAuthMiddeware {
         user: user.clone(),
+        session_id,
     });
 
     Ok(next.run(req).await)
@@ -82,22 +105,85 @@ pub async fn auth(
 }
 
 
-// pub async fn role_check(
-//     Extension(_app_state): Extension<Arc<AppState>>,
-//     req: Request,
-//     next: Next,
-//     required_roles: Vec<UserRole>,
-// ) -> Result<impl IntoResponse, HttpError> {
-//     let user = req
-//             .extensions()
-//             .get::<JWTAuthMiddeware>()
-//             .ok_or_else(|| {
-//                 HttpError::unauthorized(ErrorMessage::UserNotAuthenticated.to_string())
-//             })?;
-    
-//     if !required_roles.contains(&user.user.role) {
-//         return Err(HttpError::new(ErrorMessage::PermissionDenied.to_string(), StatusCode::FORBIDDEN));
-//     }
-
-//     Ok(next.run(req).await)
-// }
\ No newline at end of file
+#[derive(Debug, Clone, Copy)]
+pub struct RoleGuard {
+    roles: &'static [UserRole],
+}
+
+impl<B> ValidateRequest<B> for RoleGuard {
+    type ResponseBody = Body;
+
+    fn validate(&mut self, req: &mut Request<B>) -> Result<(), Response> {
+        let user = req
+            .extensions()
+            .get::<JWTAuthMiddeware>()
+            .ok_or_else(|| {
+                HttpError::unauthorized(ErrorMessage::UserNotAuthenticated.to_string()).into_response()
+            })?;
+
+        if !self.roles.contains(&user.user.role) {
+            return Err(HttpError::new(ErrorMessage::PermissionDenied.to_string(), StatusCode::FORBIDDEN).into_response());
+        }
+
+        Ok(())
+    }
+}
+
+// Attach with `route_layer` on routes nested under the `auth` middleware.
+pub fn require_roles(roles: &'static [UserRole]) -> ValidateRequestHeaderLayer<RoleGuard> {
+    ValidateRequestHeaderLayer::custom(RoleGuard { roles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_as(role: Option<UserRole>) -> Request {
+        let mut req = Request::new(Body::empty());
+        if let Some(role) = role {
+            req.extensions_mut().insert(JWTAuthMiddeware {
+                user: User {
+                    id: uuid::Uuid::new_v4(),
+                    first_name: "Test".to_string(),
+                    last_name: "User".to_string(),
+                    email: "test@example.com".to_string(),
+                    password: String::new(),
+                    role,
+                    address: None,
+                    city: None,
+                    district: None,
+                    contact_number: None,
+                    verified: true,
+                    verification_token: None,
+                    token_expires_at: None,
+                    is_active: true,
+                    deactivated_at: None,
+                    created_at: None,
+                    updated_at: None,
+                },
+                session_id: uuid::Uuid::new_v4(),
+            });
+        }
+        req
+    }
+
+    #[test]
+    fn role_guard_allows_listed_role() {
+        let mut guard = RoleGuard { roles: &[UserRole::Admin, UserRole::Manager] };
+        assert!(guard.validate(&mut request_as(Some(UserRole::Manager))).is_ok());
+    }
+
+    #[test]
+    fn role_guard_rejects_other_roles_with_forbidden() {
+        let mut guard = RoleGuard { roles: &[UserRole::Admin] };
+        let res = guard.validate(&mut request_as(Some(UserRole::Driver))).unwrap_err();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn role_guard_rejects_unauthenticated_requests() {
+        let mut guard = RoleGuard { roles: &[UserRole::Admin] };
+        let res = guard.validate(&mut request_as(None)).unwrap_err();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
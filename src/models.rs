@@ -1,6 +1,7 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
+use rust_decimal::Decimal;
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "user_role", rename_all = "lowercase")]
@@ -11,7 +12,7 @@ pub enum UserRole {
 }
 
 impl UserRole {
-    pub fn to_str(&self) -> &str {
+    pub fn to_str(self) -> &'static str {
         match self {
             UserRole::Admin => "admin",
             UserRole::Manager => "manager",
@@ -32,30 +33,346 @@ pub struct User {
     pub city: Option<String>,
     pub district: Option<String>,
     pub contact_number: Option<String>,
+    pub verified: bool,
+    pub verification_token: Option<String>,
+    pub token_expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub deactivated_at: Option<DateTime<Utc>>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub refresh_token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// Deliberately not Serialize: the TOTP secret never leaves the server after enrolment
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct UserTwoFactor {
+    pub secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
+pub struct Invite {
+    pub id: uuid::Uuid,
+    pub email: String,
+    pub role: UserRole,
+    pub invited_by: uuid::Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow, Clone)]
 pub struct Product {
     pub id: uuid::Uuid,
     pub name: String,
-    pub price: f64,
+    pub price: Decimal,
     pub unit_type: String,
-    pub commission: f64,
+    pub commission: Decimal,
+    pub category_id: Option<uuid::Uuid>,
+    pub sku: String,
+    pub barcode: Option<String>,
+    pub pack_size: Decimal,
+    pub base_unit: String,
+    pub tax_rate: Decimal,
+    pub reorder_point: Option<i32>,
+    pub target_level: Option<i32>,
+    #[serde(rename = "archivedAt")]
+    pub archived_at: Option<DateTime<Utc>>,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct ProductCategory {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct ProductPrice {
+    pub id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub price: Decimal,
+    pub effective_from: NaiveDate,
+    pub created_by: Option<uuid::Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct Delivery {
     pub deliveryid: uuid::Uuid,
     pub date: NaiveDate,
     pub userid: uuid::Uuid,
+    // Receiving warehouse
+    pub warehouse_id: uuid::Uuid,
+    // None for deliveries recorded before suppliers were tracked
+    pub supplier_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct StockBatch {
+    pub id: uuid::Uuid,
+    pub productid: uuid::Uuid,
+    pub warehouse_id: uuid::Uuid,
+    pub batch_number: String,
+    pub production_date: Option<NaiveDate>,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// Why a stock_movements row exists; stored as the snake_case name
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StockMovementReason {
+    OpeningBalance,
+    Delivery,
+    TruckLoad,
+    TruckReturn,
+    Adjustment,
+    TransferOut,
+    TransferIn,
+}
+
+impl StockMovementReason {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            StockMovementReason::OpeningBalance => "opening_balance",
+            StockMovementReason::Delivery => "delivery",
+            StockMovementReason::TruckLoad => "truck_load",
+            StockMovementReason::TruckReturn => "truck_return",
+            StockMovementReason::Adjustment => "adjustment",
+            StockMovementReason::TransferOut => "transfer_out",
+            StockMovementReason::TransferIn => "transfer_in",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentReason {
+    Spoiled,
+    Damaged,
+    Expired,
+    Sample,
+    #[serde(alias = "count-correction")]
+    CountCorrection,
+}
+
+impl AdjustmentReason {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AdjustmentReason::Spoiled => "spoiled",
+            AdjustmentReason::Damaged => "damaged",
+            AdjustmentReason::Expired => "expired",
+            AdjustmentReason::Sample => "sample",
+            AdjustmentReason::CountCorrection => "count_correction",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AdjustmentStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl AdjustmentStatus {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AdjustmentStatus::Pending => "pending",
+            AdjustmentStatus::Approved => "approved",
+            AdjustmentStatus::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct StockAdjustment {
+    pub id: uuid::Uuid,
+    pub productid: uuid::Uuid,
+    pub batch_id: Option<uuid::Uuid>,
+    pub quantity: i32,
+    pub reason: String,
+    pub notes: Option<String>,
+    // Quantity at the product's price when the adjustment was raised
+    pub value: Decimal,
+    pub status: String,
+    pub created_by: uuid::Uuid,
+    pub decided_by: Option<uuid::Uuid>,
+    #[serde(rename = "decidedAt")]
+    pub decided_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    // Set when the adjustment came from posting a stock take
+    pub stock_take_id: Option<uuid::Uuid>,
+    pub warehouse_id: uuid::Uuid,
+    // Day the stock take line was counted; the movement is dated then
+    pub counted_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StockTakeStatus {
+    Open,
+    Posted,
+    Cancelled,
+}
+
+impl StockTakeStatus {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            StockTakeStatus::Open => "open",
+            StockTakeStatus::Posted => "posted",
+            StockTakeStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct StockTake {
+    pub id: uuid::Uuid,
+    pub status: String,
+    pub notes: Option<String>,
+    pub opened_by: uuid::Uuid,
+    #[serde(rename = "openedAt")]
+    pub opened_at: DateTime<Utc>,
+    pub closed_by: Option<uuid::Uuid>,
+    #[serde(rename = "closedAt")]
+    pub closed_at: Option<DateTime<Utc>>,
+    pub warehouse_id: uuid::Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct Warehouse {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub address: Option<String>,
+    pub is_default: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct Supplier {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub kind: String,
+    pub contact_number: Option<String>,
+    pub address: Option<String>,
+    pub is_active: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplierKind {
+    Farmer,
+    #[serde(alias = "co-op", alias = "coop")]
+    Cooperative,
+    Vendor,
+}
+
+impl SupplierKind {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            SupplierKind::Farmer => "farmer",
+            SupplierKind::Cooperative => "cooperative",
+            SupplierKind::Vendor => "vendor",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct MilkRateChart {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub valid_from: NaiveDate,
+    pub created_by: Option<uuid::Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+// One slab of a rate chart; see utils::milk_rate
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct MilkRateChartEntry {
+    pub id: uuid::Uuid,
+    pub chart_id: uuid::Uuid,
+    pub min_fat: Decimal,
+    pub min_snf: Decimal,
+    pub rate: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct MilkCollection {
+    pub id: uuid::Uuid,
+    pub supplier_id: uuid::Uuid,
+    pub collected_on: NaiveDate,
+    pub litres: Decimal,
+    pub fat: Decimal,
+    pub snf: Decimal,
+    pub temperature: Decimal,
+    pub adulteration_result: String,
+    pub rejected: bool,
+    pub rejection_reason: Option<String>,
+    pub rate_chart_id: Option<uuid::Uuid>,
+    pub rate_per_litre: Option<Decimal>,
+    pub amount: Option<Decimal>,
+    pub recorded_by: uuid::Uuid,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdulterationResult {
+    Negative,
+    Positive,
+    NotTested,
+}
+
+impl AdulterationResult {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AdulterationResult::Negative => "negative",
+            AdulterationResult::Positive => "positive",
+            AdulterationResult::NotTested => "not_tested",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct StockTransfer {
+    pub id: uuid::Uuid,
+    pub from_warehouse_id: uuid::Uuid,
+    pub to_warehouse_id: uuid::Uuid,
+    pub date: NaiveDate,
+    pub notes: Option<String>,
+    pub created_by: uuid::Uuid,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
 }
 
 
@@ -79,7 +396,7 @@ pub struct Truck {
     pub truckid: uuid::Uuid,
     pub trucknumber: String,
     pub model: String,
-    pub max_allowance: Option<f64>,
+    pub max_allowance: Option<Decimal>,
 }
 
 
@@ -91,6 +408,8 @@ pub struct TruckLoad {
     pub truckid: uuid::Uuid,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
+    // Dispatching warehouse; returns go back into it
+    pub warehouse_id: uuid::Uuid,
 }
 
 // #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
@@ -108,10 +427,54 @@ pub struct Shop {
     pub city: Option<String>,          
     pub district: Option<String>,      
     pub contact_number: Option<String>, 
+    pub group_id: Option<uuid::Uuid>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
 }
 
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct ShopGroup {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct PriceList {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub shop_id: Option<uuid::Uuid>,
+    pub shop_group_id: Option<uuid::Uuid>,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+    pub created_by: Option<uuid::Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct PriceListItem {
+    pub id: uuid::Uuid,
+    pub price_list_id: uuid::Uuid,
+    pub product_id: uuid::Uuid,
+    pub min_quantity: i32,
+    pub price: Decimal,
+}
+
+// A price list row that could price a sale line; see utils::pricing
+#[derive(Debug, sqlx::FromRow, Clone)]
+pub struct PriceListOffer {
+    pub price_list_id: uuid::Uuid,
+    pub price_list_name: String,
+    pub scope_rank: i32,
+    pub valid_from: Option<NaiveDate>,
+    pub min_quantity: i32,
+    pub price: Decimal,
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct Sale {
     pub salesid: uuid::Uuid,
@@ -119,8 +482,10 @@ pub struct Sale {
     pub shopid: uuid::Uuid,
     pub date: NaiveDate,
     pub status: String,
-    pub total_amount: f64,  
-    pub paid_amount: f64,    
+    pub total_amount: Decimal,  
+    pub paid_amount: Decimal,    
+    pub net_amount: Decimal,
+    pub tax_amount: Decimal,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
 }
@@ -136,7 +501,7 @@ pub struct Sale {
 pub struct Payment {
     pub paymentid: uuid::Uuid,
     pub salesid: uuid::Uuid,
-    pub amount: f64,
+    pub amount: Decimal,
     pub method: String,
     pub date: chrono::NaiveDate,
     pub created_at: Option<chrono::NaiveDateTime>,
@@ -147,7 +512,7 @@ pub struct Payment {
 pub struct Allowance {
     pub allowanceid: uuid::Uuid,
     pub date: NaiveDate,
-    pub amount: f64,
+    pub amount: Decimal,
     pub notes: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
@@ -158,7 +523,7 @@ pub struct TruckAllowance {
     pub id: uuid::Uuid,
     pub allowanceid: uuid::Uuid,
     pub truckid: uuid::Uuid,
-    pub amount: f64,
+    pub amount: Decimal,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
 }
@@ -0,0 +1,171 @@
+use crate::models::UserRole;
+
+// Every guarded route takes its allowed roles from this matrix, so the whole
+// access policy can be audited (and tested) in one place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ViewUsers,
+    InviteUsers,
+    RevokeUserSessions,
+    UpdateUsers,
+    ChangeUserRole,
+    DeactivateUsers,
+    UnlockUsers,
+    ChangeOwnPassword,
+    ViewOwnProfile,
+    ManageTwoFactor,
+    CreateProduct,
+    UpdateProduct,
+    ArchiveProduct,
+    ManageProductCategories,
+    ViewProducts,
+    ManageSuppliers,
+    ManageMilkRates,
+    RecordMilkCollection,
+    ViewMilkCollections,
+    CreateDelivery,
+    ViewDeliveryHistory,
+    ViewAllDeliveries,
+    CreateTruckLoad,
+    ViewTruckLoads,
+    ViewOwnTruckLoad,
+    UpdateRemainingQuantity,
+    ViewStock,
+    CreateStockAdjustment,
+    ApproveStockAdjustment,
+    ManageStockTakes,
+    ManageWarehouses,
+    TransferStock,
+    CreateSale,
+    ViewOwnSales,
+    ViewSalesReports,
+    ViewPendingPayments,
+    CreatePayment,
+    ManageAllowances,
+    ManageTrucks,
+    UpdateTruckMaxAllowance,
+    ManageShops,
+    ManagePriceLists,
+}
+
+const ADMIN: &[UserRole] = &[UserRole::Admin];
+const MANAGER: &[UserRole] = &[UserRole::Manager];
+const DRIVER: &[UserRole] = &[UserRole::Driver];
+const ADMIN_MANAGER: &[UserRole] = &[UserRole::Admin, UserRole::Manager];
+const ALL_ROLES: &[UserRole] = &[UserRole::Admin, UserRole::Manager, UserRole::Driver];
+
+impl Permission {
+    pub fn roles(self) -> &'static [UserRole] {
+        match self {
+            Permission::ViewUsers => ADMIN_MANAGER,
+            Permission::InviteUsers => ADMIN,
+            Permission::RevokeUserSessions => ADMIN_MANAGER,
+            Permission::UpdateUsers => ADMIN_MANAGER,
+            Permission::ChangeUserRole => ADMIN,
+            Permission::DeactivateUsers => ADMIN,
+            Permission::UnlockUsers => ADMIN,
+            Permission::ChangeOwnPassword => ALL_ROLES,
+            Permission::ViewOwnProfile => ALL_ROLES,
+            Permission::ManageTwoFactor => ADMIN,
+            Permission::CreateProduct => ADMIN_MANAGER,
+            Permission::UpdateProduct => ADMIN_MANAGER,
+            Permission::ArchiveProduct => ADMIN_MANAGER,
+            Permission::ManageProductCategories => ADMIN_MANAGER,
+            Permission::ViewProducts => ALL_ROLES,
+            Permission::ManageSuppliers => ADMIN_MANAGER,
+            Permission::ManageMilkRates => ADMIN_MANAGER,
+            Permission::RecordMilkCollection => MANAGER,
+            Permission::ViewMilkCollections => ADMIN_MANAGER,
+            Permission::CreateDelivery => MANAGER,
+            Permission::ViewDeliveryHistory => ADMIN_MANAGER,
+            Permission::ViewAllDeliveries => ADMIN_MANAGER,
+            Permission::CreateTruckLoad => MANAGER,
+            Permission::ViewTruckLoads => ADMIN_MANAGER,
+            Permission::ViewOwnTruckLoad => DRIVER,
+            Permission::UpdateRemainingQuantity => MANAGER,
+            Permission::ViewStock => ADMIN_MANAGER,
+            Permission::CreateStockAdjustment => MANAGER,
+            Permission::ApproveStockAdjustment => ADMIN,
+            Permission::ManageStockTakes => MANAGER,
+            Permission::ManageWarehouses => ADMIN_MANAGER,
+            Permission::TransferStock => MANAGER,
+            Permission::CreateSale => DRIVER,
+            Permission::ViewOwnSales => DRIVER,
+            Permission::ViewSalesReports => ADMIN,
+            Permission::ViewPendingPayments => ALL_ROLES,
+            Permission::CreatePayment => DRIVER,
+            Permission::ManageAllowances => ADMIN_MANAGER,
+            Permission::ManageTrucks => ADMIN_MANAGER,
+            Permission::UpdateTruckMaxAllowance => ADMIN,
+            Permission::ManageShops => ADMIN_MANAGER,
+            Permission::ManagePriceLists => ADMIN_MANAGER,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (permission, admin, manager, driver)
+    const MATRIX: &[(Permission, bool, bool, bool)] = &[
+        (Permission::ViewUsers, true, true, false),
+        (Permission::InviteUsers, true, false, false),
+        (Permission::RevokeUserSessions, true, true, false),
+        (Permission::UpdateUsers, true, true, false),
+        (Permission::ChangeUserRole, true, false, false),
+        (Permission::DeactivateUsers, true, false, false),
+        (Permission::UnlockUsers, true, false, false),
+        (Permission::ChangeOwnPassword, true, true, true),
+        (Permission::ViewOwnProfile, true, true, true),
+        (Permission::ManageTwoFactor, true, false, false),
+        (Permission::CreateProduct, true, true, false),
+        (Permission::UpdateProduct, true, true, false),
+        (Permission::ArchiveProduct, true, true, false),
+        (Permission::ManageProductCategories, true, true, false),
+        (Permission::ViewProducts, true, true, true),
+        (Permission::ManageSuppliers, true, true, false),
+        (Permission::ManageMilkRates, true, true, false),
+        (Permission::RecordMilkCollection, false, true, false),
+        (Permission::ViewMilkCollections, true, true, false),
+        (Permission::CreateDelivery, false, true, false),
+        (Permission::ViewDeliveryHistory, true, true, false),
+        (Permission::ViewAllDeliveries, true, true, false),
+        (Permission::CreateTruckLoad, false, true, false),
+        (Permission::ViewTruckLoads, true, true, false),
+        (Permission::ViewOwnTruckLoad, false, false, true),
+        (Permission::UpdateRemainingQuantity, false, true, false),
+        (Permission::ViewStock, true, true, false),
+        (Permission::CreateStockAdjustment, false, true, false),
+        (Permission::ApproveStockAdjustment, true, false, false),
+        (Permission::ManageStockTakes, false, true, false),
+        (Permission::ManageWarehouses, true, true, false),
+        (Permission::TransferStock, false, true, false),
+        (Permission::CreateSale, false, false, true),
+        (Permission::ViewOwnSales, false, false, true),
+        (Permission::ViewSalesReports, true, false, false),
+        (Permission::ViewPendingPayments, true, true, true),
+        (Permission::CreatePayment, false, false, true),
+        (Permission::ManageAllowances, true, true, false),
+        (Permission::ManageTrucks, true, true, false),
+        (Permission::UpdateTruckMaxAllowance, true, false, false),
+        (Permission::ManageShops, true, true, false),
+        (Permission::ManagePriceLists, true, true, false),
+    ];
+
+    #[test]
+    fn matrix_matches_policy() {
+        for &(permission, admin, manager, driver) in MATRIX {
+            assert_eq!(permission.roles().contains(&UserRole::Admin), admin, "{:?} / admin", permission);
+            assert_eq!(permission.roles().contains(&UserRole::Manager), manager, "{:?} / manager", permission);
+            assert_eq!(permission.roles().contains(&UserRole::Driver), driver, "{:?} / driver", permission);
+        }
+    }
+
+    #[test]
+    fn every_permission_grants_at_least_one_role() {
+        for (permission, ..) in MATRIX {
+            assert!(!permission.roles().is_empty(), "{:?} grants nobody", permission);
+        }
+    }
+}
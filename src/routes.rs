@@ -18,6 +18,16 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
             crate::handler::products::products_handler()
                 .layer(middleware::from_fn(auth))
         )
+        .nest(
+            "/suppliers",
+            crate::handler::suppliers::suppliers_handler()
+                .layer(middleware::from_fn(auth))
+        )
+        .nest(
+            "/milk",
+            crate::handler::milk::milk_handler()
+                .layer(middleware::from_fn(auth))
+        )
         .nest(
             "/delivery", 
             crate::handler::delivery::delivery_handler()
@@ -53,6 +63,26 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
             crate::handler::shops::shop_handler()
                 .layer(middleware::from_fn(auth))
         )
+        .nest(
+            "/price-lists",
+            crate::handler::price_lists::price_lists_handler()
+                .layer(middleware::from_fn(auth))
+        )
+        .nest(
+            "/stock",
+            crate::handler::stock::stock_handler()
+                .layer(middleware::from_fn(auth))
+        )
+        .nest(
+            "/stock-takes",
+            crate::handler::stock_takes::stock_takes_handler()
+                .layer(middleware::from_fn(auth))
+        )
+        .nest(
+            "/warehouses",
+            crate::handler::warehouses::warehouses_handler()
+                .layer(middleware::from_fn(auth))
+        )
         
         
         .layer(TraceLayer::new_for_http())
@@ -0,0 +1,53 @@
+use rust_decimal::Decimal;
+
+use crate::models::AdjustmentStatus;
+use crate::utils::money;
+
+// Status for one counted or requested change, given the pieces it is booked
+// as (a shortfall can be split across several batches). The pieces are valued
+// together so splitting a large loss can't slip it under the approval limit.
+pub fn adjustment_status(unit_price: Decimal, quantities: &[i32], approval_limit: Decimal) -> AdjustmentStatus {
+    let units: i64 = quantities.iter().map(|quantity| i64::from(quantity.unsigned_abs())).sum();
+    let value = money::round(unit_price * Decimal::from(units));
+
+    if value > approval_limit {
+        AdjustmentStatus::Pending
+    } else {
+        AdjustmentStatus::Approved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn within_the_limit_is_approved() {
+        assert_eq!(adjustment_status(dec("10"), &[-50], dec("500")), AdjustmentStatus::Approved);
+        assert_eq!(adjustment_status(dec("10"), &[20], dec("500")), AdjustmentStatus::Approved);
+    }
+
+    #[test]
+    fn over_the_limit_is_pending() {
+        assert_eq!(adjustment_status(dec("10"), &[-51], dec("500")), AdjustmentStatus::Pending);
+    }
+
+    #[test]
+    fn shortfall_split_across_batches_is_judged_as_a_whole() {
+        // Each batch piece is worth 300 or 400, under the limit on its own
+        let pieces = [-30, -30, -40];
+        for piece in pieces {
+            assert_eq!(adjustment_status(dec("10"), &[piece], dec("500")), AdjustmentStatus::Approved);
+        }
+        assert_eq!(adjustment_status(dec("10"), &pieces, dec("500")), AdjustmentStatus::Pending);
+    }
+
+    #[test]
+    fn extreme_quantities_do_not_overflow() {
+        assert_eq!(adjustment_status(dec("1"), &[i32::MIN, i32::MIN], dec("500")), AdjustmentStatus::Pending);
+    }
+}
@@ -0,0 +1,46 @@
+use validator::ValidationError;
+
+// EAN-8 and EAN-13: digits only, last digit is the mod-10 check digit
+// with weights 3,1,3,1... counted from the right of the payload.
+pub fn is_valid_ean(code: &str) -> bool {
+    if !matches!(code.len(), 8 | 13) || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    let digits: Vec<u32> = code.bytes().map(|b| (b - b'0') as u32).collect();
+    let (payload, check) = digits.split_at(digits.len() - 1);
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+
+    (10 - sum % 10) % 10 == check[0]
+}
+
+pub fn validate_ean(code: &str) -> Result<(), ValidationError> {
+    if !is_valid_ean(code) {
+        return Err(ValidationError::new("invalid_ean"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_ean13_and_ean8() {
+        assert!(is_valid_ean("4006381333931"));
+        assert!(is_valid_ean("96385074"));
+    }
+
+    #[test]
+    fn rejects_bad_check_digit_length_or_characters() {
+        assert!(!is_valid_ean("4006381333932"));
+        assert!(!is_valid_ean("400638133393"));
+        assert!(!is_valid_ean("40063813339a1"));
+        assert!(!is_valid_ean(""));
+    }
+}
@@ -0,0 +1,43 @@
+// Minimal RFC 4180 writer for downloadable reports
+
+// Quotes a field only when it contains a separator, quote or line break
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn row<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|value| field(value.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_are_left_alone() {
+        assert_eq!(field("Milk 1L"), "Milk 1L");
+        assert_eq!(field("-3"), "-3");
+    }
+
+    #[test]
+    fn special_fields_are_quoted() {
+        assert_eq!(field("Milk, full cream"), "\"Milk, full cream\"");
+        assert_eq!(field("12\" tray"), "\"12\"\" tray\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn row_ends_with_crlf() {
+        assert_eq!(row(&["a", "b,c", ""]), "a,\"b,c\",\r\n");
+    }
+}
@@ -0,0 +1,45 @@
+// First-expiry-first-out allocation. `batches` are (batch id, quantity on
+// hand) already ordered by expiry, soonest first. Returns how much to take
+// from each batch, or None if they can't cover `quantity` together.
+pub fn allocate<T: Copy>(batches: &[(T, i32)], quantity: i32) -> Option<Vec<(T, i32)>> {
+    let mut needed = quantity;
+    let mut picks = Vec::new();
+
+    for &(batch, available) in batches {
+        if needed <= 0 {
+            break;
+        }
+        let take = available.min(needed);
+        if take > 0 {
+            picks.push((batch, take));
+            needed -= take;
+        }
+    }
+
+    (needed <= 0).then_some(picks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_from_the_earliest_expiry_first() {
+        let batches = [("mon", 5), ("tue", 10), ("wed", 10)];
+        assert_eq!(allocate(&batches, 12), Some(vec![("mon", 5), ("tue", 7)]));
+        assert_eq!(allocate(&batches, 3), Some(vec![("mon", 3)]));
+    }
+
+    #[test]
+    fn skips_empty_batches() {
+        let batches = [("mon", 0), ("tue", 4)];
+        assert_eq!(allocate(&batches, 4), Some(vec![("tue", 4)]));
+    }
+
+    #[test]
+    fn refuses_when_stock_is_short() {
+        let batches = [("mon", 5), ("tue", 4)];
+        assert_eq!(allocate(&batches, 10), None);
+        assert_eq!(allocate::<&str>(&[], 1), None);
+    }
+}
@@ -0,0 +1,41 @@
+// Failed logins are counted separately per account and per client IP.
+pub const ACCOUNT_SCOPE: &str = "account";
+pub const IP_SCOPE: &str = "ip";
+
+// Never lock anyone out for longer than a day
+pub const MAX_LOCKOUT_SECONDS: i64 = 86_400;
+
+// Seconds to lock after `failed_attempts` failures, or None while still under
+// the limit. Each failure past the limit doubles the lock, up to a day.
+pub fn lockout_seconds(failed_attempts: i32, max_attempts: i32, base_seconds: i64) -> Option<i64> {
+    if failed_attempts < max_attempts {
+        return None;
+    }
+
+    let doublings = (failed_attempts - max_attempts).min(32) as u32;
+    Some(base_seconds.saturating_mul(1_i64 << doublings).min(MAX_LOCKOUT_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_lock_below_the_limit() {
+        assert_eq!(lockout_seconds(0, 5, 60), None);
+        assert_eq!(lockout_seconds(4, 5, 60), None);
+    }
+
+    #[test]
+    fn lock_doubles_with_each_further_failure() {
+        assert_eq!(lockout_seconds(5, 5, 60), Some(60));
+        assert_eq!(lockout_seconds(6, 5, 60), Some(120));
+        assert_eq!(lockout_seconds(8, 5, 60), Some(480));
+    }
+
+    #[test]
+    fn lock_is_capped_at_a_day() {
+        assert_eq!(lockout_seconds(20, 5, 60), Some(MAX_LOCKOUT_SECONDS));
+        assert_eq!(lockout_seconds(i32::MAX, 5, 60), Some(MAX_LOCKOUT_SECONDS));
+    }
+}
@@ -0,0 +1,73 @@
+use rust_decimal::Decimal;
+
+use crate::models::MilkRateChartEntry;
+use crate::utils::money;
+
+// Rate per litre for milk testing at `fat` and `snf` percent. Of the slabs
+// the milk reaches, the highest fat slab wins, then the highest SNF slab
+// within it. None means the milk is below every slab on the chart.
+pub fn rate_for(entries: &[MilkRateChartEntry], fat: Decimal, snf: Decimal) -> Option<Decimal> {
+    entries
+        .iter()
+        .filter(|entry| entry.min_fat <= fat && entry.min_snf <= snf)
+        .max_by_key(|entry| (entry.min_fat, entry.min_snf))
+        .map(|entry| entry.rate)
+}
+
+// Payable for `litres` at `rate`, rounded once for the whole collection
+pub fn amount(rate: Decimal, litres: Decimal) -> Decimal {
+    money::round(rate * litres)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn entry(min_fat: &str, min_snf: &str, rate: &str) -> MilkRateChartEntry {
+        MilkRateChartEntry {
+            id: uuid::Uuid::new_v4(),
+            chart_id: uuid::Uuid::nil(),
+            min_fat: dec(min_fat),
+            min_snf: dec(min_snf),
+            rate: dec(rate),
+        }
+    }
+
+    fn chart() -> Vec<MilkRateChartEntry> {
+        vec![
+            entry("3.0", "8.0", "30"),
+            entry("3.0", "8.5", "32"),
+            entry("4.0", "8.0", "36"),
+            entry("4.0", "8.5", "38"),
+        ]
+    }
+
+    #[test]
+    fn highest_slab_reached_wins() {
+        assert_eq!(rate_for(&chart(), dec("3.5"), dec("8.2")), Some(dec("30")));
+        assert_eq!(rate_for(&chart(), dec("3.5"), dec("8.7")), Some(dec("32")));
+        assert_eq!(rate_for(&chart(), dec("4.6"), dec("9.0")), Some(dec("38")));
+    }
+
+    #[test]
+    fn slab_minimums_are_inclusive() {
+        assert_eq!(rate_for(&chart(), dec("4.0"), dec("8.0")), Some(dec("36")));
+    }
+
+    #[test]
+    fn milk_below_every_slab_has_no_rate() {
+        assert_eq!(rate_for(&chart(), dec("2.9"), dec("9.0")), None);
+        assert_eq!(rate_for(&chart(), dec("4.5"), dec("7.9")), None);
+        assert_eq!(rate_for(&[], dec("4.5"), dec("8.5")), None);
+    }
+
+    #[test]
+    fn amount_is_rounded_once() {
+        assert_eq!(amount(dec("32.50"), dec("10.35")), dec("336.38"));
+    }
+}
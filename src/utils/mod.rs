@@ -1,2 +1,13 @@
+pub mod approval;
+pub mod barcode;
+pub mod csv;
+pub mod fefo;
+pub mod lockout;
+pub mod milk_rate;
+pub mod money;
 pub mod password;
-pub mod token;
\ No newline at end of file
+pub mod pricing;
+pub mod reorder;
+pub mod tax;
+pub mod token;
+pub mod totp;
\ No newline at end of file
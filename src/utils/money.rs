@@ -0,0 +1,51 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use validator::ValidationError;
+
+// Money is kept to cents, rounding halves away from zero (2.345 -> 2.35)
+pub const MONEY_DP: u32 = 2;
+
+pub fn round(amount: Decimal) -> Decimal {
+    amount.round_dp_with_strategy(MONEY_DP, RoundingStrategy::MidpointAwayFromZero)
+}
+
+// Price of `quantity` units, rounded once for the whole line
+pub fn line_total(unit_price: Decimal, quantity: i32) -> Decimal {
+    round(unit_price * Decimal::from(quantity))
+}
+
+pub fn validate_non_negative(amount: &Decimal) -> Result<(), ValidationError> {
+    if *amount < Decimal::ZERO {
+        return Err(ValidationError::new("negative_amount"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn round_half_away_from_zero() {
+        assert_eq!(round(dec("2.345")), dec("2.35"));
+        assert_eq!(round(dec("2.344")), dec("2.34"));
+        assert_eq!(round(dec("-2.345")), dec("-2.35"));
+    }
+
+    #[test]
+    fn line_total_is_exact() {
+        // 0.1 * 3 drifts with floats; decimals stay exact
+        assert_eq!(line_total(dec("0.10"), 3), dec("0.30"));
+        assert_eq!(line_total(dec("33.335"), 1), dec("33.34"));
+    }
+
+    #[test]
+    fn non_negative_validator() {
+        assert!(validate_non_negative(&dec("0")).is_ok());
+        assert!(validate_non_negative(&dec("-0.01")).is_err());
+    }
+}
@@ -47,7 +47,7 @@ pub fn compare(password: &str, hashed_password: &str) -> Result<bool, ErrorMessa
 
     let password_matched = Argon2::default()
         .verify_password(password.as_bytes(), &parsed_hash)
-        .map_or(false, |_| true);
+        .is_ok();
 
     Ok(password_matched)
 }
\ No newline at end of file
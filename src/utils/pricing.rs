@@ -0,0 +1,84 @@
+use chrono::NaiveDate;
+
+use crate::models::PriceListOffer;
+
+// Lower rank = more specific price list
+pub const SHOP_SCOPE: i32 = 0;
+pub const GROUP_SCOPE: i32 = 1;
+pub const DEFAULT_SCOPE: i32 = 2;
+
+// Picks the price list row that prices `quantity` units. The most specific
+// scope wins (shop, then shop group, then default); within a scope the list
+// that started most recently wins, and within that list the largest quantity
+// break the line qualifies for. None means no list applies.
+pub fn best_offer(offers: &[PriceListOffer], quantity: i32) -> Option<&PriceListOffer> {
+    offers
+        .iter()
+        .filter(|offer| offer.min_quantity <= quantity)
+        .min_by(|a, b| {
+            a.scope_rank
+                .cmp(&b.scope_rank)
+                .then_with(|| started(b).cmp(&started(a)))
+                .then_with(|| b.min_quantity.cmp(&a.min_quantity))
+        })
+}
+
+fn started(offer: &PriceListOffer) -> NaiveDate {
+    offer.valid_from.unwrap_or(NaiveDate::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn offer(scope_rank: i32, valid_from: Option<&str>, min_quantity: i32, price: i64) -> PriceListOffer {
+        PriceListOffer {
+            price_list_id: uuid::Uuid::new_v4(),
+            price_list_name: format!("list {}", price),
+            scope_rank,
+            valid_from: valid_from.map(|d| d.parse().unwrap()),
+            min_quantity,
+            price: Decimal::from(price),
+        }
+    }
+
+    #[test]
+    fn most_specific_scope_wins() {
+        let offers = vec![
+            offer(DEFAULT_SCOPE, None, 1, 10),
+            offer(SHOP_SCOPE, None, 1, 8),
+            offer(GROUP_SCOPE, None, 1, 9),
+        ];
+        assert_eq!(best_offer(&offers, 1).unwrap().price, Decimal::from(8));
+    }
+
+    #[test]
+    fn largest_qualifying_quantity_break_applies() {
+        let offers = vec![
+            offer(GROUP_SCOPE, None, 1, 10),
+            offer(GROUP_SCOPE, None, 10, 9),
+            offer(GROUP_SCOPE, None, 50, 8),
+        ];
+        assert_eq!(best_offer(&offers, 5).unwrap().price, Decimal::from(10));
+        assert_eq!(best_offer(&offers, 10).unwrap().price, Decimal::from(9));
+        assert_eq!(best_offer(&offers, 80).unwrap().price, Decimal::from(8));
+    }
+
+    #[test]
+    fn newer_list_wins_within_a_scope() {
+        let offers = vec![
+            offer(SHOP_SCOPE, Some("2025-01-01"), 1, 10),
+            offer(SHOP_SCOPE, Some("2025-06-01"), 1, 7),
+            offer(SHOP_SCOPE, None, 1, 12),
+        ];
+        assert_eq!(best_offer(&offers, 1).unwrap().price, Decimal::from(7));
+    }
+
+    #[test]
+    fn nothing_applies_below_every_break() {
+        let offers = vec![offer(DEFAULT_SCOPE, None, 10, 9)];
+        assert!(best_offer(&offers, 9).is_none());
+        assert!(best_offer(&[], 1).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use rust_decimal::Decimal;
+
+// Average units leaving the warehouse per day over the lookback window
+pub fn daily_outflow(outflow: i64, days: i32) -> Decimal {
+    if days <= 0 || outflow <= 0 {
+        return Decimal::ZERO;
+    }
+    (Decimal::from(outflow) / Decimal::from(days)).round_dp(2)
+}
+
+// Days the stock on hand lasts at the current outflow; None when nothing
+// is going out, so cover is unlimited.
+pub fn days_of_cover(on_hand: i64, daily_outflow: Decimal) -> Option<Decimal> {
+    if daily_outflow <= Decimal::ZERO {
+        return None;
+    }
+    Some((Decimal::from(on_hand.max(0)) / daily_outflow).round_dp(1))
+}
+
+// Stock is reordered once it falls to the reorder point
+pub fn needs_reorder(on_hand: i64, reorder_point: i32) -> bool {
+    on_hand <= i64::from(reorder_point)
+}
+
+// Enough to bring stock back up to the target level, or to the reorder
+// point when no target is set
+pub fn suggested_quantity(on_hand: i64, reorder_point: i32, target_level: Option<i32>) -> i64 {
+    let target = i64::from(target_level.unwrap_or(reorder_point));
+    (target - on_hand).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn cover_follows_average_outflow() {
+        let daily = daily_outflow(70, 14);
+        assert_eq!(daily, dec("5"));
+        assert_eq!(days_of_cover(12, daily), Some(dec("2.4")));
+        assert_eq!(days_of_cover(-3, daily), Some(dec("0")));
+    }
+
+    #[test]
+    fn no_outflow_means_unlimited_cover() {
+        assert_eq!(daily_outflow(0, 14), Decimal::ZERO);
+        assert_eq!(days_of_cover(40, Decimal::ZERO), None);
+    }
+
+    #[test]
+    fn reorder_at_the_point_up_to_the_target() {
+        assert!(!needs_reorder(21, 20));
+        assert!(needs_reorder(19, 20));
+        assert_eq!(suggested_quantity(19, 20, Some(100)), 81);
+        assert_eq!(suggested_quantity(15, 20, None), 5);
+        assert_eq!(suggested_quantity(-4, 20, None), 24);
+    }
+
+    #[test]
+    fn stock_exactly_at_the_point_is_reordered() {
+        assert!(needs_reorder(20, 20));
+        assert!(needs_reorder(0, 0));
+        assert_eq!(suggested_quantity(20, 20, Some(100)), 80);
+    }
+}
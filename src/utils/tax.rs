@@ -0,0 +1,58 @@
+use rust_decimal::Decimal;
+
+use crate::utils::money;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaxBreakdown {
+    pub net: Decimal,
+    pub tax: Decimal,
+    pub gross: Decimal,
+}
+
+// Splits a line amount at `rate` percent. With tax-inclusive pricing the
+// amount is the gross and the tax is carved out of it; otherwise the amount
+// is the net and the tax is added on top. Tax is rounded per line, so
+// net + tax == gross always holds exactly.
+pub fn split(amount: Decimal, rate: Decimal, prices_include_tax: bool) -> TaxBreakdown {
+    let hundred = Decimal::ONE_HUNDRED;
+    if prices_include_tax {
+        let tax = money::round(amount * rate / (hundred + rate));
+        TaxBreakdown { net: amount - tax, tax, gross: amount }
+    } else {
+        let tax = money::round(amount * rate / hundred);
+        TaxBreakdown { net: amount, tax, gross: amount + tax }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn zero_rated_lines_carry_no_tax() {
+        let split_in = split(dec("31.68"), Decimal::ZERO, true);
+        assert_eq!(split_in, TaxBreakdown { net: dec("31.68"), tax: Decimal::ZERO, gross: dec("31.68") });
+        assert_eq!(split(dec("31.68"), Decimal::ZERO, false), split_in);
+    }
+
+    #[test]
+    fn inclusive_prices_carve_tax_out_of_the_gross() {
+        let line = split(dec("118.00"), dec("18"), true);
+        assert_eq!(line, TaxBreakdown { net: dec("100.00"), tax: dec("18.00"), gross: dec("118.00") });
+
+        let line = split(dec("10.00"), dec("5"), true);
+        assert_eq!(line.tax, dec("0.48"));
+        assert_eq!(line.net + line.tax, line.gross);
+    }
+
+    #[test]
+    fn exclusive_prices_add_tax_on_top() {
+        let line = split(dec("10.55"), dec("18"), false);
+        assert_eq!(line, TaxBreakdown { net: dec("10.55"), tax: dec("1.90"), gross: dec("12.45") });
+    }
+}
@@ -10,6 +10,7 @@ use jsonwebtoken::{
     Validation
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::error::{ErrorMessage, HttpError};
 
@@ -17,13 +18,37 @@ use crate::error::{ErrorMessage, HttpError};
 pub struct TokenClaims{
     pub sub: String,
     pub role: String,
+    pub sid: String,
     pub iat: usize,
     pub exp: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InviteClaims {
+    pub sub: String,
+    pub email: String,
+    pub role: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+// Proves the password step of a login passed; only good for finishing that
+// login (`purpose` is either TWO_FACTOR_LOGIN or TWO_FACTOR_SETUP).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeClaims {
+    pub sub: String,
+    pub purpose: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+pub const TWO_FACTOR_LOGIN: &str = "2fa_login";
+pub const TWO_FACTOR_SETUP: &str = "2fa_setup";
+
 pub fn create_token(
     user_id: &str,
     user_role: &str,
+    session_id: &str,
     secret: &[u8],
     expires_in_seconds: i64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
@@ -33,10 +58,11 @@ pub fn create_token(
 
     let now = Utc::now();
     let iat = now.timestamp() as usize;
-    let exp = (now + Duration::minutes(expires_in_seconds)).timestamp() as usize;
+    let exp = (now + Duration::seconds(expires_in_seconds)).timestamp() as usize;
     let claims = TokenClaims {
         sub: user_id.to_string(),
         role: user_role.to_string(), 
+        sid: session_id.to_string(),
         iat,
         exp,
     };
@@ -51,7 +77,7 @@ pub fn create_token(
 pub fn decode_token<T: Into<String>>(
     token: T,
     secret: &[u8]
-) -> Result<String, HttpError> {
+) -> Result<TokenClaims, HttpError> {
     let decode = decode::<TokenClaims>(
         &token.into(), 
         &DecodingKey::from_secret(secret), 
@@ -59,7 +85,205 @@ pub fn decode_token<T: Into<String>>(
     );
 
     match decode {
-        Ok(token) => Ok(token.claims.sub),
+        Ok(token) => Ok(token.claims),
         Err(_) => Err(HttpError::new(ErrorMessage::InvalidToken.to_string(), StatusCode::UNAUTHORIZED))
     }
 }
+
+pub fn create_invite_token(
+    invite_id: &str,
+    email: &str,
+    role: &str,
+    secret: &[u8],
+    expires_in_seconds: i64,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    if invite_id.is_empty() {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidSubject.into());
+    }
+
+    let now = Utc::now();
+    let claims = InviteClaims {
+        sub: invite_id.to_string(),
+        email: email.to_string(),
+        role: role.to_string(),
+        iat: now.timestamp() as usize,
+        exp: (now + Duration::seconds(expires_in_seconds)).timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret)
+    )
+}
+
+pub fn decode_invite_token<T: Into<String>>(
+    token: T,
+    secret: &[u8]
+) -> Result<InviteClaims, HttpError> {
+    let decode = decode::<InviteClaims>(
+        &token.into(),
+        &DecodingKey::from_secret(secret),
+        &Validation::new(Algorithm::HS256),
+    );
+
+    match decode {
+        Ok(token) => Ok(token.claims),
+        Err(_) => Err(HttpError::bad_request(ErrorMessage::InvalidInvite.to_string()))
+    }
+}
+
+pub fn create_challenge_token(
+    user_id: &str,
+    purpose: &str,
+    secret: &[u8],
+    expires_in_seconds: i64,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    if user_id.is_empty() {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidSubject.into());
+    }
+
+    let now = Utc::now();
+    let claims = ChallengeClaims {
+        sub: user_id.to_string(),
+        purpose: purpose.to_string(),
+        iat: now.timestamp() as usize,
+        exp: (now + Duration::seconds(expires_in_seconds)).timestamp() as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret)
+    )
+}
+
+pub fn decode_challenge_token<T: Into<String>>(
+    token: T,
+    purpose: &str,
+    secret: &[u8]
+) -> Result<ChallengeClaims, HttpError> {
+    let decode = decode::<ChallengeClaims>(
+        &token.into(),
+        &DecodingKey::from_secret(secret),
+        &Validation::new(Algorithm::HS256),
+    );
+
+    match decode {
+        Ok(token) if token.claims.purpose == purpose => Ok(token.claims),
+        _ => Err(HttpError::unauthorized(ErrorMessage::InvalidToken.to_string()))
+    }
+}
+
+// Opaque tokens (email verification, password resets, refresh tokens) are
+// handed out in clear and only their SHA-256 digest is stored.
+pub fn generate_opaque_token() -> (String, String) {
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let token_hash = hash_opaque_token(&token);
+    (token, token_hash)
+}
+
+pub fn hash_opaque_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    #[test]
+    fn token_round_trips_claims() {
+        let token = create_token("user-1", "driver", "session-1", SECRET, 900).unwrap();
+        let claims = decode_token(token, SECRET).unwrap();
+
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.role, "driver");
+        assert_eq!(claims.sid, "session-1");
+    }
+
+    #[test]
+    fn expiry_is_measured_in_seconds() {
+        let token = create_token("user-1", "driver", "session-1", SECRET, 900).unwrap();
+        let claims = decode_token(token, SECRET).unwrap();
+
+        assert_eq!(claims.exp - claims.iat, 900);
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        // Beyond the decoder's default 60 second leeway
+        let token = create_token("user-1", "driver", "session-1", SECRET, -120).unwrap();
+        let err = decode_token(token, SECRET).unwrap_err();
+
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn token_signed_with_another_secret_is_rejected() {
+        let token = create_token("user-1", "driver", "session-1", b"other-secret", 900).unwrap();
+
+        assert!(decode_token(token, SECRET).is_err());
+    }
+
+    #[test]
+    fn tampered_token_is_rejected() {
+        let token = create_token("user-1", "driver", "session-1", SECRET, 900).unwrap();
+        let mut parts: Vec<String> = token.split('.').map(String::from).collect();
+
+        // Swap in a payload that claims the admin role, keeping the old signature
+        let forged = create_token("user-1", "admin", "session-1", b"attacker", 900).unwrap();
+        parts[1] = forged.split('.').nth(1).unwrap().to_string();
+
+        assert!(decode_token(parts.join("."), SECRET).is_err());
+    }
+
+    #[test]
+    fn empty_subject_is_refused() {
+        assert!(create_token("", "driver", "session-1", SECRET, 900).is_err());
+    }
+
+    #[test]
+    fn invite_token_round_trips_and_rejects_wrong_secret() {
+        let token = create_invite_token("invite-1", "new@example.com", "manager", SECRET, 900).unwrap();
+        let claims = decode_invite_token(token.clone(), SECRET).unwrap();
+
+        assert_eq!(claims.sub, "invite-1");
+        assert_eq!(claims.email, "new@example.com");
+        assert_eq!(claims.role, "manager");
+        assert!(decode_invite_token(token, b"other-secret").is_err());
+    }
+
+    #[test]
+    fn access_token_is_not_an_invite_token() {
+        let token = create_token("user-1", "admin", "session-1", SECRET, 900).unwrap();
+
+        assert!(decode_invite_token(token, SECRET).is_err());
+    }
+
+    #[test]
+    fn challenge_token_is_bound_to_its_purpose() {
+        let token = create_challenge_token("user-1", TWO_FACTOR_LOGIN, SECRET, 300).unwrap();
+
+        assert_eq!(decode_challenge_token(token.clone(), TWO_FACTOR_LOGIN, SECRET).unwrap().sub, "user-1");
+        assert!(decode_challenge_token(token.clone(), TWO_FACTOR_SETUP, SECRET).is_err());
+        assert!(decode_token(token, SECRET).is_err());
+    }
+
+    #[test]
+    fn access_token_is_not_a_challenge_token() {
+        let token = create_token("user-1", "admin", "session-1", SECRET, 900).unwrap();
+
+        assert!(decode_challenge_token(token, TWO_FACTOR_LOGIN, SECRET).is_err());
+    }
+
+    #[test]
+    fn opaque_token_hash_is_stable_and_not_the_token() {
+        let (token, token_hash) = generate_opaque_token();
+
+        assert_eq!(hash_opaque_token(&token), token_hash);
+        assert_ne!(token, token_hash);
+        assert_eq!(token_hash.len(), 64);
+    }
+}
@@ -0,0 +1,94 @@
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::token;
+
+const ISSUER: &str = "DairyX";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// A fresh 160-bit shared secret, base32 encoded for authenticator apps.
+pub fn generate_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+// Standard RFC 6238 settings (SHA-1, 6 digits, 30 s steps) so every common
+// authenticator app works; one step of clock skew is tolerated.
+fn totp(secret: &str, email: &str) -> Option<TOTP> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, Some(ISSUER.to_string()), email.to_string()).ok()
+}
+
+pub fn otpauth_uri(secret: &str, email: &str) -> Option<String> {
+    totp(secret, email).map(|totp| totp.get_url())
+}
+
+pub fn verify_code(secret: &str, email: &str, code: &str) -> bool {
+    totp(secret, email)
+        .and_then(|totp| totp.check_current(code.trim()).ok())
+        .unwrap_or(false)
+}
+
+// One-time recovery codes as (code shown to the user, digest to store).
+pub fn generate_recovery_codes() -> Vec<(String, String)> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = uuid::Uuid::new_v4().simple().to_string();
+            let code = format!("{}-{}", &raw[..5], &raw[5..10]);
+            let code_hash = hash_recovery_code(&code);
+            (code, code_hash)
+        })
+        .collect()
+}
+
+// Case and dashes don't matter when a recovery code is typed back in.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    token::hash_opaque_token(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_code_verifies_and_wrong_code_does_not() {
+        let secret = generate_secret();
+        let code = totp(&secret, "admin@example.com").unwrap().generate_current().unwrap();
+
+        assert!(verify_code(&secret, "admin@example.com", &code));
+        assert!(!verify_code(&secret, "admin@example.com", "0000000"));
+    }
+
+    #[test]
+    fn otpauth_uri_names_issuer_and_account() {
+        let uri = otpauth_uri(&generate_secret(), "admin@example.com").unwrap();
+        assert!(uri.starts_with("otpauth://totp/DairyX:admin%40example.com?"));
+        assert!(uri.contains("issuer=DairyX"));
+    }
+
+    #[test]
+    fn invalid_secret_never_verifies() {
+        assert!(otpauth_uri("not base32!", "admin@example.com").is_none());
+        assert!(!verify_code("not base32!", "admin@example.com", "123456"));
+    }
+
+    #[test]
+    fn recovery_codes_are_unique_and_hash_loosely() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let mut plain: Vec<&String> = codes.iter().map(|(code, _)| code).collect();
+        plain.sort();
+        plain.dedup();
+        assert_eq!(plain.len(), RECOVERY_CODE_COUNT);
+
+        let (code, code_hash) = &codes[0];
+        assert_eq!(&hash_recovery_code(&code.to_uppercase().replace('-', "")), code_hash);
+    }
+}